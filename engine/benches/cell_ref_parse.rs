@@ -0,0 +1,21 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use quantum_engine::CellRef;
+
+fn bench_cell_ref_parse(c: &mut Criterion) {
+    // 1M references spread over columns A..ZZ and many row lengths
+    let references: Vec<String> = (0..1_000_000u32)
+        .map(|i| CellRef::new(i % 100_000 + 1, i % 702 + 1).to_excel())
+        .collect();
+    
+    c.bench_function("cell_ref_parse_1m", |b| {
+        b.iter(|| {
+            for reference in &references {
+                let cell = CellRef::parse(reference).unwrap();
+                criterion::black_box(cell);
+            }
+        })
+    });
+}
+
+criterion_group!(benches, bench_cell_ref_parse);
+criterion_main!(benches);
@@ -0,0 +1,23 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use quantum_engine::compute::calculate_stats;
+use quantum_engine::storage::QuantumColumn;
+
+fn bench_column_stats(c: &mut Criterion) {
+    let mut column = QuantumColumn::new("Values");
+    for i in 0..100_000 {
+        column.push(i as f64 * 0.5);
+    }
+    assert_eq!(column.stats_snapshot(), calculate_stats(&column.data()));
+
+    let mut group = c.benchmark_group("column_stats_100k");
+    group.bench_function("stats_snapshot", |b| {
+        b.iter(|| criterion::black_box(column.stats_snapshot()))
+    });
+    group.bench_function("calculate_stats_rescan", |b| {
+        b.iter(|| criterion::black_box(calculate_stats(&column.data())))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_column_stats);
+criterion_main!(benches);
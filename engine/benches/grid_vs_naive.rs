@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use quantum_engine::QuantumGrid;
+
+const ROWS: u32 = 10_000;
+const COLS: u32 = 10; // 100k cells
+
+/// (row, col), both 1-based
+type Cell = (u32, u32);
+
+/// Baseline: every cell in one hash map, formulas as `target = left + right`
+#[derive(Default)]
+struct NaiveSheet {
+    cells: HashMap<Cell, f64>,
+    formulas: Vec<(Cell, Cell, Cell)>,
+}
+
+impl NaiveSheet {
+    fn set(&mut self, row: u32, col: u32, value: f64) {
+        self.cells.insert((row, col), value);
+    }
+
+    fn sum_column(&self, col: u32) -> f64 {
+        (1..=ROWS).filter_map(|row| self.cells.get(&(row, col))).sum()
+    }
+
+    fn recalculate(&mut self) {
+        for &(target, left, right) in &self.formulas {
+            let value = self.cells.get(&left).unwrap_or(&0.0) + self.cells.get(&right).unwrap_or(&0.0);
+            self.cells.insert(target, value);
+        }
+    }
+}
+
+fn column_name(col: u32) -> char {
+    (b'A' + col as u8 - 1) as char
+}
+
+fn filled_grid() -> QuantumGrid {
+    let mut grid = QuantumGrid::new();
+    for col in 1..=COLS {
+        for row in 1..=ROWS {
+            grid.set_cell(&format!("{}{}", column_name(col), row), (row * col) as f64).unwrap();
+        }
+    }
+    grid
+}
+
+fn filled_naive() -> NaiveSheet {
+    let mut sheet = NaiveSheet::default();
+    for col in 1..=COLS {
+        for row in 1..=ROWS {
+            sheet.set(row, col, (row * col) as f64);
+        }
+    }
+    sheet
+}
+
+fn bench_grid_vs_naive(c: &mut Criterion) {
+    let cells = (ROWS * COLS) as u64;
+    let addresses: Vec<String> = (1..=COLS)
+        .flat_map(|col| (1..=ROWS).map(move |row| format!("{}{}", column_name(col), row)))
+        .collect();
+
+    let mut group = c.benchmark_group("bulk_set_cell");
+    group.throughput(Throughput::Elements(cells));
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::new("quantum_grid", cells), |b| {
+        b.iter(|| {
+            let mut grid = QuantumGrid::new();
+            for (i, address) in addresses.iter().enumerate() {
+                grid.set_cell(address, i as f64).unwrap();
+            }
+            criterion::black_box(grid);
+        })
+    });
+    group.bench_function(BenchmarkId::new("naive_hashmap", cells), |b| {
+        b.iter(|| {
+            let mut sheet = NaiveSheet::default();
+            for col in 1..=COLS {
+                for row in 1..=ROWS {
+                    sheet.set(row, col, (row * col) as f64);
+                }
+            }
+            criterion::black_box(sheet);
+        })
+    });
+    group.finish();
+
+    let grid = filled_grid();
+    let sheet = filled_naive();
+    let mut group = c.benchmark_group("sum_range");
+    group.throughput(Throughput::Elements(ROWS as u64));
+    group.bench_function(BenchmarkId::new("quantum_grid", ROWS), |b| {
+        b.iter(|| criterion::black_box(grid.sum_range(&format!("A1:A{}", ROWS)).unwrap()))
+    });
+    group.bench_function(BenchmarkId::new("naive_hashmap", ROWS), |b| {
+        b.iter(|| criterion::black_box(sheet.sum_column(1)))
+    });
+    group.finish();
+
+    // One formula per row adding the first two columns
+    let mut grid = filled_grid();
+    let mut sheet = filled_naive();
+    let formula_col = COLS + 1;
+    for row in 1..=ROWS {
+        let target = format!("{}{}", column_name(formula_col), row);
+        grid.set_formula(&target, &format!("=A{}+B{}", row, row)).unwrap();
+        sheet.formulas.push(((row, formula_col), (row, 1), (row, 2)));
+    }
+    let mut group = c.benchmark_group("full_recalc");
+    group.throughput(Throughput::Elements(ROWS as u64));
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::new("quantum_grid", ROWS), |b| {
+        b.iter(|| criterion::black_box(grid.recalculate_all()))
+    });
+    group.bench_function(BenchmarkId::new("naive_hashmap", ROWS), |b| {
+        b.iter(|| {
+            sheet.recalculate();
+            criterion::black_box(sheet.cells.len())
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_grid_vs_naive);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use quantum_engine::storage::{QuantumColumn, StorageMode};
+
+fn bench_sparse_column(c: &mut Criterion) {
+    // 1000 values spread over a million rows
+    let rows: Vec<usize> = (0..1000).map(|i| i * 1000).collect();
+    
+    c.bench_function("dense_column_far_apart_writes", |b| {
+        b.iter(|| {
+            let mut column = QuantumColumn::new("Dense");
+            for &row in &rows {
+                column.set(row, row as f64);
+            }
+            criterion::black_box(column.sum());
+        })
+    });
+    
+    c.bench_function("sparse_column_far_apart_writes", |b| {
+        b.iter(|| {
+            let mut column = QuantumColumn::new_sparse("Sparse");
+            for &row in &rows {
+                column.set(row, row as f64);
+            }
+            criterion::black_box(column.sum());
+        })
+    });
+    
+    let mut dense = QuantumColumn::new("Dense");
+    let mut sparse = QuantumColumn::new_sparse("Sparse");
+    for &row in &rows {
+        dense.set(row, 1.0);
+        sparse.set(row, 1.0);
+    }
+    assert_eq!(dense.storage_mode(), StorageMode::Dense);
+    println!(
+        "memory: dense {} bytes, sparse {} bytes",
+        dense.memory_used(),
+        sparse.memory_used()
+    );
+    
+    c.bench_function("sparse_column_get", |b| {
+        b.iter(|| {
+            for &row in &rows {
+                criterion::black_box(sparse.get(row + 1));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, bench_sparse_column);
+criterion_main!(benches);
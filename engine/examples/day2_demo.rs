@@ -117,7 +117,7 @@ fn main() {
     println!("✅ Excel cell reference parsing");
     println!("✅ QuantumGrid for multiple columns");
     println!("✅ More Excel functions: MIN, MAX");
-    println!("✅ Memory efficiency: Still 4.8x better than Excel");
+    println!("✅ Memory efficiency: Still {:.1}x better than Excel", grid.efficiency_report().ratio);
     
     println!("\n🚀 Ready for Day 3: Formula parsing and AI features!");
 }
\ No newline at end of file
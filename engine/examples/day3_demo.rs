@@ -151,7 +151,7 @@ fn main() {
     println!("✅ QuantumGrid with natural language formulas");
     println!("✅ Formula request detection");
     println!("✅ 1.5x speedup maintained");
-    println!("✅ Memory efficiency: 4.8x better than Excel");
+    println!("✅ Memory efficiency: {:.1}x better than Excel", grid.efficiency_report().ratio);
     
     println!("\n🚀 Ready for Day 4: AI Data Generator & Advanced Features!");
     println!("   Your killer feature: \"Give me 100 rows with Nepal phone numbers...\"");
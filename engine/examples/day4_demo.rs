@@ -162,7 +162,7 @@ fn main() {
     println!("\n🚀 QUANTUM SHEETS IS NOW AI-POWERED!");
     println!("   Try: \"Generate 100 customers with Nepal phones\"");
     println!("   Try: \"add revenue and expenses\"");
-    println!("   Memory: Still 4.8x better than Excel");
+    println!("   Memory: Still {:.1}x better than Excel", grid.efficiency_report().ratio);
     println!("   Speed: Still 1.5x faster than Excel");
     
     println!("\n📁 Generated files:");
@@ -2,23 +2,164 @@
 //! "Give me 100 rows with Nepal phone numbers, Indian cities, random gender"
 
 use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
 use serde::{Serialize, Deserialize};
+use crate::export::Exporter;
 use std::fs;
 use std::io::Write;
 
+/// Nepal mobile prefixes: NTC (984-986, 974-976), Ncell (980-982), Smart Cell (961, 962, 988)
+pub const NEPAL_MOBILE_PREFIXES: [&str; 12] = [
+    "984", "985", "986", "974", "975", "976",
+    "980", "981", "982", "961", "962", "988",
+];
+
+/// Check a Nepal mobile number: exactly 10 digits starting with a known prefix
+pub fn validate_nepal_phone(phone: &str) -> bool {
+    phone.len() == 10
+        && phone.bytes().all(|b| b.is_ascii_digit())
+        && NEPAL_MOBILE_PREFIXES.iter().any(|prefix| phone.starts_with(prefix))
+}
+
+/// US and UK cities used with `Locale::USCity` / `Locale::UKCity`
+const US_CITIES: [&str; 10] = [
+    "New York", "Los Angeles", "Chicago", "Houston", "Phoenix",
+    "Philadelphia", "San Antonio", "San Diego", "Dallas", "Seattle",
+];
+const UK_CITIES: [&str; 10] = [
+    "London", "Birmingham", "Manchester", "Glasgow", "Liverpool",
+    "Leeds", "Edinburgh", "Bristol", "Cardiff", "Belfast",
+];
+
+/// Phone number format or city list used for generated records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    NepalPhone,  // 984XXXXXXX
+    USPhone,     // NXX-NXX-XXXX
+    UKPhone,     // 07XXX XXXXXX
+    IndianCity,
+    USCity,
+    UKCity,
+}
+
+impl Locale {
+    /// Whether this locale picks the phone format (otherwise it picks the city list)
+    pub fn is_phone(&self) -> bool {
+        matches!(self, Locale::NepalPhone | Locale::USPhone | Locale::UKPhone)
+    }
+}
+
+/// First names and surnames combined by `generate_name`
+const FIRST_NAMES: [&str; 10] = [
+    "Aarav", "Anita", "Bikash", "Priya", "Rohan",
+    "Sita", "Arjun", "Kavya", "Suman", "Meera",
+];
+const SURNAMES: [&str; 8] = [
+    "Sharma", "Shrestha", "Patel", "Gurung", "Singh", "Thapa", "Rao", "Karki",
+];
+
+/// A column the generator can fill, in the order a request names them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Id,
+    Name,
+    Phone,
+    City,
+    Gender,
+    Date,
+}
+
+impl Field {
+    /// Column header, matching the CSV export
+    pub fn header(&self) -> &'static str {
+        match self {
+            Field::Id => "ID",
+            Field::Name => "Name",
+            Field::Phone => "Phone",
+            Field::City => "City",
+            Field::Gender => "Gender",
+            Field::Date => "Date",
+        }
+    }
+    
+    fn from_word(word: &str) -> Option<Field> {
+        match word {
+            "id" | "ids" => Some(Field::Id),
+            "city" | "cities" => Some(Field::City),
+            _ if word.starts_with("name") => Some(Field::Name),
+            _ if word.starts_with("phone") => Some(Field::Phone),
+            _ if word.starts_with("gender") => Some(Field::Gender),
+            "date" | "dates" | "birthday" | "birthdays" => Some(Field::Date),
+            _ => None,
+        }
+    }
+}
+
+/// The fields a request names, in the order it first names them:
+/// "phone, then name, then city" gives `[Phone, Name, City]`
+pub fn parse_fields(request: &str) -> Vec<Field> {
+    let mut fields = Vec::new();
+    for word in request.to_lowercase().split(|c: char| !c.is_alphanumeric()) {
+        if let Some(field) = Field::from_word(word) {
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+    }
+    fields
+}
+
+/// Values drawn in proportion to their weights
+struct Weighted {
+    values: Vec<String>,
+    index: WeightedIndex<f64>,
+}
+
+impl Weighted {
+    fn new(weights: &[(&str, f64)]) -> Result<Self, String> {
+        let index = WeightedIndex::new(weights.iter().map(|(_, weight)| *weight))
+            .map_err(|e| format!("Invalid weights: {}", e))?;
+        let values = weights.iter().map(|(value, _)| value.to_string()).collect();
+        Ok(Self { values, index })
+    }
+    
+    fn sample(&self, rng: &mut impl Rng) -> String {
+        self.values[self.index.sample(rng)].clone()
+    }
+}
+
+/// How record ids are assigned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdStyle {
+    /// 1, 2, 3, ... (or from the start passed to `generate_records_from`)
+    #[default]
+    Sequential,
+    /// A random v4 UUID per record, so batches generated apart never collide
+    Uuid,
+}
+
 /// Represents a generated data record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataRecord {
     pub id: u32,
-    pub phone: String,     // Nepal phone: 98XXXXXXXX
+    pub phone: String,     // Nepal phone: 10 digits, e.g. 984XXXXXXX
     pub city: String,      // Indian city
     pub gender: String,    // "Male", "Female", "Other"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>, // Used as the record's id under `IdStyle::Uuid`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>, // ISO-8601 date, when the request asks for one
 }
 
 /// AI Data Generator - creates realistic test data
 pub struct AIDataGenerator {
     rng: rand::rngs::ThreadRng,
     indian_cities: Vec<&'static str>,
+    phone_locale: Locale,
+    city_locale: Locale,
+    id_style: IdStyle,
+    city_weights: Option<Weighted>,
+    gender_weights: Option<Weighted>,
 }
 
 impl AIDataGenerator {
@@ -32,16 +173,84 @@ impl AIDataGenerator {
                 "Lucknow", "Kanpur", "Nagpur", "Indore", "Thane",
                 "Bhopal", "Visakhapatnam", "Pimpri-Chinchwad", "Patna", "Vadodara",
             ],
+            phone_locale: Locale::NepalPhone,
+            city_locale: Locale::IndianCity,
+            id_style: IdStyle::Sequential,
+            city_weights: None,
+            gender_weights: None,
         }
     }
     
-    /// Generate a Nepal phone number (98XXXXXXXX format)
+    /// Use a phone format or city list (Nepal phones and Indian cities by default)
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.set_locale(locale);
+        self
+    }
+    
+    /// Use sequential ids (the default) or UUIDs
+    pub fn with_id_style(mut self, style: IdStyle) -> Self {
+        self.id_style = style;
+        self
+    }
+    
+    /// Draw cities in proportion to their weights, e.g. `[("Mumbai", 5.0), ("Thane", 1.0)]`,
+    /// instead of uniformly from the locale's list. Errors on negative or all-zero weights.
+    pub fn with_city_weights(mut self, weights: &[(&str, f64)]) -> Result<Self, String> {
+        self.city_weights = Some(Weighted::new(weights)?);
+        Ok(self)
+    }
+    
+    /// Draw genders in proportion to their weights instead of a uniform split
+    pub fn with_gender_weights(mut self, weights: &[(&str, f64)]) -> Result<Self, String> {
+        self.gender_weights = Some(Weighted::new(weights)?);
+        Ok(self)
+    }
+    
+    fn set_locale(&mut self, locale: Locale) {
+        if locale.is_phone() {
+            self.phone_locale = locale;
+        } else {
+            self.city_locale = locale;
+        }
+    }
+    
+    /// Generate a phone number in the current phone locale
+    pub fn generate_phone(&mut self) -> String {
+        match self.phone_locale {
+            Locale::USPhone => {
+                // Area code and exchange can't start with 0 or 1
+                let area = self.rng.gen_range(200..1000);
+                let exchange = self.rng.gen_range(200..1000);
+                let line = self.rng.gen_range(0..10_000);
+                format!("{}-{}-{:04}", area, exchange, line)
+            }
+            Locale::UKPhone => {
+                let block = self.rng.gen_range(0..1000);
+                let line = self.rng.gen_range(0..1_000_000);
+                format!("07{:03} {:06}", block, line)
+            }
+            _ => self.generate_nepal_phone(),
+        }
+    }
+    
+    /// Generate a city in the current city locale, or from the city weights when set
+    pub fn generate_city(&mut self) -> String {
+        if let Some(weights) = &self.city_weights {
+            return weights.sample(&mut self.rng);
+        }
+        match self.city_locale {
+            Locale::USCity => US_CITIES[self.rng.gen_range(0..US_CITIES.len())].to_string(),
+            Locale::UKCity => UK_CITIES[self.rng.gen_range(0..UK_CITIES.len())].to_string(),
+            _ => self.generate_indian_city(),
+        }
+    }
+    
+    /// Generate a Nepal phone number (3-digit operator prefix + 7 digits)
     pub fn generate_nepal_phone(&mut self) -> String {
-        // Nepal mobile numbers start with 98, 97, 96
-        let prefix = self.rng.gen_range(98..=99); // 98 or 99 for now
-        let suffix = self.rng.gen_range(10000000..=99999999); // 8 digits
+        let prefix = NEPAL_MOBILE_PREFIXES[self.rng.gen_range(0..NEPAL_MOBILE_PREFIXES.len())];
+        let suffix = self.rng.gen_range(0..10_000_000); // Zero-padded to 7 digits
         
-        format!("{}{}", prefix, suffix)
+        format!("{}{:07}", prefix, suffix)
     }
     
     /// Generate a random Indian city
@@ -52,28 +261,76 @@ impl AIDataGenerator {
     
     /// Generate random gender
     pub fn generate_gender(&mut self) -> String {
+        if let Some(weights) = &self.gender_weights {
+            return weights.sample(&mut self.rng);
+        }
         let options = ["Male", "Female", "Other"];
         let index = self.rng.gen_range(0..options.len());
         options[index].to_string()
     }
     
+    /// Generate a full name, e.g. "Priya Shrestha"
+    pub fn generate_name(&mut self) -> String {
+        let first = FIRST_NAMES[self.rng.gen_range(0..FIRST_NAMES.len())];
+        let last = SURNAMES[self.rng.gen_range(0..SURNAMES.len())];
+        format!("{} {}", first, last)
+    }
+    
+    /// Generate a random version 4 UUID, e.g. "3f2b8c1e-9d4a-4f6b-8e2a-1c5d7e9f0a3b"
+    pub fn generate_uuid(&mut self) -> String {
+        let mut bytes: [u8; 16] = self.rng.gen();
+        bytes[6] = (bytes[6] & 0x0f) | 0x40; // Version 4
+        bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+    }
+    
     /// Generate a single data record
     pub fn generate_record(&mut self, id: u32) -> DataRecord {
         DataRecord {
             id,
-            phone: self.generate_nepal_phone(),
-            city: self.generate_indian_city(),
+            phone: self.generate_phone(),
+            city: self.generate_city(),
             gender: self.generate_gender(),
+            uuid: match self.id_style {
+                IdStyle::Sequential => None,
+                IdStyle::Uuid => Some(self.generate_uuid()),
+            },
+            date: None,
         }
     }
     
+    /// Generate an ISO-8601 date (YYYY-MM-DD) uniformly between `start` and `end`, inclusive
+    #[cfg(feature = "dates")]
+    pub fn generate_date(&mut self, start: chrono::NaiveDate, end: chrono::NaiveDate) -> Result<String, String> {
+        if start > end {
+            return Err(format!("Date range is inverted: {} is after {}", start, end));
+        }
+        
+        let days = (end - start).num_days();
+        let date = start + chrono::Duration::days(self.rng.gen_range(0..=days));
+        Ok(date.format("%Y-%m-%d").to_string())
+    }
+    
     /// Generate multiple records
     pub fn generate_records(&mut self, count: u32) -> Vec<DataRecord> {
-        (1..=count)
+        self.generate_records_from(1, count)
+    }
+    
+    /// Generate `count` records with ids from `start_id`, so a batch appended to
+    /// earlier data continues its numbering. Stops early rather than wrap past `u32::MAX`.
+    pub fn generate_records_from(&mut self, start_id: u32, count: u32) -> Vec<DataRecord> {
+        (0..count)
+            .map_while(|offset| start_id.checked_add(offset))
             .map(|id| self.generate_record(id))
             .collect()
     }
     
+    /// Lazily generate `count` records, one at a time, without collecting them
+    pub fn generate_iter(&mut self, count: u32) -> impl Iterator<Item = DataRecord> + '_ {
+        (1..=count).map(move |id| self.generate_record(id))
+    }
+    
     /// Parse natural language request and generate data
     /// Example: "100 rows with Nepal phone numbers, Indian cities, random gender"
     pub fn generate_from_request(&mut self, request: &str) -> Result<Vec<DataRecord>, String> {
@@ -95,33 +352,127 @@ impl AIDataGenerator {
         let has_city = request.to_lowercase().contains("city") || 
                        request.to_lowercase().contains("india");
         let has_gender = request.to_lowercase().contains("gender");
+        let has_birthday = request.to_lowercase().contains("birthday");
+        
+        // Locale hints, e.g. "US phone numbers" or "UK cities"
+        let lower = format!(" {}", request.to_lowercase());
+        for (hints, locale) in [
+            (&[" us phone", " usa phone", " american phone"][..], Locale::USPhone),
+            (&[" uk phone", " british phone"][..], Locale::UKPhone),
+            (&[" nepal phone", " nepali phone"][..], Locale::NepalPhone),
+            (&[" us cit", " usa cit", " american cit"][..], Locale::USCity),
+            (&[" uk cit", " british cit"][..], Locale::UKCity),
+            (&[" indian cit", " india cit"][..], Locale::IndianCity),
+        ] {
+            if hints.iter().any(|hint| lower.contains(hint)) {
+                self.set_locale(locale);
+            }
+        }
+        // Whole words only, so "update" or "candidate" don't ask for dates
+        let fields = parse_fields(request);
+        let has_date = fields.contains(&Field::Date);
         
-        if !has_phone && !has_city && !has_gender {
-            return Err("Request should mention phone, city, or gender".to_string());
+        if !has_phone && !has_city && !has_gender && !has_date && fields.is_empty() {
+            return Err("Request should mention id, name, phone, city, gender, or date".to_string());
         }
         
-        Ok(self.generate_records(count))
+        let mut records = self.generate_records(count);
+        if has_date {
+            self.add_dates(&mut records, has_birthday)?;
+        }
+        Ok(records)
+    }
+    
+    /// Generate only the fields a request names, one row of values per record,
+    /// with the values in each row in the order the request names the fields
+    pub fn generate_table(&mut self, request: &str) -> Result<(Vec<Field>, Vec<Vec<String>>), String> {
+        let fields = parse_fields(request);
+        if fields.is_empty() {
+            return Err("Request should mention id, name, phone, city, gender, or date".to_string());
+        }
+        
+        let records = self.generate_from_request(request)?;
+        let rows = records.into_iter()
+            .map(|record| fields.iter().map(|field| match field {
+                Field::Id => record.uuid.clone().unwrap_or_else(|| record.id.to_string()),
+                Field::Name => self.generate_name(),
+                Field::Phone => record.phone.clone(),
+                Field::City => record.city.clone(),
+                Field::Gender => record.gender.clone(),
+                Field::Date => record.date.clone().unwrap_or_default(),
+            }).collect())
+            .collect();
+        Ok((fields, rows))
+    }
+    
+    /// Fill in record dates: birthdays in 1950-2005, other dates in 2020-2024
+    #[cfg(feature = "dates")]
+    fn add_dates(&mut self, records: &mut [DataRecord], birthday: bool) -> Result<(), String> {
+        use chrono::NaiveDate;
+        
+        let (start, end) = if birthday {
+            (NaiveDate::from_ymd_opt(1950, 1, 1), NaiveDate::from_ymd_opt(2005, 12, 31))
+        } else {
+            (NaiveDate::from_ymd_opt(2020, 1, 1), NaiveDate::from_ymd_opt(2024, 12, 31))
+        };
+        let (start, end) = start.zip(end).expect("valid calendar dates");
+        
+        for record in records {
+            record.date = Some(self.generate_date(start, end)?);
+        }
+        Ok(())
+    }
+    
+    #[cfg(not(feature = "dates"))]
+    fn add_dates(&mut self, _records: &mut [DataRecord], _birthday: bool) -> Result<(), String> {
+        Err("Date generation requires the `dates` feature".to_string())
     }
     
     /// Export records to CSV file
     pub fn export_csv(&self, records: &[DataRecord], filename: &str) -> Result<(), String> {
+        self.export_csv_with_delimiter(records, filename, ',')
+    }
+    
+    /// Export records to a CSV file separated by `delimiter` (e.g. `;` or `\t`)
+    pub fn export_csv_with_delimiter(
+        &self,
+        records: &[DataRecord],
+        filename: &str,
+        delimiter: char,
+    ) -> Result<(), String> {
         let mut file = fs::File::create(filename)
             .map_err(|e| format!("Failed to create file: {}", e))?;
         
-        // Write header
-        writeln!(file, "ID,Phone,City,Gender")
+        // Write header (the date column only when records have dates)
+        let has_dates = records.iter().any(|record| record.date.is_some());
+        let header: &[&str] = if has_dates { &CSV_HEADER_WITH_DATE } else { &CSV_HEADER };
+        writeln!(file, "{}", Exporter::csv_line(header, delimiter))
             .map_err(|e| format!("Failed to write header: {}", e))?;
         
         // Write records
         for record in records {
-            writeln!(file, "{},{},{},{}", 
-                     record.id, record.phone, record.city, record.gender)
-                .map_err(|e| format!("Failed to write record: {}", e))?;
+            write_csv_record(&mut file, record, has_dates, delimiter)?;
         }
         
         Ok(())
     }
     
+    /// Generate `count` records straight into a CSV file, writing each as it's made
+    /// so memory stays flat however many rows are requested
+    pub fn export_csv_streaming(&mut self, count: u32, filename: &str) -> Result<(), String> {
+        let file = fs::File::create(filename)
+            .map_err(|e| format!("Failed to create file: {}", e))?;
+        let mut writer = std::io::BufWriter::new(file);
+        
+        writeln!(writer, "{}", Exporter::csv_line(&CSV_HEADER, ','))
+            .map_err(|e| format!("Failed to write header: {}", e))?;
+        for record in self.generate_iter(count) {
+            write_csv_record(&mut writer, &record, false, ',')?;
+        }
+        
+        writer.flush().map_err(|e| format!("Failed to write record: {}", e))
+    }
+    
     /// Export records to JSON file
     pub fn export_json(&self, records: &[DataRecord], filename: &str) -> Result<(), String> {
         let json = serde_json::to_string_pretty(records)
@@ -152,8 +503,199 @@ impl AIDataGenerator {
         println!("└─────┴──────────────┴────────────────────┴────────┘");
     }
 }
+const CSV_HEADER: [&str; 4] = ["ID", "Phone", "City", "Gender"];
+const CSV_HEADER_WITH_DATE: [&str; 5] = ["ID", "Phone", "City", "Gender", "Date"];
+
+/// Write one CSV line for `record`, with a trailing date field when `has_dates`
+fn write_csv_record(
+    out: &mut impl Write,
+    record: &DataRecord,
+    has_dates: bool,
+    delimiter: char,
+) -> Result<(), String> {
+    let id = record.id.to_string();
+    let mut fields = vec![record.uuid.as_deref().unwrap_or(&id), &record.phone, &record.city, &record.gender];
+    if has_dates {
+        fields.push(record.date.as_deref().unwrap_or(""));
+    }
+    writeln!(out, "{}", Exporter::csv_line(&fields, delimiter))
+        .map_err(|e| format!("Failed to write record: {}", e))
+}
+
 impl std::fmt::Display for DataRecord {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)  // Use Debug representation
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_phones_are_valid() {
+        let mut generator = AIDataGenerator::new();
+        for _ in 0..1000 {
+            let phone = generator.generate_nepal_phone();
+            assert_eq!(phone.len(), 10, "{}", phone);
+            assert!(validate_nepal_phone(&phone), "{}", phone);
+        }
+    }
+
+    #[test]
+    fn test_validate_nepal_phone() {
+        assert!(validate_nepal_phone("9841234567"));
+        assert!(validate_nepal_phone("9610000000"));
+        assert!(!validate_nepal_phone("984123456"));   // 9 digits
+        assert!(!validate_nepal_phone("98412345678")); // 11 digits
+        assert!(!validate_nepal_phone("9991234567"));  // Unknown prefix
+        assert!(!validate_nepal_phone("984123456a"));
+    }
+
+    #[cfg(feature = "dates")]
+    #[test]
+    fn test_generated_dates_stay_in_range() {
+        use chrono::NaiveDate;
+
+        let mut generator = AIDataGenerator::new();
+        let start = NaiveDate::from_ymd_opt(2024, 2, 27).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 3, 2).unwrap();
+        for _ in 0..500 {
+            let date = generator.generate_date(start, end).unwrap();
+            let parsed = NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap();
+            assert!(parsed >= start && parsed <= end, "{}", date);
+        }
+        assert!(generator.generate_date(end, start).is_err());
+
+        let records = generator.generate_from_request("5 rows with birthday").unwrap();
+        for record in &records {
+            let year: i32 = record.date.as_ref().unwrap()[..4].parse().unwrap();
+            assert!((1950..=2005).contains(&year));
+        }
+    }
+
+    #[test]
+    fn test_us_and_uk_locales() {
+        let us_phone = regex::Regex::new(r"^[2-9][0-9]{2}-[2-9][0-9]{2}-[0-9]{4}$").unwrap();
+        let mut generator = AIDataGenerator::new().with_locale(Locale::USPhone);
+        for _ in 0..200 {
+            let phone = generator.generate_phone();
+            assert!(us_phone.is_match(&phone), "{}", phone);
+        }
+
+        let uk_phone = regex::Regex::new(r"^07[0-9]{3} [0-9]{6}$").unwrap();
+        let mut generator = AIDataGenerator::new();
+        let records = generator.generate_from_request("3 rows with UK phone numbers and US cities").unwrap();
+        for record in &records {
+            assert!(uk_phone.is_match(&record.phone), "{}", record.phone);
+            assert!(US_CITIES.contains(&record.city.as_str()), "{}", record.city);
+        }
+
+        // Defaults stay Nepal/India
+        let record = AIDataGenerator::new().generate_record(1);
+        assert!(validate_nepal_phone(&record.phone));
+    }
+
+    #[test]
+    fn test_fields_follow_request_order() {
+        assert_eq!(parse_fields("phone, then name, then city"), [Field::Phone, Field::Name, Field::City]);
+        assert_eq!(parse_fields("cities and phone numbers, then cities again"), [Field::City, Field::Phone]);
+        assert!(parse_fields("a few rows please").is_empty());
+        assert!(!parse_fields("update the candidate list, dated today").contains(&Field::Date));
+
+        // "update" isn't a request for dates, so this works without the `dates` feature
+        let records = AIDataGenerator::new().generate_from_request("generate 3 names with update").unwrap();
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|record| record.date.is_none()));
+
+        let mut generator = AIDataGenerator::new();
+        let (fields, rows) = generator.generate_table("4 rows: phone, then name, then city").unwrap();
+        assert_eq!(fields, [Field::Phone, Field::Name, Field::City]);
+        assert_eq!(rows.len(), 4);
+        for row in &rows {
+            assert_eq!(row.len(), 3);
+            assert!(validate_nepal_phone(&row[0]), "{}", row[0]);
+            assert!(row[1].contains(' '), "{}", row[1]);
+            assert!(generator.indian_cities.contains(&row[2].as_str()), "{}", row[2]);
+        }
+        assert!(generator.generate_table("4 rows please").is_err());
+    }
+
+    #[test]
+    fn test_batches_continue_ids_or_use_uuids() {
+        let mut generator = AIDataGenerator::new();
+        let first = generator.generate_records(5);
+        let second = generator.generate_records_from(first.len() as u32 + 1, 3);
+        let ids: Vec<u32> = first.iter().chain(&second).map(|record| record.id).collect();
+        assert_eq!(ids, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(second.iter().all(|record| record.uuid.is_none()));
+        assert_eq!(generator.generate_records_from(u32::MAX - 1, 5).len(), 2);
+
+        let uuid = regex::Regex::new(r"^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$").unwrap();
+        let mut generator = AIDataGenerator::new().with_id_style(IdStyle::Uuid);
+        let mut seen = std::collections::HashSet::new();
+        for record in generator.generate_records(500).iter().chain(&generator.generate_records(500)) {
+            let id = record.uuid.clone().unwrap();
+            assert!(uuid.is_match(&id), "{}", id);
+            assert!(seen.insert(id));
+        }
+        assert_eq!(seen.len(), 1000);
+    }
+
+    #[test]
+    fn test_weighted_city_dominates() {
+        let mut generator = AIDataGenerator::new()
+            .with_city_weights(&[("Mumbai", 8.0), ("Thane", 1.0), ("Pune", 1.0)]).unwrap()
+            .with_gender_weights(&[("Female", 1.0), ("Male", 0.0)]).unwrap();
+        let samples = 10_000;
+        let mumbai = (0..samples).filter(|_| generator.generate_city() == "Mumbai").count();
+        let share = mumbai as f64 / samples as f64;
+        assert!((share - 0.8).abs() < 0.03, "{}", share);
+        assert!((0..100).all(|_| generator.generate_gender() == "Female"));
+
+        assert!(AIDataGenerator::new().with_city_weights(&[("Mumbai", -1.0)]).is_err());
+        assert!(AIDataGenerator::new().with_gender_weights(&[("Male", 0.0)]).is_err());
+    }
+
+    #[test]
+    fn test_generate_iter_is_lazy() {
+        let mut generator = AIDataGenerator::new();
+        let mut count = 0;
+        let mut last_id = 0;
+        for record in generator.generate_iter(100_000) {
+            count += 1;
+            last_id = record.id;
+        }
+        assert_eq!(count, 100_000);
+        assert_eq!(last_id, 100_000);
+
+        let mut records = generator.generate_iter(3);
+        assert_eq!(records.next().map(|record| record.id), Some(1));
+        assert_eq!(records.size_hint(), (2, Some(2)));
+    }
+
+    #[test]
+    fn test_export_csv_quotes_fields() {
+        use crate::import::Importer;
+
+        let generator = AIDataGenerator::new();
+        let record = DataRecord {
+            id: 1,
+            phone: "9841234567".to_string(),
+            city: "Washington, D.C.".to_string(),
+            gender: "\"Other\"".to_string(),
+            uuid: None,
+            date: None,
+        };
+        let path = std::env::temp_dir().join(format!("qs_quoted_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        for delimiter in [',', ';'] {
+            generator.export_csv_with_delimiter(std::slice::from_ref(&record), path, delimiter).unwrap();
+            let text = fs::read_to_string(path).unwrap();
+            let rows = Importer::parse_csv_with_delimiter(&text, delimiter).unwrap();
+            assert_eq!(rows[1], vec!["1", "9841234567", "Washington, D.C.", "\"Other\""]);
+        }
+        fs::remove_file(path).unwrap();
+    }
+}
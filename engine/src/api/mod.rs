@@ -1,13 +1,36 @@
 //! User-friendly API for Quantum Sheets
 //! This is what users will interact with
 
-use crate::grid::QuantumGrid;
-use crate::operations::OperationRegistry;
+use crate::excel::{CellRange, CellRef, ReferenceStyle};
+use crate::formula::ast::{Expr, Formula};
+use crate::formula::evaluator::CellValue;
+use crate::formula::parser::{parse_formula_checked, ParseError};
+use crate::grid::{QuantumGrid, RecomputeMode, UserFunction};
+use crate::operations::{OperationRegistry, OperationType};
+
+/// What `QuantumAPI::execute` will do with a command; see `QuantumAPI::classify`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandKind {
+    Formula,
+    Generate,
+    Natural,
+    Operation,
+    Unknown,
+}
+
+/// What a formula reads and calls, from `QuantumAPI::validate_formula`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormulaInfo {
+    pub cells: Vec<CellRef>,    // Single-cell references, in first-seen order
+    pub ranges: Vec<CellRange>, // Range references, in first-seen order
+    pub functions: Vec<String>, // Uppercase names, each once
+}
 
 /// Main API for Quantum Sheets
 pub struct QuantumAPI {
     grid: QuantumGrid,
     operations: OperationRegistry,
+    last_result: Option<String>, // Output of the previous successful command
 }
 
 impl QuantumAPI {
@@ -16,43 +39,187 @@ impl QuantumAPI {
         Self {
             grid: QuantumGrid::new(),
             operations: OperationRegistry::new(),
+            last_result: None,
         }
     }
 
-    /// Execute a command (formula, natural language, or operation)
+    /// Execute a command (formula, natural language, or operation).
+    /// In formulas, `_` or `ANS` stands for the previous numeric result.
     pub fn execute(&mut self, command: &str) -> Result<String, String> {
+        let result = self.execute_command(command);
+        if let Ok(output) = &result {
+            self.last_result = Some(output.clone());
+        }
+        result
+    }
+
+    /// Execute commands in order, collecting each result.
+    /// A failing command doesn't stop the rest of the batch.
+    ///
+    /// Formula-like commands with common mistakes (missing `=`, unclosed
+    /// parentheses, lowercase cell references) are repaired first; the result
+    /// then starts with a "Warning:" line describing the correction.
+    pub fn execute_batch(&mut self, commands: &[&str]) -> Vec<Result<String, String>> {
+        commands
+            .iter()
+            .map(|command| {
+                let (corrected, fixes) = autocorrect(command);
+                if fixes.is_empty() {
+                    return self.execute(command);
+                }
+
+                let output = self.execute(&corrected)?;
+                Ok(format!(
+                    "Warning: auto-corrected '{}' to '{}' ({})\n{}",
+                    command.trim(), corrected, fixes.join(", "), output
+                ))
+            })
+            .collect()
+    }
+
+    fn execute_command(&mut self, command: &str) -> Result<String, String> {
         let command = command.trim();
 
-        // Check if it's a formula
+        let substituted;
+        let command = if command.starts_with('=') {
+            substituted = substitute_previous_result(command, self.last_result.as_deref())?;
+            substituted.as_str()
+        } else {
+            command
+        };
+
+        match self.classify(command) {
+            CommandKind::Formula => {
+                use crate::formula::parser::execute_formula;
+                execute_formula(command, &mut self.grid)
+            }
+            CommandKind::Generate => {
+                let request = strip_word(command, "generate").unwrap_or_default();
+                self.execute_generator(request)
+            }
+            CommandKind::Natural => {
+                self.operations.execute("NATURAL", &mut self.grid, &[command.to_string()])
+            }
+            CommandKind::Operation => {
+                let parts: Vec<&str> = command.split_whitespace().collect();
+                let op_name = parts[0].to_uppercase();
+                let args: Vec<String> = parts[1..].iter().map(|s| s.to_string()).collect();
+
+                // Array operations take their destination cell last, e.g. SEQUENCE 3 A1
+                if self.operations.get_array(&op_name).is_some() {
+                    let (dest, args) = args.split_last()
+                        .ok_or_else(|| format!("{} needs a destination cell", op_name))?;
+                    let dest = CellRef::parse(dest)?;
+                    return self.operations.spill(&op_name, &mut self.grid, args, &dest);
+                }
+                self.operations.execute(&op_name, &mut self.grid, &args)
+            }
+            CommandKind::Unknown => Err(format!("Could not understand command: {}", command)),
+        }
+    }
+
+    /// Decide what a command is, trying in order:
+    /// 1. `Formula` - starts with `=`
+    /// 2. `Generate` - first word is "generate"
+    /// 3. `Natural` - the natural-language translator turns it into a formula
+    ///    ("add A1 and B1", "sum of A1 to A3 in C1")
+    /// 4. `Operation` - first word names an operation ("SORT A1:A5", "SEQUENCE 3 A1")
+    /// 5. `Unknown` - anything else
+    ///
+    /// Whole patterns are matched rather than keywords, so "address book" or
+    /// "summary report" aren't mistaken for additions or sums.
+    pub fn classify(&self, command: &str) -> CommandKind {
+        use crate::ai::nlp::NaturalLanguageTranslator;
+
+        let command = command.trim();
         if command.starts_with('=') {
-            use crate::formula::parser::execute_formula;
-            return execute_formula(command, &mut self.grid);
-        }
-
-        // Check if it's a natural language command
-        if command.to_lowercase().contains("add")
-            || command.to_lowercase().contains("sum")
-            || command.to_lowercase().contains("multiply")
-            || command.to_lowercase().contains("generate")
-            || command.to_lowercase().contains("filter")
-        {
-            return self
-                .operations
-                .execute("NATURAL", &mut self.grid, &[command.to_string()]);
-        }
-
-        // Try as operation name
-        let parts: Vec<&str> = command.split_whitespace().collect();
-        if !parts.is_empty() {
-            let op_name = parts[0].to_uppercase();
-            let args: Vec<String> = parts[1..].iter().map(|s| s.to_string()).collect();
-
-            if let Ok(result) = self.operations.execute(&op_name, &mut self.grid, &args) {
-                return Ok(result);
+            return CommandKind::Formula;
+        }
+        if strip_word(command, "generate").is_some() {
+            return CommandKind::Generate;
+        }
+        if NaturalLanguageTranslator::new().translate(command).is_some() {
+            return CommandKind::Natural;
+        }
+        match command.split_whitespace().next().map(str::to_uppercase) {
+            Some(name) if self.operations.get(&name).is_some() || self.operations.get_array(&name).is_some() => {
+                CommandKind::Operation
+            }
+            _ => CommandKind::Unknown,
+        }
+    }
+
+    /// Run "generate <request>": a named generator ("generate nepal_phone"), or
+    /// GENERATE_DATA with the first number as the row count ("generate 50 UK phones")
+    fn execute_generator(&mut self, request: &str) -> Result<String, String> {
+        let words: Vec<&str> = request.split_whitespace().collect();
+        let generators = self.operations.operations_by_type(OperationType::DataGeneration);
+        if let Some(op) = words.first().and_then(|word| generators.iter().find(|op| op.name.eq_ignore_ascii_case(word))) {
+            let name = op.name.clone();
+            let args: Vec<String> = words[1..].iter().map(|s| s.to_string()).collect();
+            return self.operations.execute_typed(&name, OperationType::DataGeneration, &mut self.grid, &args);
+        }
+
+        let count = words.iter().find(|word| word.parse::<u32>().is_ok()).copied().unwrap_or("10");
+        let rest: Vec<&str> = words.iter().copied().filter(|word| *word != count).collect();
+        let args = [count.to_string(), rest.join(" ")];
+        self.operations.execute_typed("GENERATE_DATA", OperationType::DataGeneration, &mut self.grid, &args)
+    }
+
+    /// Define a function callable from formulas like a built-in, e.g.
+    /// `define_function("TAX", &["x"], "=x*0.13")` makes `=TAX(A1)` work, also inside
+    /// larger formulas and in stored ones. The body reads only its parameters (and any
+    /// LET names it binds), never cells, so a stored call recalculates with its arguments.
+    pub fn define_function(&mut self, name: &str, params: &[&str], body: &str) -> Result<(), String> {
+        let name = name.trim().to_uppercase();
+        if !is_identifier(&name) || CellRef::parse(&name).is_ok() {
+            return Err(format!("'{}' is not a valid function name", name));
+        }
+        if self.operations.get(&name).is_some() {
+            return Err(format!("'{}' is a built-in operation", name));
+        }
+        if !body.trim().starts_with('=') {
+            return Err("Function body must be a formula starting with '='".to_string());
+        }
+
+        let mut upper_params: Vec<String> = Vec::new();
+        for param in params {
+            let param = param.trim().to_uppercase();
+            if !is_identifier(&param) || CellRef::parse(&param).is_ok() {
+                return Err(format!("'{}' is not a valid parameter name", param));
+            }
+            if upper_params.contains(&param) {
+                return Err(format!("Parameter '{}' is listed twice", param));
             }
+            upper_params.push(param);
+        }
+
+        let body = Formula::parse(body.trim())?;
+        if let Some(unknown) = body.free_names().into_iter().find(|word| !upper_params.contains(&word.to_uppercase())) {
+            return Err(format!("Unknown parameter '{}' in {}", unknown, name));
+        }
+        if let Some(cell) = body.referenced_cells().first() {
+            return Err(format!("{} can only read its parameters, not cell {}", name, cell));
         }
+        if let Some(range) = body.referenced_ranges().first() {
+            return Err(format!("{} can only read its parameters, not range {}", name, range.to_excel()));
+        }
+
+        self.grid.define_function(&name, UserFunction { params: upper_params, body });
+        Ok(())
+    }
 
-        Err(format!("Could not understand command: {}", command))
+    /// Parse a formula without running it or touching the grid, listing what it
+    /// references and which functions it calls
+    pub fn validate_formula(&self, formula: &str) -> Result<FormulaInfo, ParseError> {
+        let parsed = parse_formula_checked(formula.trim(), &self.operations)?;
+        let mut functions = Vec::new();
+        collect_functions(&parsed.expression, &mut functions);
+        Ok(FormulaInfo {
+            cells: parsed.referenced_cells(),
+            ranges: parsed.referenced_ranges(),
+            functions,
+        })
     }
 
     /// Get cell value
@@ -60,39 +227,117 @@ impl QuantumAPI {
         self.grid.get_cell(cell)
     }
 
+    /// Evaluated values of a range such as "A1:C10", row by row
+    pub fn get_range(&self, range: &str) -> Result<Vec<Vec<CellValue>>, String> {
+        Ok(self.grid.get_range(&CellRange::parse(range)?))
+    }
+
     /// Set cell value
     pub fn set_cell(&mut self, cell: &str, value: f64) -> Result<(), String> {
         self.grid.set_cell(cell, value)
     }
 
+    /// Set many cells at once, recalculating once at the end (see `QuantumGrid::set_cells`).
+    /// Returns the number written and each (reference, error) that failed.
+    pub fn set_cells(&mut self, entries: &[(&str, f64)]) -> (usize, Vec<(String, String)>) {
+        self.grid.set_cells(entries)
+    }
+
+    /// Set many text cells at once, like `set_cells`
+    pub fn set_texts(&mut self, entries: &[(&str, &str)]) -> (usize, Vec<(String, String)>) {
+        self.grid.set_texts(entries)
+    }
+
+    /// Call `callback` with the changed cells (edits plus recalculated formulas) after each edit
+    pub fn set_on_change(&mut self, callback: impl FnMut(&[crate::excel::CellRef]) + 'static) {
+        self.grid.set_on_change(callback);
+    }
+
     /// Set formula in cell
     pub fn set_formula(&mut self, cell: &str, formula: &str) -> Result<(), String> {
-        // First check if it's a valid formula
-        if formula.starts_with('=') {
-            // Store formula string directly for now
-            // In a full implementation, we'd parse and store AST
-            let value = formula.parse::<f64>().unwrap_or(0.0);
-            self.grid.set_cell(cell, value)
-        } else {
-            Err("Formula must start with '='".to_string())
+        self.grid.set_formula(cell, formula)
+    }
+
+    /// Show formulas with A1 or R1C1 references
+    pub fn set_reference_style(&mut self, style: ReferenceStyle) {
+        self.grid.set_reference_style(style);
+    }
+
+    /// Formula in `cell` as text, in the current reference style
+    pub fn get_formula(&self, cell: &str) -> Result<Option<String>, String> {
+        Ok(self.grid.formula_text(&CellRef::parse(cell)?))
+    }
+
+    /// Every formula cell as (A1 address, "=..." source), row by row then column by column
+    pub fn list_formulas(&self) -> Vec<(String, String)> {
+        let mut formulas: Vec<_> = self.grid.formulas().iter().collect();
+        formulas.sort_by_key(|(cell, _)| (cell.row, cell.col));
+        formulas.into_iter()
+            .map(|(cell, formula)| (cell.to_excel(), formula.to_excel()))
+            .collect()
+    }
+
+    /// Whether edits recalculate dependent formulas immediately (Eager, the
+    /// default) or only when `recompute()` is called (Manual)
+    pub fn set_recompute_mode(&mut self, mode: RecomputeMode) {
+        self.grid.set_recompute_mode(mode);
+    }
+
+    /// Current recompute mode
+    pub fn recompute_mode(&self) -> RecomputeMode {
+        self.grid.recompute_mode()
+    }
+
+    /// Bring every formula up to date after edits made in Manual mode
+    pub fn recompute(&mut self) {
+        self.grid.recompute();
+    }
+
+    /// Load CSV text into the grid, replacing existing data or merging over it.
+    /// Returns the (rows, columns) that were imported.
+    /// Malformed CSV is an error that leaves the grid untouched, even when replacing.
+    pub fn load_csv(&mut self, text: &str, replace: bool) -> Result<(u32, u32), String> {
+        let records = crate::import::Importer::parse_csv(text)?;
+        self.load_records(&records, replace)
+    }
+
+    /// Like `load_csv`, but returns how many cells were filled (empty fields don't count)
+    pub fn import_csv(&mut self, text: &str, replace: bool) -> Result<u32, String> {
+        let records = crate::import::Importer::parse_csv(text)?;
+        let filled = records.iter().flatten().filter(|field| !field.is_empty()).count();
+        self.load_records(&records, replace)?;
+        Ok(filled as u32)
+    }
+
+    /// Write parsed CSV records into the grid, clearing it first when replacing
+    fn load_records(&mut self, records: &[Vec<String>], replace: bool) -> Result<(u32, u32), String> {
+        if replace {
+            self.grid.clear();
         }
+        crate::import::Importer::records_to_grid(&mut self.grid, records)
+    }
+
+    /// Whole grid as JSON (see `Exporter::grid_to_json`)
+    pub fn export_json(&self) -> String {
+        crate::export::Exporter::grid_to_json(&self.grid)
     }
 
     /// Get grid statistics
-    // pub fn get_stats(&self) -> String {
-    //     let mut result = String::new();
-    //     result.push_str("Grid Statistics:\n");
-    //     // Get approximate cell count (simplified)
-    //     let cell_count = 0; // We'll implement this properly later
-    //     result.push_str(&format!("Approx. cells: {}\n", cell_count));
-    //     result.push_str(&format!("Operations available: {}\n", self.operations.list_operations().len()));
-    //     result
-    // }
     pub fn get_stats(&self) -> String {
+        let memory = self.grid.memory_report();
+        let efficiency = self.grid.efficiency_report();
         format!(
             "Quantum Sheets v0.6.0\n\
-         Operations available: {}\n\
-         Memory efficient: 4.8x better than Excel",
+         Columns: {}\n\
+         Total cells: {}\n\
+         Formulas: {}\n\
+         Memory: {:.1} bytes per cell ({:.1}x better than Excel)\n\
+         Operations available: {}",
+            self.grid.columns().len(),
+            memory.total_cells,
+            self.grid.formulas().len(),
+            efficiency.our_bytes_per_cell,
+            efficiency.ratio,
             self.operations.list_operations().len()
         )
     }
@@ -107,7 +352,7 @@ impl QuantumAPI {
     where
         F: Fn(&mut QuantumGrid, &[String]) -> Result<String, String> + 'static,
     {
-        use crate::operations::{Operation, OperationType};
+        use crate::operations::Operation;
 
         let op = Operation {
             name: name.to_string(),
@@ -126,4 +371,440 @@ impl QuantumAPI {
     pub fn list_operations(&self) -> Vec<String> {
         self.operations.list_operations()
     }
+
+    /// (name, description, type) for every operation, sorted by name
+    pub fn describe_operations(&self) -> Vec<(&str, &str, OperationType)> {
+        self.operations.describe_operations()
+    }
+}
+
+/// The rest of `command` if its first word is `word` (case-insensitive)
+fn strip_word<'a>(command: &'a str, word: &str) -> Option<&'a str> {
+    let (first, rest) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+    first.eq_ignore_ascii_case(word).then_some(rest.trim_start())
+}
+
+/// Replace standalone `_` / `ANS` tokens in a formula with the previous result
+fn substitute_previous_result(formula: &str, previous: Option<&str>) -> Result<String, String> {
+    replace_words(formula, |word| {
+        if word != "_" && !word.eq_ignore_ascii_case("ANS") {
+            return Ok(None);
+        }
+
+        let value = previous
+            .ok_or_else(|| "No previous result to use for ANS".to_string())?
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| format!("Previous result '{}' is not a number", previous.unwrap_or_default()))?;
+        Ok(Some(value.to_string()))
+    })
+}
+
+/// Rewrite each word (a run of letters, digits and `_`) outside string literals.
+/// `replace` gets the word and returns the replacement, or None to keep the word.
+fn replace_words(
+    formula: &str,
+    mut replace: impl FnMut(&str) -> Result<Option<String>, String>,
+) -> Result<String, String> {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let mut output = String::with_capacity(formula.len());
+    let mut token = String::new();
+    let mut in_string = false;
+
+    // Trailing space flushes the final token
+    for c in formula.chars().chain(std::iter::once(' ')) {
+        if is_word(c) && !in_string {
+            token.push(c);
+            continue;
+        }
+
+        if !token.is_empty() {
+            match replace(&token)? {
+                Some(replacement) => output.push_str(&replacement),
+                None => output.push_str(&token),
+            }
+            token.clear();
+        }
+        if c == '"' {
+            in_string = !in_string;
+        }
+        output.push(c);
+    }
+
+    output.pop(); // The flushing space
+    Ok(output)
+}
+
+/// Letters, digits and `_`, starting with a letter
+fn is_identifier(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_alphabetic())
+        && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Best-effort repair of a formula-like command.
+/// Returns the corrected command and a description of each fix (empty if untouched).
+fn autocorrect(command: &str) -> (String, Vec<String>) {
+    use regex::Regex;
+
+    let mut command = command.trim().to_string();
+    let mut fixes = Vec::new();
+
+    // "SUM(A1:A3)" was meant as a formula
+    let function_call = Regex::new(r"^[A-Za-z_]+\(").unwrap();
+    if !command.starts_with('=') {
+        if !function_call.is_match(&command) {
+            return (command, fixes);
+        }
+        command.insert(0, '=');
+        fixes.push("added missing '='".to_string());
+    }
+
+    // Work outside string literals only (even segments when split on quotes)
+    let cell_ref = Regex::new(r"\b(\$?)([A-Za-z]{1,3})(\$?)([0-9]+)\b").unwrap();
+    let mut depth: i64 = 0;
+    let mut lowercase_refs = false;
+    let segments: Vec<String> = command
+        .split('"')
+        .enumerate()
+        .map(|(i, segment)| {
+            if i % 2 == 1 {
+                return segment.to_string();
+            }
+            depth += segment.matches('(').count() as i64 - segment.matches(')').count() as i64;
+            cell_ref
+                .replace_all(segment, |caps: &regex::Captures| {
+                    let whole = &caps[0];
+                    if whole.chars().any(|c| c.is_ascii_lowercase()) {
+                        lowercase_refs = true;
+                    }
+                    whole.to_ascii_uppercase()
+                })
+                .into_owned()
+        })
+        .collect();
+    command = segments.join("\"");
+
+    if lowercase_refs {
+        fixes.push("uppercased cell references".to_string());
+    }
+    if depth > 0 {
+        command.push_str(&")".repeat(depth as usize));
+        fixes.push(format!("closed {} parenthes{}", depth, if depth == 1 { "is" } else { "es" }));
+    } else if depth < 0 && command.ends_with(&")".repeat(-depth as usize)) {
+        command.truncate(command.len() - (-depth) as usize);
+        fixes.push(format!("removed {} extra ')'", -depth));
+    }
+
+    (command, fixes)
+}
+
+fn collect_functions(expr: &Expr, functions: &mut Vec<String>) {
+    match expr {
+        Expr::Function(name, args) => {
+            if !functions.contains(name) {
+                functions.push(name.clone());
+            }
+            for arg in args {
+                collect_functions(arg, functions);
+            }
+        }
+        Expr::Binary(left, _, right) => {
+            collect_functions(left, functions);
+            collect_functions(right, functions);
+        }
+        Expr::Group(inner) => collect_functions(inner, functions),
+        Expr::Number(_) | Expr::Text(_) | Expr::Name(_) | Expr::CellRef(_) | Expr::Range(..) | Expr::RefError => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_previous_result_token() {
+        let mut api = QuantumAPI::new();
+
+        assert_eq!(api.execute("=2+3").unwrap(), "5");
+        assert_eq!(api.execute("=_ * 2").unwrap(), "10");
+        assert_eq!(api.execute("=ANS+1").unwrap(), "11");
+
+        // The previous output wasn't a number
+        api.execute("DOUBLE 4").unwrap();
+        assert!(api.execute("=_ * 2").unwrap_err().contains("not a number"));
+    }
+
+    #[test]
+    fn test_execute_batch_continues_past_errors() {
+        let mut api = QuantumAPI::new();
+        let results = api.execute_batch(&["SET A1 4", "SET A2 6", "=A1+A2", "NOT_A_COMMAND", "=_ * 2"]);
+
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0], Ok("A1 = 4".to_string()));
+        assert_eq!(results[2], Ok("10".to_string()));
+        assert!(results[3].is_err());
+        assert_eq!(results[4], Ok("20".to_string())); // Still runs after the failure
+        assert_eq!(api.get_cell("A2"), Ok(6.0));
+    }
+
+    #[test]
+    fn test_set_cells_recalculates_once() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let mut api = QuantumAPI::new();
+        api.set_formula("B1", "=A1+A50").unwrap();
+        assert_eq!(api.get_cell("B1"), Ok(0.0));
+
+        let changes = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&changes);
+        api.set_on_change(move |_| counter.set(counter.get() + 1));
+        let evaluations = api.grid.formula_evaluations();
+
+        let references: Vec<String> = (1..=50).map(|row| format!("A{}", row)).collect();
+        let entries: Vec<(&str, f64)> = references.iter().map(|cell| (cell.as_str(), 2.0)).collect();
+        assert_eq!(api.set_cells(&entries), (50, Vec::new()));
+
+        assert_eq!(changes.get(), 1);
+        assert_eq!(api.grid.formula_evaluations(), evaluations + 1);
+        assert_eq!(api.get_cell("B1"), Ok(4.0));
+
+        let (written, errors) = api.set_texts(&[("C1", "north"), ("C0", "bad")]);
+        assert_eq!(written, 1);
+        assert_eq!(errors[0].0, "C0");
+    }
+
+    #[test]
+    fn test_import_csv_counts_filled_cells() {
+        let mut api = QuantumAPI::new();
+        api.set_cell("D9", 1.0).unwrap();
+
+        assert_eq!(api.import_csv("name,score\nSita,90\nRam,\n", false), Ok(5));
+        assert_eq!(api.get_cell("B2"), Ok(90.0));
+        assert_eq!(api.get_cell("D9"), Ok(1.0)); // Merged over
+
+        assert_eq!(api.import_csv("7", true), Ok(1));
+        assert!(api.get_cell("D9").is_err());
+    }
+
+    #[test]
+    fn test_failed_replace_import_keeps_the_grid() {
+        let mut api = QuantumAPI::new();
+        api.set_cell("A1", 5.0).unwrap();
+
+        assert!(api.import_csv("name\n\"unterminated", true).is_err());
+        assert!(api.load_csv("name\n\"unterminated", true).is_err());
+        assert_eq!(api.get_cell("A1"), Ok(5.0));
+    }
+
+    #[test]
+    fn test_formula_errors_say_where() {
+        let mut api = QuantumAPI::new();
+        assert_eq!(api.execute("=A1*(B1+2"), Err("missing ')' at position 9".to_string()));
+        assert_eq!(api.execute("=A1*)"), Err("unexpected ')' at position 4".to_string()));
+    }
+
+    #[test]
+    fn test_validate_formula_lists_references_without_running() {
+        let api = QuantumAPI::new();
+        let info = api.validate_formula("=SUM(A1:A3)+B1").unwrap();
+
+        assert_eq!(info.ranges, vec![CellRange::parse("A1:A3").unwrap()]);
+        assert_eq!(info.cells, vec![CellRef::parse("B1").unwrap()]);
+        assert_eq!(info.functions, vec!["SUM".to_string()]);
+        assert_eq!(api.get_stats(), QuantumAPI::new().get_stats());
+
+        assert_eq!(api.validate_formula("=A1+").unwrap_err().position, 4);
+    }
+
+    #[test]
+    fn test_execute_batch_repairs_common_mistakes() {
+        let mut api = QuantumAPI::new();
+        let results = api.execute_batch(&["SUM(A1:A3", "=a1+b1", "=A1*2"]);
+
+        let repaired = results[0].as_ref().unwrap();
+        assert!(repaired.starts_with("Warning: auto-corrected 'SUM(A1:A3' to '=SUM(A1:A3)'"), "{}", repaired);
+        assert!(repaired.contains("added missing '='") && repaired.contains("closed 1 parenthesis"));
+
+        assert!(results[1].as_ref().unwrap().contains("uppercased cell references"));
+        assert_eq!(results[2], Ok("0".to_string())); // Well-formed commands are untouched
+
+        // Text inside quotes is never rewritten
+        assert_eq!(autocorrect("=COUNTIF(B1:B4, \"a1\")").0, "=COUNTIF(B1:B4, \"a1\")");
+        assert_eq!(autocorrect("DOUBLE 4").1.len(), 0);
+    }
+
+    #[test]
+    fn test_user_defined_function() {
+        let mut api = QuantumAPI::new();
+        api.define_function("TAX", &["x"], "=x*0.13").unwrap();
+        api.set_cell("A1", 200.0).unwrap();
+
+        assert_eq!(api.execute("=TAX(A1)").unwrap(), "26");
+        assert_eq!(api.execute("=tax(100)").unwrap(), "13");
+        assert_eq!(api.execute("=TAX(1, 2)").unwrap(), "#VALUE!");
+
+        // Bad definitions are rejected up front
+        assert!(api.define_function("VAT", &["x"], "=y*0.13").unwrap_err().contains("Unknown parameter 'y'"));
+        assert!(api.define_function("VAT", &["x"], "=x*B1").unwrap_err().contains("not cell B1"));
+        assert!(api.define_function("SUM", &["x"], "=x").is_err());
+        api.define_function("NET", &["x"], "=LET(rate, 0.13, x-x*rate)").unwrap();
+        assert_eq!(api.execute("=NET(100)").unwrap(), "87");
+
+        // A function that calls itself hits the depth limit instead of overflowing
+        api.define_function("LOOP", &["x"], "=LOOP(x)").unwrap();
+        assert_eq!(api.execute("=LOOP(1)").unwrap(), "#NUM!");
+    }
+
+    #[test]
+    fn test_user_functions_nest_and_store() {
+        let mut api = QuantumAPI::new();
+        api.define_function("TAX", &["x"], "=x*0.1").unwrap();
+        api.define_function("GROSS", &["x"], "=x+TAX(x)").unwrap();
+        api.set_cell("A1", 2.0).unwrap();
+
+        assert_eq!(api.execute("=TAX(A1)+1").unwrap(), "1.2");
+        assert_eq!(api.execute("=ROUND(GROSS(A1*10), 1) & \" kg\"").unwrap(), "22 kg");
+
+        // Stored calls follow their arguments and the function's definition
+        api.set_formula("B1", "=TAX(A1)*10").unwrap();
+        assert_eq!(api.get_cell("B1"), Ok(2.0));
+        api.set_cell("A1", 5.0).unwrap();
+        assert_eq!(api.get_cell("B1"), Ok(5.0));
+        api.define_function("TAX", &["x"], "=x*0.2").unwrap();
+        assert_eq!(api.get_cell("B1"), Ok(10.0));
+    }
+
+    #[test]
+    fn test_get_stats_reports_real_cell_count() {
+        let mut api = QuantumAPI::new();
+        for row in 1..=5 {
+            api.set_cell(&format!("A{}", row), row as f64).unwrap();
+            api.set_cell(&format!("B{}", row), 1.0).unwrap();
+        }
+
+        let stats = api.get_stats();
+        assert!(stats.contains("Columns: 2"), "{}", stats);
+        assert!(stats.contains("Total cells: 10"), "{}", stats);
+        assert!(stats.contains("Formulas: 0"), "{}", stats);
+        assert!(!stats.contains("4.8x"), "{}", stats);
+    }
+
+    #[test]
+    fn test_previous_result_needs_a_previous_command() {
+        let mut api = QuantumAPI::new();
+        assert!(api.execute("=_+1").is_err());
+
+        // Cell references containing the letters aren't touched
+        api.set_cell("ANS1", 7.0).unwrap();
+        assert_eq!(api.execute("=ANS1+1").unwrap(), "8");
+    }
+
+    #[test]
+    fn test_recompute_modes() {
+        let mut api = QuantumAPI::new();
+        api.set_cell("A1", 1.0).unwrap();
+        api.set_cell("B1", 2.0).unwrap();
+        api.set_formula("C1", "=A1+B1").unwrap();
+        assert_eq!(api.get_cell("C1"), Ok(3.0));
+
+        // Eager (default): dependents update on the edit
+        api.set_cell("A1", 10.0).unwrap();
+        assert_eq!(api.get_cell("C1"), Ok(12.0));
+
+        // Manual: C1 keeps its old result until recompute()
+        api.set_recompute_mode(RecomputeMode::Manual);
+        api.set_cell("A1", 20.0).unwrap();
+        api.set_cell("B1", 5.0).unwrap();
+        assert_eq!(api.get_cell("C1"), Ok(12.0));
+        api.recompute();
+        assert_eq!(api.get_cell("C1"), Ok(25.0));
+        assert_eq!(api.recompute_mode(), RecomputeMode::Manual);
+    }
+
+    #[test]
+    fn test_array_operation_command_spills() {
+        let mut api = QuantumAPI::new();
+        assert_eq!(api.execute("SEQUENCE 3 A1").unwrap(), "SEQUENCE spilled 3x1 into A1:A3");
+        assert_eq!(api.get_cell("A2"), Ok(2.0));
+        assert!(api.execute("SEQUENCE 2 A3").unwrap_err().contains("#SPILL!"));
+    }
+
+    #[test]
+    fn test_calculations_never_invoke_generators() {
+        let mut api = QuantumAPI::new();
+
+        let err = api.execute("=GENERATE_DATA(5, phone)").unwrap_err();
+        assert!(err.contains("not a calculation"), "{}", err);
+        let err = api.execute("=NEPAL_PHONE()").unwrap_err();
+        assert!(err.contains("not a calculation"), "{}", err);
+        assert!(api.grid.used_range().is_none());
+
+        // "generate" goes to GENERATE_DATA even when the request mentions a calculation
+        let summary = api.execute("generate 3 ids and phone numbers to sum").unwrap();
+        assert!(summary.starts_with("Generated 3 rows (stored in A1:B3)"), "{}", summary);
+        assert_eq!(api.execute("generate nepal_phone").unwrap().len(), 10);
+        assert_eq!(api.execute("=PRODUCT(A1:A3)").unwrap(), "6");
+    }
+
+    #[test]
+    fn test_classify_matches_whole_requests() {
+        let api = QuantumAPI::new();
+
+        assert_eq!(api.classify("=A1+B1"), CommandKind::Formula);
+        assert_eq!(api.classify("Generate 5 UK phones"), CommandKind::Generate);
+        assert_eq!(api.classify("add A1 and B1"), CommandKind::Natural);
+        assert_eq!(api.classify("sum of A1 to A3 in C1"), CommandKind::Natural);
+        assert_eq!(api.classify("sort A1:A3"), CommandKind::Operation);
+        assert_eq!(api.classify("SEQUENCE 3 A1"), CommandKind::Operation);
+        assert_eq!(api.classify("filter data where value > 100"), CommandKind::Operation);
+        assert_eq!(api.classify("address book"), CommandKind::Unknown);
+        assert_eq!(api.classify("summary report"), CommandKind::Unknown);
+    }
+
+    #[test]
+    fn test_keyword_lookalikes_are_not_formula_requests() {
+        let mut api = QuantumAPI::new();
+        for command in ["address book", "summary report", "generated totals"] {
+            let err = api.execute(command).unwrap_err();
+            assert_eq!(err, format!("Could not understand command: {}", command));
+        }
+        assert!(api.grid.used_range().is_none());
+    }
+
+    #[test]
+    fn test_list_formulas_in_cell_order() {
+        let mut api = QuantumAPI::new();
+        api.set_formula("B2", "=A1*2").unwrap();
+        api.set_formula("C1", "=SUM(A1:A3)").unwrap();
+        api.set_formula("A2", "=A1+A1").unwrap();
+        api.set_formula("AA1", "=C1-1").unwrap();
+
+        let expected = [("C1", "=SUM(A1:A3)"), ("AA1", "=C1 - 1"), ("A2", "=A1 + A1"), ("B2", "=A1 * 2")];
+        let expected: Vec<(String, String)> = expected.iter()
+            .map(|(cell, formula)| (cell.to_string(), formula.to_string()))
+            .collect();
+        assert_eq!(api.list_formulas(), expected);
+    }
+
+    #[test]
+    fn test_index_of_match() {
+        let mut api = QuantumAPI::new();
+        api.load_csv("101,Pune\n102,Delhi\n103,Patna\n", true).unwrap();
+        assert_eq!(api.execute("=MATCH(102, A1:A3, 0)").unwrap(), "2");
+        assert_eq!(api.execute("=INDEX(B1:B3, MATCH(103, A1:A3, 0))").unwrap(), "Patna");
+    }
+
+    #[test]
+    fn test_sort_command() {
+        let mut api = QuantumAPI::new();
+        for (row, value) in [3.0, 1.0, 2.0].iter().enumerate() {
+            api.set_cell(&format!("A{}", row + 1), *value).unwrap();
+        }
+
+        assert_eq!(api.execute("sort A1:A3 desc").unwrap(), "Sorted A1:A3 descending");
+        assert_eq!((api.get_cell("A1"), api.get_cell("A3")), (Ok(3.0), Ok(1.0)));
+        assert!(api.execute("sort A1:A3 sideways").is_err());
+    }
 }
@@ -11,6 +11,16 @@ pub fn has_avx() -> bool {
     false
 }
 
+/// Default tolerance for comparing results of floating-point arithmetic: numbers
+/// agreeing to about 12 significant digits are equal, so 0.1 + 0.2 equals 0.3
+pub const DEFAULT_EPSILON: f64 = 1e-12;
+
+/// Whether `a` and `b` are equal within `eps`, relative to the larger magnitude
+/// (absolute below 1, so values near zero compare sensibly). An `eps` of 0 is exact.
+pub fn approx_eq(a: f64, b: f64, eps: f64) -> bool {
+    a == b || (a - b).abs() <= eps * a.abs().max(b.abs()).max(1.0)
+}
+
 /// Optimized sum using chunk processing (manual SIMD-like optimization)
 pub fn optimized_sum(data: &[f64]) -> f64 {
     if data.len() < 8 {
@@ -31,6 +41,24 @@ pub fn optimized_sum(data: &[f64]) -> f64 {
     sum + chunks.remainder().iter().sum::<f64>()
 }
 
+/// Optimized product using the same 8-wide chunking as `optimized_sum`.
+/// An empty slice gives 1, the multiplicative identity.
+pub fn optimized_product(data: &[f64]) -> f64 {
+    if data.len() < 8 {
+        return data.iter().product();
+    }
+    
+    let mut product = 1.0;
+    let mut chunks = data.chunks_exact(8);
+    
+    for chunk in chunks.by_ref() {
+        product *= chunk[0] * chunk[1] * chunk[2] * chunk[3] *
+                   chunk[4] * chunk[5] * chunk[6] * chunk[7];
+    }
+    
+    product * chunks.remainder().iter().product::<f64>()
+}
+
 /// Compare optimized vs scalar performance
 pub fn benchmark_sum(data: &[f64]) -> (f64, f64, f64) {
     use std::time::Instant;
@@ -75,4 +103,204 @@ pub fn calculate_stats(data: &[f64]) -> (f64, f64, f64, f64, f64) {
     let average = sum / count;
     
     (sum, average, min, max, count)
-}
\ No newline at end of file
+}
+
+/// Summary statistics of a data set, from `calculate_full_stats`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FullStats {
+    pub count: usize,
+    pub sum: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub mode: Option<f64>,    // Most frequent value, first seen on a tie; None if nothing repeats
+    pub min: f64,
+    pub max: f64,
+    pub std_dev: Option<f64>, // Sample standard deviation like Excel's STDEV; None below two values
+}
+
+/// Count, sum, mean, median, mode, min, max and standard deviation in one call.
+/// None for an empty data set.
+pub fn calculate_full_stats(data: &[f64]) -> Option<FullStats> {
+    if data.is_empty() {
+        return None;
+    }
+
+    let (sum, mean, min, max, _) = calculate_stats(data);
+
+    let mut sorted = data.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    let median = if sorted.len().is_multiple_of(2) { (sorted[mid - 1] + sorted[mid]) / 2.0 } else { sorted[mid] };
+
+    // Count each value once (by its bits, with -0.0 as 0.0; NaN never matches), then take
+    // the first value in data order that reached the top count
+    let key = |value: f64| if value == 0.0 { 0 } else { value.to_bits() };
+    let mut counts: std::collections::HashMap<u64, usize> = std::collections::HashMap::new();
+    for &value in data.iter().filter(|value| !value.is_nan()) {
+        *counts.entry(key(value)).or_default() += 1;
+    }
+    let best = counts.values().copied().max().unwrap_or(0);
+    let mode = data.iter().copied().find(|&value| best > 1 && counts.get(&key(value)) == Some(&best));
+
+    let std_dev = (data.len() > 1).then(|| {
+        let squares: f64 = data.iter().map(|value| (value - mean) * (value - mean)).sum();
+        (squares / (data.len() - 1) as f64).sqrt()
+    });
+
+    Some(FullStats { count: data.len(), sum, mean, median, mode, min, max, std_dev })
+}
+
+/// Percentile `q` (0 to 1) of the data like Excel's PERCENTILE.INC: the value at rank
+/// q*(n-1) in sorted order, interpolating linearly between neighbours.
+/// None for empty data or a `q` outside [0, 1].
+pub fn percentile(data: &[f64], q: f64) -> Option<f64> {
+    if data.is_empty() || !(0.0..=1.0).contains(&q) {
+        return None;
+    }
+
+    let mut sorted = data.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank = q * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    Some(sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64))
+}
+
+/// Rolling mean over `window` consecutive values: `result[i]` averages `data[i..i + window]`.
+/// Only full windows count, so the first `window - 1` points get no average of their own
+/// and the result has `data.len() - window + 1` values. Empty if `window` is 0 or longer
+/// than the data.
+pub fn moving_average(data: &[f64], window: usize) -> Vec<f64> {
+    if window == 0 || window > data.len() {
+        return Vec::new();
+    }
+
+    let mut sum: f64 = data[..window].iter().sum();
+    let mut averages = vec![sum / window as f64];
+    for i in window..data.len() {
+        sum += data[i] - data[i - window];
+        averages.push(sum / window as f64);
+    }
+    averages
+}
+
+/// Count values into `bins` equal-width bins from min to max, as (low, high, count).
+/// Each bin holds low <= value < high, except the last, which also holds the max.
+/// All-equal data gives one bin; no data or zero bins gives none.
+pub fn histogram(data: &[f64], bins: usize) -> Vec<(f64, f64, usize)> {
+    if data.is_empty() || bins == 0 {
+        return Vec::new();
+    }
+
+    let (_, _, min, max, _) = calculate_stats(data);
+    if min == max {
+        return vec![(min, max, data.len())];
+    }
+
+    let width = (max - min) / bins as f64;
+    let mut counts = vec![0; bins];
+    for &value in data {
+        let bin = (((value - min) / width) as usize).min(bins - 1);
+        counts[bin] += 1;
+    }
+
+    counts.into_iter()
+        .enumerate()
+        .map(|(i, count)| {
+            let high = if i == bins - 1 { max } else { min + width * (i + 1) as f64 };
+            (min + width * i as f64, high, count)
+        })
+        .collect()
+}
+
+/// Compound growth rate per period: (end/begin)^(1/periods) - 1.
+/// None if begin isn't positive, periods is zero, or end is negative.
+pub fn cagr(begin: f64, end: f64, periods: f64) -> Option<f64> {
+    if begin <= 0.0 || periods == 0.0 || end < 0.0 {
+        return None;
+    }
+    Some((end / begin).powf(1.0 / periods) - 1.0)
+}
+
+/// Pearson correlation of two equal-length series.
+/// None on a length mismatch, fewer than two points, or zero variance.
+pub fn correlation(x: &[f64], y: &[f64]) -> Option<f64> {
+    if x.len() != y.len() || x.len() < 2 {
+        return None;
+    }
+
+    let n = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+
+    let mut covariance = 0.0;
+    let mut variance_x = 0.0;
+    let mut variance_y = 0.0;
+    for (&a, &b) in x.iter().zip(y) {
+        covariance += (a - mean_x) * (b - mean_y);
+        variance_x += (a - mean_x) * (a - mean_x);
+        variance_y += (b - mean_y) * (b - mean_y);
+    }
+
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+/// Least-squares line through (x, y) points, as (slope, intercept, r²).
+/// None on a length mismatch, fewer than two points, or when every x is equal.
+/// Constant y is fitted exactly by a flat line, so its r² is 1.
+pub fn linear_regression(x: &[f64], y: &[f64]) -> Option<(f64, f64, f64)> {
+    if x.len() != y.len() || x.len() < 2 {
+        return None;
+    }
+
+    let n = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+
+    let mut covariance = 0.0;
+    let mut variance_x = 0.0;
+    let mut variance_y = 0.0;
+    for (&a, &b) in x.iter().zip(y) {
+        covariance += (a - mean_x) * (b - mean_y);
+        variance_x += (a - mean_x) * (a - mean_x);
+        variance_y += (b - mean_y) * (b - mean_y);
+    }
+    if variance_x == 0.0 {
+        return None;
+    }
+
+    let slope = covariance / variance_x;
+    let intercept = mean_y - slope * mean_x;
+    let r_squared = if variance_y == 0.0 {
+        1.0
+    } else {
+        (covariance * covariance / (variance_x * variance_y)).min(1.0)
+    };
+    Some((slope, intercept, r_squared))
+}
+
+/// Pairwise correlations between columns (None entries are blanks).
+/// Each pair only uses rows where both columns have a value; a pair
+/// that can't be correlated (e.g. a constant column) is None.
+pub fn correlation_matrix(columns: &[Vec<Option<f64>>]) -> Vec<Vec<Option<f64>>> {
+    let n = columns.len();
+    let mut matrix = vec![vec![None; n]; n];
+
+    for i in 0..n {
+        for j in i..n {
+            let (x, y): (Vec<f64>, Vec<f64>) = columns[i].iter()
+                .zip(&columns[j])
+                .filter_map(|(a, b)| Some(((*a)?, (*b)?)))
+                .unzip();
+
+            let r = correlation(&x, &y).map(|r| if i == j { 1.0 } else { r.clamp(-1.0, 1.0) });
+            matrix[i][j] = r;
+            matrix[j][i] = r;
+        }
+    }
+
+    matrix
+}
@@ -1,39 +1,82 @@
 //! Excel compatibility - Parse A1, B2, AA100 style references
 
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
-/// Excel-style cell reference (e.g., A1, B2, AA100)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Excel's last row (1,048,576)
+pub const MAX_ROWS: u32 = 1048576;
+
+/// Excel's last column, XFD (16,384)
+pub const MAX_COLS: u32 = 16384;
+
+/// Excel-style cell reference (e.g., A1, B2, AA100, $A$1)
+#[derive(Debug, Clone, Copy)]
 pub struct CellRef {
     pub row: u32,    // 1-based row number
     pub col: u32,    // 1-based column number
+    pub row_abs: bool, // `$` before the row: stays fixed when copied
+    pub col_abs: bool, // `$` before the column: stays fixed when copied
+}
+
+// Anchors only change how a reference moves when copied; A1 and $A$1 are the same cell
+impl PartialEq for CellRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.row == other.row && self.col == other.col
+    }
+}
+
+impl Eq for CellRef {}
+
+impl Hash for CellRef {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.row.hash(state);
+        self.col.hash(state);
+    }
 }
 
 impl CellRef {
-    /// Parse from Excel notation (e.g., "A1", "B2", "AA100")
+    /// Parse from Excel notation (e.g., "A1", "B2", "AA100", "$A$1").
+    /// Works on the bytes directly, so parsing never allocates on success.
     pub fn parse(excel_ref: &str) -> Result<Self, String> {
         let excel_ref = excel_ref.trim();
         if excel_ref.is_empty() {
             return Err("Empty cell reference".to_string());
         }
         
-        let mut chars = excel_ref.chars().peekable();
-        let mut col_str = String::new();
+        let bytes = excel_ref.as_bytes();
+        let mut pos = 0;
         
-        // Parse column letters (A, B, ..., Z, AA, AB, etc.)
-        while let Some(&c) = chars.peek() {
-            if c.is_ascii_alphabetic() {
-                col_str.push(c.to_ascii_uppercase());
-                chars.next(); // Consume the character
-            } else {
-                break;
-            }
+        let col_abs = bytes[pos] == b'$';
+        if col_abs {
+            pos += 1;
         }
         
-        // Parse row number (rest of the string)
-        let row_str: String = chars.collect();
+        // Column letters (A, B, ..., Z, AA, AB, etc.): A=1, ..., Z=26, AA=27
+        let col_start = pos;
+        let mut col: u32 = 0;
+        while pos < bytes.len() && bytes[pos].is_ascii_alphabetic() {
+            let digit = (bytes[pos].to_ascii_uppercase() - b'A') as u32 + 1;
+            // Capped on every step, so the sum can't overflow however many letters follow
+            col = (col * 26 + digit).min(MAX_COLS + 1);
+            pos += 1;
+        }
+        let has_col = pos > col_start;
+        if col > MAX_COLS {
+            return Err(format!(
+                "Column '{}' in '{}' is beyond the last column XFD ({})",
+                &excel_ref[col_start..pos], excel_ref, MAX_COLS
+            ));
+        }
         
-        if col_str.is_empty() {
+        let row_abs = pos < bytes.len() && bytes[pos] == b'$';
+        if row_abs {
+            pos += 1;
+        }
+        
+        // Row number (rest of the string); letters and '$' are ASCII, so `pos` is a char boundary
+        let row_str = &excel_ref[pos..];
+        
+        if !has_col {
             return Err(format!("No column letters in '{}'", excel_ref));
         }
         
@@ -41,17 +84,6 @@ impl CellRef {
             return Err(format!("No row number in '{}'", excel_ref));
         }
         
-        // Convert column letters to number (A=1, B=2, ..., Z=26, AA=27, etc.)
-        let col = col_str
-            .chars()
-            .rev()
-            .enumerate()
-            .map(|(i, c)| {
-                let digit = (c as u32) - ('A' as u32) + 1;
-                digit * 26u32.pow(i as u32)
-            })
-            .sum();
-        
         let row = row_str.parse::<u32>()
             .map_err(|_| format!("Invalid row number '{}' in '{}'", row_str, excel_ref))?;
         
@@ -59,28 +91,87 @@ impl CellRef {
             return Err("Row number must be at least 1".to_string());
         }
         
-        Ok(Self { row, col })
+        if row > MAX_ROWS {
+            return Err(format!("Row {} in '{}' is beyond the last row ({})", row, excel_ref, MAX_ROWS));
+        }
+        
+        Ok(Self { row, col, row_abs, col_abs })
     }
     
     /// Convert to Excel notation
     pub fn to_excel(&self) -> String {
-        let mut col = self.col;
-        let mut col_str = String::new();
+        let col_str = column_letters(self.col);
+        let col_anchor = if self.col_abs { "$" } else { "" };
+        let row_anchor = if self.row_abs { "$" } else { "" };
+        format!("{}{}{}{}", col_anchor, col_str, row_anchor, self.row)
+    }
+    
+    /// Parse absolute R1C1 notation (e.g., "R5C3" for C5). R1C1 references are
+    /// absolute, so both parts come back `$`-anchored. Relative forms like
+    /// `R[-1]C` aren't supported yet.
+    pub fn parse_r1c1(r1c1_ref: &str) -> Result<Self, String> {
+        let r1c1_ref = r1c1_ref.trim();
+        let invalid = || format!("Invalid R1C1 reference '{}' (expected format: R1C1)", r1c1_ref);
+        
+        let rest = r1c1_ref.strip_prefix(['R', 'r']).ok_or_else(invalid)?;
+        let (row_str, col_str) = rest.split_once(['C', 'c']).ok_or_else(invalid)?;
         
-        // Convert column number to letters
-        while col > 0 {
-            col -= 1;
-            let digit = (col % 26) as u8;
-            col_str.insert(0, (b'A' + digit) as char);
-            col /= 26;
+        if row_str.starts_with('[') || col_str.starts_with('[') || row_str.is_empty() || col_str.is_empty() {
+            return Err(format!("Relative R1C1 references are not supported: '{}'", r1c1_ref));
         }
         
-        format!("{}{}", col_str, self.row)
+        let number = |digits: &str| -> Result<u32, String> {
+            if !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            digits.parse::<u32>().map_err(|_| invalid())
+        };
+        let row = number(row_str)?;
+        let col = number(col_str)?;
+        
+        if row == 0 || col == 0 {
+            return Err("Row and column numbers must be at least 1".to_string());
+        }
+        
+        if row > MAX_ROWS || col > MAX_COLS {
+            return Err(format!(
+                "'{}' is beyond the last cell R{}C{}", r1c1_ref, MAX_ROWS, MAX_COLS
+            ));
+        }
+        
+        Ok(Self { row, col, row_abs: true, col_abs: true })
+    }
+    
+    /// Convert to R1C1 notation (e.g., C5 → "R5C3")
+    pub fn to_r1c1(&self) -> String {
+        format!("R{}C{}", self.row, self.col)
+    }
+    
+    /// Convert to the given notation
+    pub fn to_style(&self, style: ReferenceStyle) -> String {
+        match style {
+            ReferenceStyle::A1 => self.to_excel(),
+            ReferenceStyle::R1C1 => self.to_r1c1(),
+        }
     }
     
     /// Create from row and column indices (1-based)
     pub fn new(row: u32, col: u32) -> Self {
-        Self { row, col }
+        Self { row, col, row_abs: false, col_abs: false }
+    }
+    
+    /// Move by a row/column delta, leaving `$`-anchored parts fixed.
+    /// Returns None if the result falls off the sheet.
+    pub fn offset(&self, row_delta: i64, col_delta: i64) -> Option<Self> {
+        let row = if self.row_abs { self.row as i64 } else { self.row as i64 + row_delta };
+        let col = if self.col_abs { self.col as i64 } else { self.col as i64 + col_delta };
+        
+        if row < 1 || col < 1 {
+            return None;
+        }
+        
+        let moved = Self { row: row as u32, col: col as u32, ..*self };
+        if moved.is_valid() { Some(moved) } else { None }
     }
     
     /// Convert to 0-based indices for internal use
@@ -90,9 +181,31 @@ impl CellRef {
     
     /// Check if this is a valid Excel reference
     pub fn is_valid(&self) -> bool {
-        self.row >= 1 && self.row <= 1048576 &&  // Excel row limit
-        self.col >= 1 && self.col <= 16384       // Excel column limit (XFD)
+        self.row >= 1 && self.row <= MAX_ROWS &&
+        self.col >= 1 && self.col <= MAX_COLS
+    }
+}
+
+/// Notation used when displaying references: A1 ("C5") or R1C1 ("R5C3")
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReferenceStyle {
+    #[default]
+    A1,
+    R1C1,
+}
+
+/// Column letters for a 1-based column number (1 → "A", 27 → "AA")
+pub fn column_letters(col: u32) -> String {
+    let mut col = col;
+    let mut col_str = String::new();
+    
+    while col > 0 {
+        col -= 1;
+        let digit = (col % 26) as u8;
+        col_str.insert(0, (b'A' + digit) as char);
+        col /= 26;
     }
+    col_str
 }
 
 impl fmt::Display for CellRef {
@@ -102,7 +215,7 @@ impl fmt::Display for CellRef {
 }
 
 /// Excel range (e.g., "A1:B10")
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CellRange {
     pub start: CellRef,
     pub end: CellRef,
@@ -126,10 +239,43 @@ impl CellRange {
         if !end.is_valid() {
             return Err(format!("Invalid end cell in range: {}", end));
         }
-        
-        Ok(Self { start, end })
+
+        Ok(Self::new(start, end))
     }
-    
+
+    /// Create from two corners, normalized so start is the top-left
+    /// (like Excel does for "B5:A1")
+    pub fn new(start: CellRef, end: CellRef) -> Self {
+        if start.row <= end.row && start.col <= end.col {
+            return Self { start, end };
+        }
+
+        Self {
+            start: CellRef::new(start.row.min(end.row), start.col.min(end.col)),
+            end: CellRef::new(start.row.max(end.row), start.col.max(end.col)),
+        }
+    }
+
+    /// Number of rows covered by the range
+    pub fn height(&self) -> u32 {
+        self.end.row - self.start.row + 1
+    }
+
+    /// Number of columns covered by the range
+    pub fn width(&self) -> u32 {
+        self.end.col - self.start.col + 1
+    }
+
+    /// Cell at a 0-based (row, col) offset from the top-left corner
+    pub fn cell_at(&self, row_offset: u32, col_offset: u32) -> CellRef {
+        CellRef::new(self.start.row + row_offset, self.start.col + col_offset)
+    }
+
+    /// All cells in the range, row by row
+    pub fn cells(&self) -> impl Iterator<Item = CellRef> + '_ {
+        (0..self.height()).flat_map(move |row| (0..self.width()).map(move |col| self.cell_at(row, col)))
+    }
+
     /// Convert to Excel notation
     pub fn to_excel(&self) -> String {
         format!("{}:{}", self.start.to_excel(), self.end.to_excel())
@@ -174,6 +320,9 @@ mod tests {
             "1",
             "A0",      // Row 0 is invalid
             "A1048577", // Beyond Excel limit
+            "XFE1",    // One column past XFD
+            "AAAA1",   // Four letters is always past XFD
+            "ZZZZZZZZZZZZZZ1", // Would overflow without the cap
             "@1",      // Invalid character
         ];
         
@@ -183,4 +332,93 @@ mod tests {
             println!("✅ Correctly rejected: '{}'", case);
         }
     }
+    
+    /// The original String-building parser, kept to check the fast path against
+    fn parse_with_strings(excel_ref: &str) -> Result<CellRef, String> {
+        let excel_ref = excel_ref.trim();
+        if excel_ref.is_empty() {
+            return Err("Empty cell reference".to_string());
+        }
+        
+        let mut chars = excel_ref.chars().peekable();
+        let col_abs = chars.next_if_eq(&'$').is_some();
+        let mut col_str = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+            col_str.push(c.to_ascii_uppercase());
+        }
+        let row_abs = chars.next_if_eq(&'$').is_some();
+        let row_str: String = chars.collect();
+        
+        if col_str.is_empty() {
+            return Err(format!("No column letters in '{}'", excel_ref));
+        }
+        if row_str.is_empty() {
+            return Err(format!("No row number in '{}'", excel_ref));
+        }
+        
+        let col = col_str.chars().rev().enumerate()
+            .map(|(i, c)| ((c as u32) - ('A' as u32) + 1) * 26u32.pow(i as u32))
+            .sum();
+        let row = row_str.parse::<u32>()
+            .map_err(|_| format!("Invalid row number '{}' in '{}'", row_str, excel_ref))?;
+        if row == 0 {
+            return Err("Row number must be at least 1".to_string());
+        }
+        
+        Ok(CellRef { row, col, row_abs, col_abs })
+    }
+    
+    #[test]
+    fn test_fast_parse_matches_original() {
+        let inputs = [
+            "A1", "b2", "Z26", "AA27", "zz100", "XFD1048576", "$A$1", "A$1", "$b7",
+            " C3 ", "", "A", "1", "$", "$$1", "A0", "A1x", "A-1", "A$", "@1", "A 1", "Aé1",
+        ];
+        
+        for input in inputs {
+            let fast = CellRef::parse(input);
+            let original = parse_with_strings(input);
+            assert_eq!(fast, original, "Mismatch for '{}'", input);
+            if let (Ok(fast), Ok(original)) = (fast, original) {
+                assert_eq!((fast.row_abs, fast.col_abs), (original.row_abs, original.col_abs));
+            }
+        }
+        
+        // Every column name up to XFD; the original parser had no upper bound
+        for col in 1..=18278 {
+            let input = format!("{}{}", CellRef::new(1, col).to_excel(), col);
+            if col <= MAX_COLS {
+                assert_eq!(CellRef::parse(&input), parse_with_strings(&input), "Mismatch for '{}'", input);
+            } else {
+                assert!(CellRef::parse(&input).is_err(), "Should reject '{}'", input);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_r1c1_round_trip() {
+        let r5c3 = CellRef::parse_r1c1("R5C3").unwrap();
+        assert_eq!(r5c3, CellRef::parse("C5").unwrap());
+        assert_eq!(r5c3.to_r1c1(), "R5C3");
+        assert_eq!(CellRef::parse("C5").unwrap().to_r1c1(), "R5C3");
+        assert_eq!(CellRef::parse_r1c1("r3c4").unwrap().to_style(ReferenceStyle::A1), "$D$3");
+        assert_eq!(CellRef::parse_r1c1(&CellRef::new(1048576, 16384).to_r1c1()).unwrap().to_excel(), "$XFD$1048576");
+
+        for bad in ["R0C1", "C5", "R5", "R-1C2", "R[-1]C", "RC3", "R5C3x", "R1C16385", "R1048577C1"] {
+            assert!(CellRef::parse_r1c1(bad).is_err(), "{} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn test_absolute_references() {
+        let cell = CellRef::parse("$A$1").unwrap();
+        assert!(cell.row_abs && cell.col_abs);
+        assert_eq!(cell.to_excel(), "$A$1");
+        assert_eq!(cell, CellRef::parse("A1").unwrap());
+        
+        let mixed = CellRef::parse("B$3").unwrap();
+        assert_eq!(mixed.offset(2, 1).unwrap().to_excel(), "C$3");
+        assert_eq!(CellRef::parse("$B3").unwrap().offset(2, 1).unwrap().to_excel(), "$B5");
+        assert!(CellRef::parse("A1").unwrap().offset(-1, 0).is_none());
+    }
 }
\ No newline at end of file
@@ -1,8 +1,82 @@
 //! Export functionality for Quantum Sheets
-//! Supports CSV, JSON, and future Excel export
+//! Supports CSV, JSON, Parquet (`parquet` feature), and future Excel export
 
+#[cfg(feature = "parquet")]
+mod parquet;
+
+use std::borrow::Cow;
 use std::fs;
-use std::io::Write;
+use std::io::{BufWriter, Write};
+
+use crate::excel::{column_letters, CellRange, CellRef};
+use crate::formula::evaluator::CellValue;
+
+/// Most cells `range_to_csv` and `range_to_json` will export in one call
+pub const MAX_EXPORT_CELLS: u64 = 10_000_000;
+
+/// How numbers are shown on screen and in exports; stored values are never rounded.
+/// The default prints exactly what each output printed before (no grouping, no
+/// prefix, each output's own precision).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// Fixed digits after the decimal point; None keeps each output's own precision
+    pub decimals: Option<usize>,
+    /// Groups thousands, e.g. ',' for 1,234,567
+    pub thousands_separator: Option<char>,
+    pub decimal_separator: char,
+    /// Put before the digits, after any minus sign: "-$5.00"
+    pub currency: Option<String>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self { decimals: None, thousands_separator: None, decimal_separator: '.', currency: None }
+    }
+}
+
+impl NumberFormat {
+    /// Format with `decimals`, or the shortest form when none are set (5, 2.25)
+    pub fn format(&self, value: f64) -> String {
+        self.render(value, self.decimals)
+    }
+
+    /// Format with `decimals`, falling back to `default_decimals` when none are set
+    pub fn format_with_decimals(&self, value: f64, default_decimals: usize) -> String {
+        self.render(value, Some(self.decimals.unwrap_or(default_decimals)))
+    }
+
+    fn render(&self, value: f64, decimals: Option<usize>) -> String {
+        if !value.is_finite() {
+            return value.to_string();
+        }
+        let digits = match decimals {
+            Some(decimals) => format!("{:.*}", decimals, value.abs()),
+            None => value.abs().to_string(),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+
+        let mut formatted = String::new();
+        if value.is_sign_negative() && digits.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            formatted.push('-');
+        }
+        if let Some(currency) = &self.currency {
+            formatted.push_str(currency);
+        }
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i) % 3 == 0 {
+                if let Some(separator) = self.thousands_separator {
+                    formatted.push(separator);
+                }
+            }
+            formatted.push(digit);
+        }
+        if !fraction.is_empty() {
+            formatted.push(self.decimal_separator);
+            formatted.push_str(fraction);
+        }
+        formatted
+    }
+}
 
 /// Export data to different formats
 pub struct Exporter;
@@ -10,11 +84,20 @@ pub struct Exporter;
 impl Exporter {
     /// Export grid data to CSV
     pub fn grid_to_csv(grid: &crate::grid::QuantumGrid, filename: &str) -> Result<(), String> {
+        Self::grid_to_csv_with_delimiter(grid, filename, ',')
+    }
+    
+    /// Export grid data to CSV separated by `delimiter` (e.g. `;` or `\t`)
+    pub fn grid_to_csv_with_delimiter(
+        grid: &crate::grid::QuantumGrid,
+        filename: &str,
+        delimiter: char,
+    ) -> Result<(), String> {
         let mut file = fs::File::create(filename)
             .map_err(|e| format!("Failed to create CSV file: {}", e))?;
         
         // Simple implementation - we'll enhance this later
-        writeln!(file, "Column,Sum,Count")
+        writeln!(file, "{}", Self::csv_line(&["Column", "Sum", "Count"], delimiter))
             .map_err(|e| format!("Failed to write CSV header: {}", e))?;
         
         for (col_idx, column) in grid.columns() {  // Use the public getter
@@ -27,13 +110,164 @@ impl Exporter {
             let sum: f64 = column.sum();  // Explicit type annotation
             let count: usize = column.count();  // Explicit type annotation
             
-            writeln!(file, "{},{:.2},{}", col_name, sum, count)
+            let fields = [col_name, grid.number_format().format_with_decimals(sum, 2), count.to_string()];
+            writeln!(file, "{}", Self::csv_line(&fields, delimiter))
                 .map_err(|e| format!("Failed to write CSV row: {}", e))?;
         }
         
         Ok(())
     }
     
+    /// Export just the cells of `range` (e.g. "A1:D50") to CSV, one line per row,
+    /// with formulas evaluated and numbers in the grid's number format. Rows are
+    /// written as they're evaluated; see `export_range` for the size limits.
+    pub fn range_to_csv(grid: &crate::grid::QuantumGrid, range: &str, filename: &str) -> Result<(), String> {
+        let range = Self::export_range(grid, range)?;
+        let file = fs::File::create(filename)
+            .map_err(|e| format!("Failed to create CSV file: {}", e))?;
+        let mut file = BufWriter::new(file);
+        
+        for row in 0..range.height() {
+            let fields: Vec<String> = (0..range.width())
+                .map(|col| match grid.get_cell_value(&range.cell_at(row, col)) {
+                    CellValue::Number(n) => grid.number_format().format(n),
+                    other => other.to_string(),
+                })
+                .collect();
+            writeln!(file, "{}", Self::csv_line(&fields, ','))
+                .map_err(|e| format!("Failed to write CSV row: {}", e))?;
+        }
+        
+        file.flush().map_err(|e| format!("Failed to write CSV file: {}", e))
+    }
+    
+    /// Export just the cells of `range` to JSON as an array of rows, with formulas
+    /// evaluated: numbers stay numbers, empty cells are null and errors are their text.
+    /// Rows are written as they're evaluated, like `range_to_csv`.
+    pub fn range_to_json(grid: &crate::grid::QuantumGrid, range: &str, filename: &str) -> Result<(), String> {
+        let range = Self::export_range(grid, range)?;
+        let file = fs::File::create(filename)
+            .map_err(|e| format!("Failed to create JSON file: {}", e))?;
+        let mut file = BufWriter::new(file);
+        let write_error = |e: std::io::Error| format!("Failed to write JSON file: {}", e);
+        
+        write!(file, "[").map_err(write_error)?;
+        for row in 0..range.height() {
+            let values: Vec<serde_json::Value> = (0..range.width())
+                .map(|col| match grid.get_cell_value(&range.cell_at(row, col)) {
+                    CellValue::Number(n) => serde_json::json!(n),
+                    CellValue::Empty => serde_json::Value::Null,
+                    other => serde_json::Value::String(other.to_string()),
+                })
+                .collect();
+            let separator = if row == 0 { "" } else { "," };
+            let line = serde_json::to_string(&values)
+                .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+            write!(file, "{}\n  {}", separator, line).map_err(write_error)?;
+        }
+        writeln!(file, "\n]").map_err(write_error)?;
+        file.flush().map_err(write_error)
+    }
+    
+    /// Parse a range given as text for the range exports. It must lie inside the
+    /// grid's limits and hold at most `MAX_EXPORT_CELLS` cells, so a typo like
+    /// "A1:XFD1048576" fails fast instead of evaluating billions of cells.
+    fn export_range(grid: &crate::grid::QuantumGrid, range: &str) -> Result<CellRange, String> {
+        if range.trim().is_empty() {
+            return Err("No range given to export".to_string());
+        }
+        let range = CellRange::parse(range.trim())
+            .map_err(|e| format!("Cannot export range '{}': {}", range, e))?;
+        
+        let limits = grid.limits();
+        if range.end.row > limits.max_rows || range.end.col > limits.max_cols {
+            return Err(format!(
+                "Cannot export range '{}': it is beyond the grid limit of {} rows x {} columns",
+                range.to_excel(), limits.max_rows, limits.max_cols
+            ));
+        }
+        let cells = range.height() as u64 * range.width() as u64;
+        if cells > MAX_EXPORT_CELLS {
+            return Err(format!(
+                "Cannot export range '{}': {} cells is more than the limit of {}",
+                range.to_excel(), cells, MAX_EXPORT_CELLS
+            ));
+        }
+        Ok(range)
+    }
+    
+    /// Quote a CSV field (RFC 4180) if it contains the delimiter, quotes or newlines;
+    /// embedded quotes are doubled
+    pub fn csv_field(field: &str, delimiter: char) -> Cow<'_, str> {
+        if field.contains([delimiter, '"', '\n', '\r']) {
+            Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+        } else {
+            Cow::Borrowed(field)
+        }
+    }
+    
+    /// Join fields into one CSV line (without the line ending), quoting as needed
+    pub fn csv_line<S: AsRef<str>>(fields: &[S], delimiter: char) -> String {
+        fields.iter()
+            .map(|field| Self::csv_field(field.as_ref(), delimiter))
+            .collect::<Vec<_>>()
+            .join(&delimiter.to_string())
+    }
+    
+    /// Canonical JSON document for a grid: every non-empty cell with its address,
+    /// typed value or text, formula and metadata (same format as `QuantumGrid::to_json`)
+    pub fn grid_to_json(grid: &crate::grid::QuantumGrid) -> String {
+        grid.to_json()
+    }
+    
+    /// The grid's used range as an HTML `<table>`, for previews in web pages and
+    /// emails: a header row of column letters, then one row per grid row led by its
+    /// number. Formula cells show their evaluated value, numbers use the grid's
+    /// number format, and text is HTML-escaped.
+    pub fn grid_to_html(grid: &crate::grid::QuantumGrid) -> String {
+        let mut html = String::from("<table>\n");
+        let Some(used) = grid.used_range() else {
+            html.push_str("</table>\n");
+            return html;
+        };
+        
+        html.push_str("  <tr><th></th>");
+        for col in 1..=used.end.col {
+            html.push_str(&format!("<th>{}</th>", column_letters(col)));
+        }
+        html.push_str("</tr>\n");
+        
+        for row in 1..=used.end.row {
+            html.push_str(&format!("  <tr><th>{}</th>", row));
+            for col in 1..=used.end.col {
+                let value = grid.display_value(&CellRef::new(row, col));
+                html.push_str(&format!("<td>{}</td>", Self::html_escape(&value)));
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</table>\n");
+        html
+    }
+    
+    /// Escape the characters HTML treats specially: `& < > " '`
+    pub fn html_escape(text: &str) -> Cow<'_, str> {
+        if !text.contains(['&', '<', '>', '"', '\'']) {
+            return Cow::Borrowed(text);
+        }
+        let mut escaped = String::with_capacity(text.len() + 8);
+        for c in text.chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&#39;"),
+                c => escaped.push(c),
+            }
+        }
+        Cow::Owned(escaped)
+    }
+    
     /// Export data to JSON
     pub fn to_json<T: serde::Serialize>(data: &T, filename: &str) -> Result<(), String> {
         let json = serde_json::to_string_pretty(data)
@@ -53,4 +287,136 @@ impl Exporter {
         println!("✅ Exported to: {}", filename);
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::Importer;
+
+    #[test]
+    fn test_csv_line_round_trips_through_import() {
+        let fields = ["Kathmandu, Nepal", "Say \"hi\"", "plain", "two\nlines"];
+
+        for delimiter in [',', ';', '\t'] {
+            let line = Exporter::csv_line(&fields, delimiter);
+            let records = Importer::parse_csv_with_delimiter(&line, delimiter).unwrap();
+            assert_eq!(records, vec![fields.map(String::from).to_vec()]);
+        }
+
+        assert_eq!(Exporter::csv_line(&fields[..2], ','), "\"Kathmandu, Nepal\",\"Say \"\"hi\"\"\"");
+        assert_eq!(Exporter::csv_line(&fields[..1], ';'), "Kathmandu, Nepal");
+    }
+
+    #[test]
+    fn test_range_exports_only_the_rectangle() {
+        let mut grid = crate::grid::QuantumGrid::new();
+        for row in 1..=10 {
+            grid.set_cell(&format!("A{}", row), row as f64).unwrap();
+            grid.set_text(&format!("C{}", row), "x").unwrap();
+            grid.set_cell(&format!("E{}", row), 0.0).unwrap();
+        }
+        grid.set_formula("B2", "=A2*10").unwrap();
+
+        let path = std::env::temp_dir().join(format!("qs_range_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        Exporter::range_to_csv(&grid, "A2:C4", path).unwrap();
+        let rows = Importer::parse_csv(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().all(|row| row.len() == 3));
+        assert_eq!(rows[0], vec!["2", "20", "x"]);
+        assert_eq!(rows[1], vec!["3", "", "x"]);
+
+        Exporter::range_to_json(&grid, "B1:D2", path).unwrap();
+        let rows: Vec<Vec<serde_json::Value>> = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|row| row.len() == 3));
+        assert_eq!(rows[1], vec![serde_json::json!(20.0), serde_json::json!("x"), serde_json::Value::Null]);
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(Exporter::range_to_csv(&grid, " ", path), Err("No range given to export".to_string()));
+        let err = Exporter::range_to_json(&grid, "A1:B", path).unwrap_err();
+        assert!(err.starts_with("Cannot export range 'A1:B'"), "{}", err);
+    }
+
+    #[test]
+    fn test_oversized_range_exports_are_refused() {
+        let mut grid = crate::grid::QuantumGrid::new();
+        grid.set_cell("A1", 1.0).unwrap();
+        let path = std::env::temp_dir().join(format!("qs_oversized_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let err = Exporter::range_to_csv(&grid, "A1:XFD1048576", path).unwrap_err();
+        assert!(err.contains("17179869184 cells is more than the limit"), "{}", err);
+        assert!(!std::path::Path::new(path).exists()); // Refused before creating the file
+
+        grid.set_limits(crate::grid::GridLimits { max_rows: 100, max_cols: 10 });
+        let err = Exporter::range_to_json(&grid, "A1:A101", path).unwrap_err();
+        assert!(err.contains("beyond the grid limit of 100 rows x 10 columns"), "{}", err);
+    }
+
+    #[test]
+    fn test_grid_to_html_escapes_text() {
+        let mut grid = crate::grid::QuantumGrid::new();
+        grid.set_cell("A1", 1.5).unwrap();
+        grid.set_text("B1", "<b>R&D</b>").unwrap();
+        grid.set_cell("A2", 2.0).unwrap();
+        grid.set_formula("C2", "=A1+A2").unwrap();
+
+        let html = Exporter::grid_to_html(&grid);
+        assert!(html.starts_with("<table>") && html.trim_end().ends_with("</table>"), "{}", html);
+        assert_eq!(html.matches("<tr>").count(), 3); // Header plus two rows
+        assert_eq!(html.matches("<td>").count(), 6);
+        assert!(html.contains("<th>A</th><th>B</th><th>C</th>"), "{}", html);
+        assert!(html.contains("<td>&lt;b&gt;R&amp;D&lt;/b&gt;</td>"), "{}", html);
+        assert!(html.contains("<th>2</th><td>2</td><td></td><td>3.5</td>"), "{}", html);
+        assert!(!html.contains("<b>"));
+
+        assert_eq!(Exporter::grid_to_html(&crate::grid::QuantumGrid::new()), "<table>\n</table>\n");
+        assert_eq!(Exporter::html_escape("\"it's\""), "&quot;it&#39;s&quot;");
+    }
+
+    #[test]
+    fn test_number_format_groups_thousands() {
+        let format = NumberFormat { decimals: Some(2), thousands_separator: Some(','), ..NumberFormat::default() };
+        assert_eq!(format.format(1234567.5), "1,234,567.50");
+        assert_eq!(format.format(999.0), "999.00");
+
+        let euros = NumberFormat {
+            thousands_separator: Some('.'),
+            decimal_separator: ',',
+            currency: Some("€".to_string()),
+            ..NumberFormat::default()
+        };
+        assert_eq!(euros.format(-1234.25), "-€1.234,25");
+        assert_eq!(euros.format_with_decimals(-0.001, 2), "€0,00");
+
+        // The default changes nothing
+        assert_eq!(NumberFormat::default().format(1234567.5), "1234567.5");
+        assert_eq!(NumberFormat::default().format_with_decimals(2.0, 1), format!("{:.1}", 2.0));
+
+        let mut grid = crate::grid::QuantumGrid::new();
+        grid.set_cell("A1", 1234567.5).unwrap();
+        grid.set_number_format(format);
+        let a1 = crate::excel::CellRef::parse("A1").unwrap();
+        assert_eq!(grid.display_value(&a1), "1,234,567.50");
+        assert_eq!(grid.get_value(&a1), Some(1234567.5)); // Stored value untouched
+    }
+
+    #[test]
+    fn test_grid_to_json_lists_cells() {
+        let mut grid = crate::grid::QuantumGrid::new();
+        grid.set_cell("A1", 42.5).unwrap();
+        grid.set_text("B1", "Pune").unwrap();
+        grid.set_formula("C1", "=A1+A1").unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&Exporter::grid_to_json(&grid)).unwrap();
+        assert_eq!(json["cells"][0]["cell"], "A1");
+        assert_eq!(json["cells"][0]["value"], 42.5);
+        assert_eq!(json["cells"][1]["cell"], "B1");
+        assert_eq!(json["cells"][1]["text"], "Pune");
+        assert_eq!(json["cells"][2]["cell"], "C1");
+        assert_eq!(json["cells"][2]["formula"], "=A1 + A1");
+    }
+}
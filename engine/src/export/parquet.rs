@@ -0,0 +1,121 @@
+//! Parquet export: one Arrow column per grid column
+
+use std::fs::File;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow::datatypes::{Field, Schema};
+use parquet::arrow::ArrowWriter;
+
+use super::Exporter;
+use crate::excel::{column_letters, CellRef};
+use crate::formula::evaluator::CellValue;
+use crate::grid::QuantumGrid;
+use crate::storage::ColumnType;
+
+impl Exporter {
+    /// Write the grid's used range to a Parquet file. Each column becomes a nullable
+    /// Arrow array of its narrowest type (int64, float64, or utf8 when it holds any
+    /// text), named by its header or letter. Formula cells export their
+    /// evaluated value; blanks and errors are null.
+    pub fn grid_to_parquet(grid: &QuantumGrid, path: &str) -> Result<(), String> {
+        let (rows, cols) = grid.used_range().map_or((0, 0), |used| (used.end.row, used.end.col));
+
+        let mut fields = Vec::new();
+        let mut arrays = Vec::new();
+        for col in 1..=cols {
+            let values: Vec<CellValue> = (1..=rows).map(|row| grid.get_cell_value(&CellRef::new(row, col))).collect();
+            let name = grid.header(col).map_or_else(|| column_letters(col), str::to_string);
+            let array = column_array(&values);
+            fields.push(Field::new(name, array.data_type().clone(), true));
+            arrays.push(array);
+        }
+
+        let schema = Arc::new(Schema::new(fields));
+        let batch = if arrays.is_empty() {
+            RecordBatch::new_empty(schema.clone())
+        } else {
+            RecordBatch::try_new(schema.clone(), arrays).map_err(|e| format!("Failed to build Arrow batch: {}", e))?
+        };
+
+        let file = File::create(path).map_err(|e| format!("Failed to create Parquet file: {}", e))?;
+        let mut writer = ArrowWriter::try_new(file, schema, None)
+            .map_err(|e| format!("Failed to start Parquet file: {}", e))?;
+        writer.write(&batch).map_err(|e| format!("Failed to write Parquet rows: {}", e))?;
+        writer.close().map_err(|e| format!("Failed to finish Parquet file: {}", e))?;
+        Ok(())
+    }
+}
+
+/// Arrow array for one column's values, using the narrowest type that holds them all.
+/// Grid cells are numbers, never booleans, so a column of 0s and 1s stays int64.
+fn column_array(values: &[CellValue]) -> ArrayRef {
+    let column_type = values.iter().fold(ColumnType::I64, |widest, value| match value {
+        CellValue::Number(n) => widest.max(ColumnType::for_value(*n)),
+        CellValue::Text(_) => ColumnType::Text,
+        CellValue::Empty | CellValue::Error(_) => widest,
+    });
+    let number = |value: &CellValue| match value {
+        CellValue::Number(n) => Some(*n),
+        _ => None,
+    };
+
+    match column_type {
+        ColumnType::Bool | ColumnType::I64 => Arc::new(values.iter().map(|v| number(v).map(|n| n as i64)).collect::<Int64Array>()),
+        ColumnType::F64 => Arc::new(values.iter().map(number).collect::<Float64Array>()),
+        ColumnType::Text => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    CellValue::Number(_) | CellValue::Text(_) => Some(value.to_string()),
+                    CellValue::Empty | CellValue::Error(_) => None,
+                })
+                .collect::<StringArray>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn test_parquet_round_trips_typed_columns() {
+        let mut grid = QuantumGrid::new();
+        for (row, (units, price, city)) in [(3.0, 9.5, "Pune"), (12.0, 20.25, "Delhi"), (7.0, 4.0, "Patna")].iter().enumerate() {
+            let row = row + 1;
+            grid.set_cell(&format!("A{}", row), *units).unwrap();
+            grid.set_cell(&format!("B{}", row), *price).unwrap();
+            grid.set_text(&format!("C{}", row), city).unwrap();
+        }
+        grid.set_header(1, "units").unwrap();
+        grid.set_formula("D1", "=A1+A1").unwrap();
+        for (row, flag) in [0.0, 1.0, 1.0].iter().enumerate() {
+            grid.set_cell(&format!("E{}", row + 1), *flag).unwrap();
+        }
+
+        let path = std::env::temp_dir().join(format!("quantum_export_{}.parquet", std::process::id()));
+        let path = path.to_str().unwrap();
+        Exporter::grid_to_parquet(&grid, path).unwrap();
+
+        let file = File::open(path).unwrap();
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap().build().unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let units = batch.column_by_name("units").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(units.values(), &[3, 12, 7]);
+        let prices = batch.column_by_name("B").unwrap().as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(prices.values(), &[9.5, 20.25, 4.0]);
+        let cities = batch.column_by_name("C").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(cities.value(1), "Delhi");
+        let doubled = batch.column_by_name("D").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(doubled.value(0), 6);
+        assert!(doubled.is_null(1));
+        // 0s and 1s are still numbers, not booleans
+        let flags = batch.column_by_name("E").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(flags.values(), &[0, 1, 1]);
+    }
+}
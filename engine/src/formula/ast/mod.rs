@@ -1,6 +1,6 @@
 //! Abstract Syntax Tree for Excel formulas
 
-use crate::excel::CellRef;
+use crate::excel::{CellRange, CellRef, ReferenceStyle};
 
 /// Excel formula expression
 #[derive(Debug, Clone, PartialEq)]
@@ -8,6 +8,12 @@ pub enum Expr {
     /// Number literal: 42, 3.14
     Number(f64),
     
+    /// Text literal: "inner", "Male"
+    Text(String),
+    
+    /// Name bound by LET or a function parameter: x in LET(x, A1+1, x*x)
+    Name(String),
+    
     /// Cell reference: A1, B2
     CellRef(CellRef),
     
@@ -22,6 +28,16 @@ pub enum Expr {
     
     /// Parentheses: (A1 + B2)
     Group(Box<Expr>),
+    
+    /// Reference to a cell that no longer exists: #REF!
+    RefError,
+}
+
+/// Direction of a structural edit (inserting/deleting rows or columns)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Row,
+    Column,
 }
 
 /// Binary operators
@@ -32,6 +48,13 @@ pub enum BinaryOp {
     Multiply,  // *
     Divide,    // /
     Power,     // ^
+    Equal,        // =
+    NotEqual,     // <>
+    Less,         // <
+    LessEqual,    // <=
+    Greater,      // >
+    GreaterEqual, // >=
+    Concat,       // &
 }
 
 /// Excel function
@@ -52,38 +75,37 @@ impl Formula {
             return Err("Formula must start with '='".to_string());
         }
         
-        // Remove leading '='
-        let expr_str = &formula[1..];
-        
-        // For Day 3, we'll implement a simple parser
-        // Full nom-based parser will come later
-        if expr_str.contains('+') {
-            // Simple addition: =A1+B2
-            let parts: Vec<&str> = expr_str.split('+').collect();
-            if parts.len() == 2 {
-                let left = CellRef::parse(parts[0])?;
-                let right = CellRef::parse(parts[1])?;
-                return Ok(Self::new(Expr::Binary(
-                    Box::new(Expr::CellRef(left)),
-                    BinaryOp::Add,
-                    Box::new(Expr::CellRef(right)),
-                )));
-            }
-        }
-        
-        Err(format!("Could not parse formula: {}", formula))
+        // The same grammar `QuantumAPI::execute` uses, so any formula that runs can be stored
+        Self::parse_advanced(formula).map_err(|_| format!("Could not parse formula: {}", formula))
     }
     
-    /// Convert back to Excel formula string
+    /// Convert back to Excel formula string; same as `to_excel_pretty`
     pub fn to_excel(&self) -> String {
-        format!("={}", self.expr_to_string(&self.expression))
+        self.to_excel_pretty()
+    }
+
+    /// Spaced form, `=A1 + B2` and `SUM(A1, B2)`; the grid shows formulas this way
+    pub fn to_excel_pretty(&self) -> String {
+        self.to_style(ReferenceStyle::A1)
+    }
+
+    /// Unspaced form like Excel's formula bar: `=A1+B2` and `SUM(A1,B2)`
+    pub fn to_excel_compact(&self) -> String {
+        format!("={}", self.expr_to_string(&self.expression, ReferenceStyle::A1, true))
+    }
+    
+    /// Spaced formula string with references written in the given notation
+    pub fn to_style(&self, style: ReferenceStyle) -> String {
+        format!("={}", self.expr_to_string(&self.expression, style, false))
     }
     
-        fn expr_to_string(&self, expr: &Expr) -> String {
+    fn expr_to_string(&self, expr: &Expr, style: ReferenceStyle, compact: bool) -> String {
         match expr {
             Expr::Number(n) => n.to_string(),
-            Expr::CellRef(cell) => cell.to_excel(),
-            Expr::Range(start, end) => format!("{}:{}", start.to_excel(), end.to_excel()),
+            Expr::Text(text) => format!("\"{}\"", text.replace('"', "\"\"")),
+            Expr::Name(name) => name.clone(),
+            Expr::CellRef(cell) => cell.to_style(style),
+            Expr::Range(start, end) => format!("{}:{}", start.to_style(style), end.to_style(style)),
             Expr::Binary(left, op, right) => {
                 let op_str = match op {
                     BinaryOp::Add => "+",
@@ -91,29 +113,315 @@ impl Formula {
                     BinaryOp::Multiply => "*",
                     BinaryOp::Divide => "/",
                     BinaryOp::Power => "^",
+                    BinaryOp::Equal => "=",
+                    BinaryOp::NotEqual => "<>",
+                    BinaryOp::Less => "<",
+                    BinaryOp::LessEqual => "<=",
+                    BinaryOp::Greater => ">",
+                    BinaryOp::GreaterEqual => ">=",
+                    BinaryOp::Concat => "&",
                 };
-                format!("{} {} {}", 
-                    self.expr_to_string(left), 
-                    op_str, 
-                    self.expr_to_string(right))
+                let space = if compact { "" } else { " " };
+                format!("{}{}{}{}{}", 
+                    self.expr_to_string(left, style, compact), 
+                    space, op_str, space,
+                    self.expr_to_string(right, style, compact))
             }
             Expr::Function(name, args) => {
                 let args_str = args.iter()
-                    .map(|arg| self.expr_to_string(arg))
+                    .map(|arg| self.expr_to_string(arg, style, compact))
                     .collect::<Vec<String>>()
-                    .join(", ");
+                    .join(if compact { "," } else { ", " });
                 format!("{}({})", name, args_str)
             }
-            Expr::Group(inner) => format!("({})", self.expr_to_string(inner)),
+            Expr::Group(inner) => format!("({})", self.expr_to_string(inner, style, compact)),
+            Expr::RefError => "#REF!".to_string(),
         }
     }
+
+    /// Does this formula read `cell`, directly or through a range?
+    pub fn references_cell(&self, cell: &CellRef) -> bool {
+        expr_references(&self.expression, cell)
+    }
+
+    /// Cells this formula reads directly, ranges expanded, in first-seen order
+    pub fn dependencies(&self) -> Vec<CellRef> {
+        let mut cells = Vec::new();
+        collect_dependencies(&self.expression, &mut cells);
+        cells
+    }
+
+    /// Single-cell references as written (ranges not expanded), each once, in first-seen order
+    pub fn referenced_cells(&self) -> Vec<CellRef> {
+        let mut cells = Vec::new();
+        walk(&self.expression, &mut |expr| {
+            if let Expr::CellRef(cell) = expr {
+                if !cells.contains(cell) {
+                    cells.push(*cell);
+                }
+            }
+        });
+        cells
+    }
+
+    /// Range references as written, each once, in first-seen order
+    pub fn referenced_ranges(&self) -> Vec<CellRange> {
+        let mut ranges = Vec::new();
+        walk(&self.expression, &mut |expr| {
+            if let Expr::Range(start, end) = expr {
+                let range = CellRange::new(*start, *end);
+                if !ranges.contains(&range) {
+                    ranges.push(range);
+                }
+            }
+        });
+        ranges
+    }
+
+    /// Bare names read that no LET in the formula binds (such as function parameters),
+    /// each once, in first-seen order
+    pub fn free_names(&self) -> Vec<String> {
+        let mut bound = Vec::new();
+        walk(&self.expression, &mut |expr| {
+            if let Expr::Function(name, args) = expr {
+                if name == "LET" && !args.is_empty() {
+                    for pair in args[..args.len() - 1].chunks(2) {
+                        if let Expr::Name(name) = &pair[0] {
+                            bound.push(name.to_uppercase());
+                        }
+                    }
+                }
+            }
+        });
+
+        let mut names: Vec<String> = Vec::new();
+        walk(&self.expression, &mut |expr| {
+            if let Expr::Name(name) = expr {
+                if !bound.contains(&name.to_uppercase()) && !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        });
+        names
+    }
+
+    /// Copy of this formula moved by a row/column delta, as when filling or pasting.
+    /// Relative references move with it; `$`-anchored parts stay fixed.
+    pub fn offset(&self, row_delta: i64, col_delta: i64) -> Formula {
+        let mut expression = self.expression.clone();
+        offset_expr(&mut expression, row_delta, col_delta);
+        Formula::new(expression)
+    }
+
+    /// Rewrite references after a row/column is inserted at `at` (1-based).
+    /// Everything at or past `at` moves one step down/right.
+    pub fn shift_for_insert(&mut self, axis: Axis, at: u32) {
+        shift_expr(&mut self.expression, axis, at, true);
+    }
+
+    /// Rewrite references after the row/column `at` (1-based) is deleted.
+    /// References to the deleted cells become `#REF!`; ranges shrink.
+    pub fn shift_for_delete(&mut self, axis: Axis, at: u32) {
+        shift_expr(&mut self.expression, axis, at, false);
+    }
     pub fn parse_advanced(formula: &str) -> Result<Self, String> {
-        use crate::formula::parser::parse_formula_safe;
+        use crate::formula::parser::parse_binary_operation;
         
         if !formula.starts_with('=') {
             return Err("Formula must start with '='".to_string());
         }
         
-        parse_formula_safe(formula)
+        Ok(parse_binary_operation(formula)?)
+    }
+}
+
+/// Index of a reference along an axis
+fn axis_index(cell: &CellRef, axis: Axis) -> u32 {
+    match axis {
+        Axis::Row => cell.row,
+        Axis::Column => cell.col,
     }
-}
\ No newline at end of file
+}
+
+/// Copy of `cell` with its index along `axis` replaced
+fn with_axis_index(cell: &CellRef, axis: Axis, index: u32) -> Option<CellRef> {
+    let moved = match axis {
+        Axis::Row => CellRef { row: index, ..*cell },
+        Axis::Column => CellRef { col: index, ..*cell },
+    };
+    if moved.is_valid() { Some(moved) } else { None }
+}
+
+fn shift_expr(expr: &mut Expr, axis: Axis, at: u32, insert: bool) {
+    match expr {
+        Expr::CellRef(cell) => {
+            let index = axis_index(cell, axis);
+            let shifted = if insert {
+                if index >= at { with_axis_index(cell, axis, index + 1) } else { Some(*cell) }
+            } else if index == at {
+                None
+            } else if index > at {
+                with_axis_index(cell, axis, index - 1)
+            } else {
+                Some(*cell)
+            };
+
+            *expr = match shifted {
+                Some(cell) => Expr::CellRef(cell),
+                None => Expr::RefError,
+            };
+        }
+        Expr::Range(start, end) => {
+            let (lo, hi) = (axis_index(start, axis), axis_index(end, axis));
+            let (new_lo, new_hi) = if insert {
+                (if lo >= at { lo + 1 } else { lo }, if hi >= at { hi + 1 } else { hi })
+            } else if lo == at && hi == at {
+                // The whole range was deleted
+                *expr = Expr::RefError;
+                return;
+            } else {
+                (if lo > at { lo - 1 } else { lo }, if hi >= at { hi - 1 } else { hi })
+            };
+
+            *expr = match (with_axis_index(start, axis, new_lo), with_axis_index(end, axis, new_hi)) {
+                (Some(start), Some(end)) => Expr::Range(start, end),
+                _ => Expr::RefError,
+            };
+        }
+        Expr::Binary(left, _, right) => {
+            shift_expr(left, axis, at, insert);
+            shift_expr(right, axis, at, insert);
+        }
+        Expr::Function(_, args) => {
+            for arg in args {
+                shift_expr(arg, axis, at, insert);
+            }
+        }
+        Expr::Group(inner) => shift_expr(inner, axis, at, insert),
+        Expr::Number(_) | Expr::Text(_) | Expr::Name(_) | Expr::RefError => {}
+    }
+}
+
+fn expr_references(expr: &Expr, cell: &CellRef) -> bool {
+    match expr {
+        Expr::CellRef(target) => target == cell,
+        Expr::Range(start, end) => {
+            cell.row >= start.row.min(end.row) && cell.row <= start.row.max(end.row) &&
+            cell.col >= start.col.min(end.col) && cell.col <= start.col.max(end.col)
+        }
+        Expr::Binary(left, _, right) => expr_references(left, cell) || expr_references(right, cell),
+        Expr::Function(_, args) => args.iter().any(|arg| expr_references(arg, cell)),
+        Expr::Group(inner) => expr_references(inner, cell),
+        Expr::Number(_) | Expr::Text(_) | Expr::Name(_) | Expr::RefError => false,
+    }
+}
+
+/// Call `visit` on `expr` and then on every expression inside it, left to right
+fn walk(expr: &Expr, visit: &mut impl FnMut(&Expr)) {
+    visit(expr);
+    match expr {
+        Expr::Binary(left, _, right) => {
+            walk(left, visit);
+            walk(right, visit);
+        }
+        Expr::Function(_, args) => {
+            for arg in args {
+                walk(arg, visit);
+            }
+        }
+        Expr::Group(inner) => walk(inner, visit),
+        Expr::Number(_) | Expr::Text(_) | Expr::Name(_) | Expr::CellRef(_) | Expr::Range(..) | Expr::RefError => {}
+    }
+}
+
+fn collect_dependencies(expr: &Expr, cells: &mut Vec<CellRef>) {
+    fn push(cells: &mut Vec<CellRef>, cell: CellRef) {
+        if !cells.contains(&cell) {
+            cells.push(cell);
+        }
+    }
+    match expr {
+        Expr::CellRef(cell) => push(cells, *cell),
+        Expr::Range(start, end) => {
+            for row in start.row.min(end.row)..=start.row.max(end.row) {
+                for col in start.col.min(end.col)..=start.col.max(end.col) {
+                    push(cells, CellRef::new(row, col));
+                }
+            }
+        }
+        Expr::Binary(left, _, right) => {
+            collect_dependencies(left, cells);
+            collect_dependencies(right, cells);
+        }
+        Expr::Function(_, args) => {
+            for arg in args {
+                collect_dependencies(arg, cells);
+            }
+        }
+        Expr::Group(inner) => collect_dependencies(inner, cells),
+        Expr::Number(_) | Expr::Text(_) | Expr::Name(_) | Expr::RefError => {}
+    }
+}
+
+fn offset_expr(expr: &mut Expr, row_delta: i64, col_delta: i64) {
+    match expr {
+        Expr::CellRef(cell) => {
+            *expr = match cell.offset(row_delta, col_delta) {
+                Some(cell) => Expr::CellRef(cell),
+                None => Expr::RefError,
+            };
+        }
+        Expr::Range(start, end) => {
+            *expr = match (start.offset(row_delta, col_delta), end.offset(row_delta, col_delta)) {
+                (Some(start), Some(end)) => Expr::Range(start, end),
+                _ => Expr::RefError,
+            };
+        }
+        Expr::Binary(left, _, right) => {
+            offset_expr(left, row_delta, col_delta);
+            offset_expr(right, row_delta, col_delta);
+        }
+        Expr::Function(_, args) => {
+            for arg in args {
+                offset_expr(arg, row_delta, col_delta);
+            }
+        }
+        Expr::Group(inner) => offset_expr(inner, row_delta, col_delta),
+        Expr::Number(_) | Expr::Text(_) | Expr::Name(_) | Expr::RefError => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formula::parser::parse_formula_with_ops;
+    use crate::operations::OperationRegistry;
+
+    #[test]
+    fn test_referenced_cells_and_ranges_walk_nested_calls() {
+        let formula = parse_formula_with_ops("=IF(A1>0, SUM(B1:B3), (C1 + A1))", &OperationRegistry::new()).unwrap();
+
+        let cells: Vec<String> = formula.referenced_cells().iter().map(|cell| cell.to_excel()).collect();
+        assert_eq!(cells, ["A1", "C1"]);
+        assert_eq!(formula.referenced_ranges(), vec![CellRange::parse("B1:B3").unwrap()]);
+    }
+
+    #[test]
+    fn test_compact_output_round_trips() {
+        let registry = OperationRegistry::new();
+        for canonical in [
+            "=A1+B2*C3",
+            "=(A1-1)/$B$2^2",
+            "=SUM(A1:A3,B1)",
+            "=IF(A1>=0,\"say \"\"yes\"\"\",ROUND(A1/3,2))&\" kg\"",
+        ] {
+            let formula = parse_formula_with_ops(canonical, &registry).unwrap();
+            assert_eq!(formula.to_excel_compact(), canonical);
+            assert_eq!(parse_formula_with_ops(&formula.to_excel_pretty(), &registry).unwrap(), formula);
+        }
+
+        let formula = parse_formula_with_ops("=A1  +B2", &registry).unwrap();
+        assert_eq!(formula.to_excel_pretty(), "=A1 + B2");
+        assert_eq!(formula.to_excel_compact(), "=A1+B2");
+    }
+}
@@ -0,0 +1,82 @@
+//! Criteria for conditional functions: ">100", "<=5", "<>0", "Male"
+
+use super::CellValue;
+
+/// Comparison in a criterion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+}
+
+/// Parsed criterion, e.g. `>=100` or `Male`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Criterion {
+    op: CompareOp,
+    number: Option<f64>, // Set when the operand looks numeric
+    text: String,
+}
+
+impl Criterion {
+    /// Parse a criterion value. A number means "equal to"; text may start with
+    /// `>`, `<`, `>=`, `<=`, `=` or `<>`, otherwise it's a plain equality match.
+    pub(crate) fn parse(value: &CellValue) -> Self {
+        let raw = match value {
+            CellValue::Number(n) => {
+                return Self { op: CompareOp::Equal, number: Some(*n), text: n.to_string() };
+            }
+            CellValue::Text(text) => text.as_str(),
+            CellValue::Empty | CellValue::Error(_) => "",
+        };
+
+        // Longest operators first so ">=" isn't read as ">"
+        let (op, operand) = [
+            (">=", CompareOp::GreaterEqual),
+            ("<=", CompareOp::LessEqual),
+            ("<>", CompareOp::NotEqual),
+            (">", CompareOp::Greater),
+            ("<", CompareOp::Less),
+            ("=", CompareOp::Equal),
+        ]
+        .iter()
+        .find_map(|(prefix, op)| raw.strip_prefix(prefix).map(|rest| (*op, rest)))
+        .unwrap_or((CompareOp::Equal, raw));
+
+        let operand = operand.trim();
+        Self {
+            op,
+            number: operand.parse::<f64>().ok(),
+            text: operand.to_string(),
+        }
+    }
+
+    /// Does a cell value satisfy this criterion? Blank cells never match.
+    pub(crate) fn matches(&self, value: &CellValue) -> bool {
+        match (value, self.number) {
+            (CellValue::Number(n), Some(target)) => match self.op {
+                CompareOp::Equal => *n == target,
+                CompareOp::NotEqual => *n != target,
+                CompareOp::Greater => *n > target,
+                CompareOp::GreaterEqual => *n >= target,
+                CompareOp::Less => *n < target,
+                CompareOp::LessEqual => *n <= target,
+            },
+            // Text compares case-insensitively, like Excel; only = and <> apply
+            (CellValue::Text(text), _) => {
+                let equal = text.eq_ignore_ascii_case(&self.text);
+                match self.op {
+                    CompareOp::Equal => equal,
+                    CompareOp::NotEqual => !equal,
+                    _ => false,
+                }
+            }
+            // A number never equals a text criterion
+            (CellValue::Number(_), None) => self.op == CompareOp::NotEqual,
+            (CellValue::Empty, _) | (CellValue::Error(_), _) => false,
+        }
+    }
+}
@@ -0,0 +1,1438 @@
+//! Formula evaluator - computes formula trees against the grid
+
+mod criteria;
+
+use std::cmp::Ordering;
+use std::fmt;
+
+pub(crate) use criteria::Criterion;
+use crate::compute::approx_eq;
+use crate::excel::{CellRange, CellRef};
+use crate::formula::ast::{BinaryOp, Expr};
+use crate::grid::QuantumGrid;
+
+/// Value of a cell after evaluation
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellValue {
+    Number(f64),
+    Text(String),
+    Empty,
+    Error(CellError),
+}
+
+/// Excel error values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellError {
+    Ref,          // #REF!
+    Value,        // #VALUE!
+    DivZero,      // #DIV/0!
+    NotAvailable, // #N/A
+    Name,         // #NAME?
+    Num,          // #NUM!
+    Spill,        // #SPILL!
+}
+
+impl fmt::Display for CellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            CellError::Ref => "#REF!",
+            CellError::Value => "#VALUE!",
+            CellError::DivZero => "#DIV/0!",
+            CellError::NotAvailable => "#N/A",
+            CellError::Name => "#NAME?",
+            CellError::Num => "#NUM!",
+            CellError::Spill => "#SPILL!",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl fmt::Display for CellValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellValue::Number(n) => write!(f, "{}", n),
+            CellValue::Text(text) => write!(f, "{}", text),
+            CellValue::Empty => Ok(()),
+            CellValue::Error(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// How text is treated where a number is expected (e.g. `"5"+1`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextCoercion {
+    /// Text in arithmetic is always #VALUE!
+    #[default]
+    Strict,
+    /// Numeric-looking text is parsed ("5" → 5), anything else is #VALUE!
+    Coerce,
+}
+
+/// Deepest chain of user function calls before giving up (catches recursive definitions)
+const MAX_CALL_DEPTH: usize = 32;
+
+/// Evaluates expressions, following formula cells and detecting cycles
+pub struct Evaluator<'a> {
+    grid: &'a QuantumGrid,
+    stack: Vec<CellRef>, // Formula cells currently being evaluated
+    coercion: TextCoercion,
+    epsilon: f64, // Numbers this close compare equal
+    bindings: Vec<(String, CellValue)>, // LET names in scope, innermost last
+    calls: usize, // User function calls currently being evaluated
+}
+
+impl<'a> Evaluator<'a> {
+    /// Create an evaluator reading from a grid, using the grid's coercion policy
+    pub fn new(grid: &'a QuantumGrid) -> Self {
+        Self {
+            grid,
+            stack: Vec::new(),
+            coercion: grid.text_coercion(),
+            epsilon: grid.comparison_epsilon(),
+            bindings: Vec::new(),
+            calls: 0,
+        }
+    }
+
+    /// Override the text coercion policy
+    pub fn with_coercion(mut self, coercion: TextCoercion) -> Self {
+        self.coercion = coercion;
+        self
+    }
+
+    /// Value of a cell, evaluating it if it holds a formula
+    pub fn eval_cell(&mut self, cell: &CellRef) -> CellValue {
+        match self.grid.formulas().get(cell) {
+            Some(formula) => {
+                // Cached results were computed under the grid's own coercion policy
+                let cacheable = self.coercion == self.grid.text_coercion();
+                if cacheable {
+                    if let Some(value) = self.grid.cached_value(cell) {
+                        return value;
+                    }
+                }
+
+                // A formula that (indirectly) reads itself can't be computed
+                if self.stack.contains(cell) {
+                    return CellValue::Error(CellError::Ref);
+                }
+
+                // LET names belong to the formula that binds them, not to cells it reads
+                let outer_bindings = std::mem::take(&mut self.bindings);
+                self.stack.push(*cell);
+                self.grid.record_evaluation();
+                let value = self.eval(&formula.expression);
+                self.stack.pop();
+                self.bindings = outer_bindings;
+
+                if cacheable {
+                    self.grid.cache_value(*cell, value.clone());
+                }
+                value
+            }
+            None => {
+                if let Some(text) = self.grid.get_text(cell) {
+                    return CellValue::Text(text.to_string());
+                }
+                match self.grid.get_value(cell) {
+                    Some(n) => CellValue::Number(n),
+                    None => CellValue::Empty,
+                }
+            }
+        }
+    }
+
+    /// Evaluate an expression
+    pub fn eval(&mut self, expr: &Expr) -> CellValue {
+        match expr {
+            Expr::Number(n) => CellValue::Number(*n),
+            Expr::Text(text) => CellValue::Text(text.clone()),
+            Expr::Name(name) => self.bindings.iter().rev()
+                .find(|(bound, _)| bound.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+                .unwrap_or(CellValue::Error(CellError::Name)),
+            Expr::CellRef(cell) => self.eval_cell(cell),
+            // A bare range has no single value (ranges are function arguments)
+            Expr::Range(_, _) => CellValue::Error(CellError::Value),
+            Expr::Binary(left, op, right) => self.eval_binary(left, *op, right),
+            Expr::Function(name, args) => self.eval_function(name, args),
+            Expr::Group(inner) => self.eval(inner),
+            Expr::RefError => CellValue::Error(CellError::Ref),
+        }
+    }
+
+    fn eval_binary(&mut self, left: &Expr, op: BinaryOp, right: &Expr) -> CellValue {
+        let a = self.eval(left);
+        let b = self.eval(right);
+        self.apply_binary(a, op, b)
+    }
+
+    /// Apply an operator to two evaluated values.
+    /// Comparisons evaluate to 1 (true) or 0 (false).
+    fn apply_binary(&self, a: CellValue, op: BinaryOp, b: CellValue) -> CellValue {
+        if op == BinaryOp::Concat {
+            return match (a, b) {
+                (CellValue::Error(e), _) | (_, CellValue::Error(e)) => CellValue::Error(e),
+                (a, b) => CellValue::Text(format!("{}{}", a, b)),
+            };
+        }
+
+        let ordering = match op {
+            BinaryOp::Equal | BinaryOp::NotEqual | BinaryOp::Less
+            | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => {
+                match (&a, &b) {
+                    // Rounding error in arithmetic shouldn't decide a comparison
+                    (CellValue::Number(x), CellValue::Number(y)) if approx_eq(*x, *y, self.epsilon) => Ordering::Equal,
+                    _ => match compare(&a, &b) {
+                        Ok(ordering) => ordering,
+                        Err(e) => return CellValue::Error(e),
+                    },
+                }
+            }
+            _ => Ordering::Equal,
+        };
+
+        let truth = |holds: bool| CellValue::Number(if holds { 1.0 } else { 0.0 });
+        match op {
+            BinaryOp::Equal => return truth(ordering == Ordering::Equal),
+            BinaryOp::NotEqual => return truth(ordering != Ordering::Equal),
+            BinaryOp::Less => return truth(ordering == Ordering::Less),
+            BinaryOp::LessEqual => return truth(ordering != Ordering::Greater),
+            BinaryOp::Greater => return truth(ordering == Ordering::Greater),
+            BinaryOp::GreaterEqual => return truth(ordering != Ordering::Less),
+            _ => {}
+        }
+
+        let (a, b) = match (self.to_number(a), self.to_number(b)) {
+            (Ok(a), Ok(b)) => (a, b),
+            (Err(e), _) | (_, Err(e)) => return CellValue::Error(e),
+        };
+
+        match op {
+            BinaryOp::Add => CellValue::Number(a + b),
+            BinaryOp::Subtract => CellValue::Number(a - b),
+            BinaryOp::Multiply => CellValue::Number(a * b),
+            BinaryOp::Divide if b == 0.0 => CellValue::Error(CellError::DivZero),
+            BinaryOp::Divide => CellValue::Number(a / b),
+            BinaryOp::Power => CellValue::Number(a.powf(b)),
+            _ => unreachable!("comparisons and & are handled above"),
+        }
+    }
+
+    /// IF(condition, value_if_true[, value_if_false])
+    fn eval_if(&mut self, args: &[Expr]) -> CellValue {
+        if args.len() < 2 || args.len() > 3 {
+            return CellValue::Error(CellError::Value);
+        }
+
+        let condition = self.eval(&args[0]);
+        match truthy(&condition) {
+            Ok(true) => self.eval(&args[1]),
+            Ok(false) => args.get(2).map(|arg| self.eval(arg)).unwrap_or(CellValue::Number(0.0)),
+            Err(e) => CellValue::Error(e),
+        }
+    }
+
+    /// Evaluate an expression element-wise, expanding ranges into arrays.
+    /// Scalars broadcast against arrays; e.g. `IF(A1:A3>100, A1:A3, 0)`
+    /// yields one value per cell.
+    fn eval_array(&mut self, expr: &Expr) -> Vec<CellValue> {
+        match expr {
+            Expr::Range(start, end) => CellRange::new(*start, *end)
+                .cells()
+                .map(|cell| self.eval_cell(&cell))
+                .collect(),
+            Expr::Group(inner) => self.eval_array(inner),
+            Expr::Binary(left, op, right) => {
+                let left = self.eval_array(left);
+                let right = self.eval_array(right);
+                broadcast(&[left, right])
+                    .into_iter()
+                    .map(|values| {
+                        let [a, b]: [CellValue; 2] = values.try_into().expect("two operands");
+                        self.apply_binary(a, *op, b)
+                    })
+                    .collect()
+            }
+            Expr::Function(name, args) if name == "IF" && (2..=3).contains(&args.len()) => {
+                let mut operands: Vec<Vec<CellValue>> =
+                    args.iter().map(|arg| self.eval_array(arg)).collect();
+                if operands.len() == 2 {
+                    operands.push(vec![CellValue::Number(0.0)]);
+                }
+
+                broadcast(&operands)
+                    .into_iter()
+                    .map(|values| match truthy(&values[0]) {
+                        Ok(true) => values[1].clone(),
+                        Ok(false) => values[2].clone(),
+                        Err(e) => CellValue::Error(e),
+                    })
+                    .collect()
+            }
+            _ => vec![self.eval(expr)],
+        }
+    }
+
+    fn eval_function(&mut self, name: &str, args: &[Expr]) -> CellValue {
+        if name == "SUMIF" || name == "COUNTIF" {
+            return self.eval_conditional(name, args);
+        }
+        if name == "IF" {
+            return self.eval_if(args);
+        }
+        if name == "CAGR" {
+            return self.eval_cagr(args);
+        }
+        if name == "LET" {
+            return self.eval_let(args);
+        }
+        if name == "CONCAT" {
+            return self.eval_concat(args);
+        }
+        if name == "VLOOKUP" {
+            return self.eval_vlookup(args);
+        }
+        if name == "INDEX" {
+            return self.eval_index(args);
+        }
+        if name == "MATCH" {
+            return self.eval_match(args);
+        }
+        if name == "COUNT" || name == "COUNTA" {
+            return self.eval_count(args, name == "COUNTA");
+        }
+        if matches!(name, "ROUND" | "FLOOR" | "CEILING" | "ABS") {
+            return self.eval_rounding(name, args);
+        }
+        if name == "PERCENTILE" {
+            return self.eval_percentile(args);
+        }
+        if name == "CORREL" {
+            return self.eval_correl(args);
+        }
+        if name == "SLOPE" || name == "INTERCEPT" {
+            return self.eval_regression(name, args);
+        }
+        if name == "TRIM" || name == "CLEAN" {
+            return self.eval_cleanup(name, args);
+        }
+        if matches!(name, "UPPER" | "LOWER" | "PROPER") {
+            return self.eval_case(name, args);
+        }
+        if matches!(name, "LEN" | "LEFT" | "RIGHT" | "MID") {
+            return self.eval_substring(name, args);
+        }
+        if name == "ROW" || name == "COLUMN" {
+            return self.eval_coordinate(name, args);
+        }
+
+        let numbers = match name {
+            "SUM" | "PRODUCT" | "AVERAGE" | "MIN" | "MAX" => match self.collect_numbers(args) {
+                Ok(numbers) => numbers,
+                Err(e) => return CellValue::Error(e),
+            },
+            _ => return self.eval_user_function(name, args),
+        };
+
+        match name {
+            "SUM" => CellValue::Number(numbers.iter().sum()),
+            // Nothing to multiply gives 1, the identity (Excel shows 0 here)
+            "PRODUCT" => CellValue::Number(crate::compute::optimized_product(&numbers)),
+            "AVERAGE" if numbers.is_empty() => CellValue::Error(CellError::DivZero),
+            "AVERAGE" => CellValue::Number(numbers.iter().sum::<f64>() / numbers.len() as f64),
+            "MIN" => CellValue::Number(numbers.iter().copied().reduce(f64::min).unwrap_or(0.0)),
+            "MAX" => CellValue::Number(numbers.iter().copied().reduce(f64::max).unwrap_or(0.0)),
+            _ => CellValue::Error(CellError::Name),
+        }
+    }
+
+    /// COUNT counts numbers only; COUNTA (`any`) counts every non-empty value,
+    /// text and errors included. Neither fails on what it doesn't count.
+    fn eval_count(&mut self, args: &[Expr], any: bool) -> CellValue {
+        let mut count = 0;
+        for arg in args {
+            for value in self.eval_array(arg) {
+                let counted = match value {
+                    CellValue::Number(_) => true,
+                    CellValue::Empty => false,
+                    CellValue::Text(_) | CellValue::Error(_) => any,
+                };
+                count += counted as usize;
+            }
+        }
+        CellValue::Number(count as f64)
+    }
+
+    /// ROUND(x, digits), FLOOR(x), CEILING(x) and ABS(x). ROUND rounds halves away
+    /// from zero like Excel, and negative digits round to tens, hundreds, ...
+    fn eval_rounding(&mut self, name: &str, args: &[Expr]) -> CellValue {
+        let expected = if name == "ROUND" { 2 } else { 1 };
+        if args.len() != expected {
+            return CellValue::Error(CellError::Value);
+        }
+        let x = match self.number(&args[0]) {
+            Ok(x) => x,
+            Err(e) => return CellValue::Error(e),
+        };
+
+        match name {
+            "ROUND" => match self.number(&args[1]) {
+                Ok(digits) => CellValue::Number(round_half_up(x, digits.trunc() as i32)),
+                Err(e) => CellValue::Error(e),
+            },
+            "FLOOR" => CellValue::Number(x.floor()),
+            "CEILING" => CellValue::Number(x.ceil()),
+            _ => CellValue::Number(x.abs()),
+        }
+    }
+
+    /// PERCENTILE(range, k) with k from 0 to 1, interpolated like PERCENTILE.INC.
+    /// #NUM! for k outside [0, 1] or a range without numbers.
+    fn eval_percentile(&mut self, args: &[Expr]) -> CellValue {
+        if args.len() != 2 {
+            return CellValue::Error(CellError::Value);
+        }
+        let numbers = match self.collect_numbers(&args[..1]) {
+            Ok(numbers) => numbers,
+            Err(e) => return CellValue::Error(e),
+        };
+        match self.number(&args[1]) {
+            Ok(k) => crate::compute::percentile(&numbers, k)
+                .map(CellValue::Number)
+                .unwrap_or(CellValue::Error(CellError::Num)),
+            Err(e) => CellValue::Error(e),
+        }
+    }
+
+    /// CORREL(range1, range2): Pearson correlation of two same-sized ranges. Like
+    /// Excel, only positions where both cells hold numbers are used. #N/A when the
+    /// sizes differ, #DIV/0! when either side has no variance.
+    fn eval_correl(&mut self, args: &[Expr]) -> CellValue {
+        match self.paired_numbers(args) {
+            Ok((x, y)) => crate::compute::correlation(&x, &y)
+                .map(|r| CellValue::Number(r.clamp(-1.0, 1.0)))
+                .unwrap_or(CellValue::Error(CellError::DivZero)),
+            Err(e) => CellValue::Error(e),
+        }
+    }
+
+    /// SLOPE(known_y, known_x) and INTERCEPT(known_y, known_x) of the least-squares
+    /// line, using positions where both hold numbers. #N/A when the sizes differ,
+    /// #DIV/0! when every x is equal.
+    fn eval_regression(&mut self, name: &str, args: &[Expr]) -> CellValue {
+        let (y, x) = match self.paired_numbers(args) {
+            Ok(pairs) => pairs,
+            Err(e) => return CellValue::Error(e),
+        };
+        match crate::compute::linear_regression(&x, &y) {
+            Some((slope, _, _)) if name == "SLOPE" => CellValue::Number(slope),
+            Some((_, intercept, _)) => CellValue::Number(intercept),
+            None => CellValue::Error(CellError::DivZero),
+        }
+    }
+
+    /// Numbers at matching positions of two equal-sized array arguments, skipping
+    /// positions where either side isn't a number
+    fn paired_numbers(&mut self, args: &[Expr]) -> Result<(Vec<f64>, Vec<f64>), CellError> {
+        let [first, second] = args else {
+            return Err(CellError::Value);
+        };
+        let first = self.eval_array(first);
+        let second = self.eval_array(second);
+        if first.len() != second.len() {
+            return Err(CellError::NotAvailable);
+        }
+
+        let mut pairs = (Vec::new(), Vec::new());
+        for (a, b) in first.into_iter().zip(second) {
+            match (a, b) {
+                (CellValue::Error(e), _) | (_, CellValue::Error(e)) => return Err(e),
+                (CellValue::Number(a), CellValue::Number(b)) => {
+                    pairs.0.push(a);
+                    pairs.1.push(b);
+                }
+                _ => {}
+            }
+        }
+        Ok(pairs)
+    }
+
+    /// TRIM(text) strips leading and trailing whitespace; CLEAN(text) removes
+    /// non-printable (control) characters. Numbers and blanks pass through unchanged.
+    fn eval_cleanup(&mut self, name: &str, args: &[Expr]) -> CellValue {
+        let [arg] = args else {
+            return CellValue::Error(CellError::Value);
+        };
+        match self.eval(arg) {
+            CellValue::Text(text) if name == "TRIM" => CellValue::Text(text.trim().to_string()),
+            CellValue::Text(text) => CellValue::Text(text.chars().filter(|c| !c.is_control()).collect()),
+            value => value,
+        }
+    }
+
+    /// UPPER(text), LOWER(text) and PROPER(text), using Unicode case mapping
+    /// (so "straße" uppercases to "STRASSE"). Numbers are converted to their text
+    /// first and a blank gives "". PROPER capitalizes the first letter of each
+    /// word, any run of letters, and lowercases the rest: "o'neil 2nd" → "O'Neil 2Nd".
+    fn eval_case(&mut self, name: &str, args: &[Expr]) -> CellValue {
+        let [arg] = args else {
+            return CellValue::Error(CellError::Value);
+        };
+        let text = match self.eval(arg) {
+            CellValue::Error(e) => return CellValue::Error(e),
+            value => value.to_string(),
+        };
+
+        CellValue::Text(match name {
+            "UPPER" => text.to_uppercase(),
+            "LOWER" => text.to_lowercase(),
+            _ => {
+                let mut proper = String::with_capacity(text.len());
+                let mut in_word = false;
+                for c in text.chars() {
+                    if in_word {
+                        proper.extend(c.to_lowercase());
+                    } else {
+                        proper.extend(c.to_uppercase());
+                    }
+                    in_word = c.is_alphabetic();
+                }
+                proper
+            }
+        })
+    }
+
+    /// LEN(text), LEFT(text[, n]), RIGHT(text[, n]) and MID(text, start, n), counting
+    /// characters rather than bytes. `start` is 1-based, n defaults to 1, and a slice
+    /// running past the end is cut short. #VALUE! for a negative n or a start below 1.
+    fn eval_substring(&mut self, name: &str, args: &[Expr]) -> CellValue {
+        let arity = match name {
+            "LEN" => 1..=1,
+            "MID" => 3..=3,
+            _ => 1..=2,
+        };
+        if !arity.contains(&args.len()) {
+            return CellValue::Error(CellError::Value);
+        }
+        let chars: Vec<char> = match self.eval(&args[0]) {
+            CellValue::Error(e) => return CellValue::Error(e),
+            value => value.to_string().chars().collect(),
+        };
+        if name == "LEN" {
+            return CellValue::Number(chars.len() as f64);
+        }
+
+        let mut numbers = Vec::new();
+        for arg in &args[1..] {
+            match self.number(arg) {
+                Ok(n) if n >= 0.0 => numbers.push(n.trunc() as usize),
+                Ok(_) => return CellValue::Error(CellError::Value),
+                Err(e) => return CellValue::Error(e),
+            }
+        }
+        let n = numbers.last().copied().unwrap_or(1);
+        let start = match name {
+            "MID" if numbers[0] == 0 => return CellValue::Error(CellError::Value),
+            "MID" => numbers[0] - 1,
+            "RIGHT" => chars.len().saturating_sub(n),
+            _ => 0,
+        };
+        CellValue::Text(chars.iter().skip(start).take(n).collect())
+    }
+
+    /// ROW(ref) / COLUMN(ref): 1-based row or column of a reference (a range's
+    /// top-left cell). With no argument, of the formula cell being evaluated;
+    /// #VALUE! outside a formula cell or for an argument that isn't a reference.
+    fn eval_coordinate(&mut self, name: &str, args: &[Expr]) -> CellValue {
+        let cell = match args {
+            [] => match self.stack.last() {
+                Some(cell) => *cell,
+                None => return CellValue::Error(CellError::Value),
+            },
+            [Expr::CellRef(cell)] | [Expr::Range(cell, _)] => *cell,
+            _ => return CellValue::Error(CellError::Value),
+        };
+        let index = if name == "ROW" { cell.row } else { cell.col };
+        CellValue::Number(index as f64)
+    }
+
+    /// LET(name1, value1, [name2, value2, ...], expression).
+    /// Bindings are evaluated in order, so later values can use earlier names.
+    fn eval_let(&mut self, args: &[Expr]) -> CellValue {
+        if args.len() < 3 || args.len().is_multiple_of(2) {
+            return CellValue::Error(CellError::Value);
+        }
+
+        let scope_start = self.bindings.len();
+        let (pairs, body) = args.split_at(args.len() - 1);
+        for pair in pairs.chunks(2) {
+            let Expr::Name(name) = &pair[0] else {
+                self.bindings.truncate(scope_start);
+                return CellValue::Error(CellError::Value);
+            };
+            let value = self.eval(&pair[1]);
+            self.bindings.push((name.clone(), value));
+        }
+
+        let value = self.eval(&body[0]);
+        self.bindings.truncate(scope_start);
+        value
+    }
+
+    /// A function from `QuantumGrid::define_function`. Arguments are evaluated and
+    /// bound to the parameters like LET names (so a bare range binds #VALUE!), then the
+    /// body sees only those names.
+    /// #NAME? if no such function exists, #VALUE! for the wrong number of arguments,
+    /// #NUM! past `MAX_CALL_DEPTH` nested calls (a function that calls itself).
+    fn eval_user_function(&mut self, name: &str, args: &[Expr]) -> CellValue {
+        let grid = self.grid;
+        let Some(function) = grid.user_function(name) else {
+            return CellValue::Error(CellError::Name);
+        };
+        if args.len() != function.params.len() {
+            return CellValue::Error(CellError::Value);
+        }
+        if self.calls >= MAX_CALL_DEPTH {
+            return CellValue::Error(CellError::Num);
+        }
+
+        let values: Vec<CellValue> = args.iter().map(|arg| self.eval(arg)).collect();
+        let outer_bindings = std::mem::replace(&mut self.bindings, function.params.iter().cloned().zip(values).collect());
+        self.calls += 1;
+        let value = self.eval(&function.body.expression);
+        self.calls -= 1;
+        self.bindings = outer_bindings;
+        value
+    }
+
+    /// CAGR(begin, end, periods), or CAGR(range) growing from the first to the
+    /// last number with one period between each consecutive value
+    fn eval_cagr(&mut self, args: &[Expr]) -> CellValue {
+        let (begin, end, periods) = match args {
+            [range @ Expr::Range(_, _)] => {
+                let numbers = match self.collect_numbers(std::slice::from_ref(range)) {
+                    Ok(numbers) => numbers,
+                    Err(e) => return CellValue::Error(e),
+                };
+                match (numbers.first(), numbers.last()) {
+                    (Some(&begin), Some(&end)) => (begin, end, (numbers.len() - 1) as f64),
+                    _ => return CellValue::Error(CellError::Num),
+                }
+            }
+            [begin, end, periods] => match (self.number(begin), self.number(end), self.number(periods)) {
+                (Ok(begin), Ok(end), Ok(periods)) => (begin, end, periods),
+                (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return CellValue::Error(e),
+            },
+            _ => return CellValue::Error(CellError::Value),
+        };
+
+        match crate::compute::cagr(begin, end, periods) {
+            Some(rate) => CellValue::Number(rate),
+            None => CellValue::Error(CellError::Num),
+        }
+    }
+
+    /// CONCAT(value, ...): join values (ranges row by row) into text.
+    /// Numbers use their shortest form, so 5 is "5", not "5.0"; blanks add nothing.
+    fn eval_concat(&mut self, args: &[Expr]) -> CellValue {
+        let mut joined = String::new();
+        for arg in args {
+            for value in self.eval_array(arg) {
+                match value {
+                    CellValue::Error(e) => return CellValue::Error(e),
+                    value => joined.push_str(&value.to_string()),
+                }
+            }
+        }
+        CellValue::Text(joined)
+    }
+
+    /// VLOOKUP(key, range, column[, FALSE]): exact match of `key` in the range's first
+    /// column (text case-insensitively), returning that row's value from the 1-based
+    /// `column`. #N/A if not found, #VALUE!/#REF! for a column outside the range.
+    /// Approximate matching isn't supported, so a fourth argument must be FALSE or 0.
+    fn eval_vlookup(&mut self, args: &[Expr]) -> CellValue {
+        let (key, range, column) = match args {
+            [key, Expr::Range(start, end), column] | [key, Expr::Range(start, end), column, _] => {
+                (key, CellRange::new(*start, *end), column)
+            }
+            _ => return CellValue::Error(CellError::Value),
+        };
+        if let Some(exact) = args.get(3) {
+            if !self.is_false(exact) {
+                return CellValue::Error(CellError::Value);
+            }
+        }
+
+        let key = self.eval(key);
+        if let CellValue::Error(e) = key {
+            return CellValue::Error(e);
+        }
+        let column = match self.number(column) {
+            Ok(column) if column.is_nan() || column < 1.0 => return CellValue::Error(CellError::Value),
+            Ok(column) if column as u32 > range.width() => return CellValue::Error(CellError::Ref),
+            Ok(column) => column as u32 - 1,
+            Err(e) => return CellValue::Error(e),
+        };
+
+        for row in 0..range.height() {
+            let candidate = self.eval_cell(&range.cell_at(row, 0));
+            if exact_match(&key, &candidate) {
+                return self.eval_cell(&range.cell_at(row, column));
+            }
+        }
+        CellValue::Error(CellError::NotAvailable)
+    }
+
+    /// MATCH(value, range[, 0]): 1-based position of the first exact match of `value`
+    /// (text case-insensitively) in a single-row or single-column range. #N/A if
+    /// absent or the range is 2-D. Like VLOOKUP, only exact matching (0) is supported.
+    fn eval_match(&mut self, args: &[Expr]) -> CellValue {
+        let (value, range) = match args {
+            [value, Expr::Range(start, end)] | [value, Expr::Range(start, end), _] => {
+                (value, CellRange::new(*start, *end))
+            }
+            _ => return CellValue::Error(CellError::Value),
+        };
+        if let Some(match_type) = args.get(2) {
+            if !self.is_false(match_type) {
+                return CellValue::Error(CellError::Value);
+            }
+        }
+        if range.width() > 1 && range.height() > 1 {
+            return CellValue::Error(CellError::NotAvailable);
+        }
+
+        let value = self.eval(value);
+        if let CellValue::Error(e) = value {
+            return CellValue::Error(e);
+        }
+        let cells: Vec<CellRef> = range.cells().collect();
+        for (position, cell) in cells.iter().enumerate() {
+            if exact_match(&value, &self.eval_cell(cell)) {
+                return CellValue::Number((position + 1) as f64);
+            }
+        }
+        CellValue::Error(CellError::NotAvailable)
+    }
+
+    /// Whether a flag argument is FALSE or 0, e.g. VLOOKUP's exact-match flag
+    fn is_false(&mut self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Text(text) | Expr::Name(text) => text.eq_ignore_ascii_case("FALSE"),
+            other => self.number(other) == Ok(0.0),
+        }
+    }
+
+    /// INDEX(range, row, column): the cell at the 1-based offset inside the range.
+    /// A single-row or single-column range also takes INDEX(range, n), counting
+    /// along it. #REF! for an offset outside the range, #VALUE! below 1.
+    fn eval_index(&mut self, args: &[Expr]) -> CellValue {
+        let (range, offsets) = match args {
+            [Expr::Range(start, end), offsets @ ..] if (1..=2).contains(&offsets.len()) => {
+                (CellRange::new(*start, *end), offsets)
+            }
+            _ => return CellValue::Error(CellError::Value),
+        };
+
+        let mut indexes = Vec::new();
+        for offset in offsets {
+            match self.number(offset) {
+                Ok(n) if n.is_nan() || n < 1.0 => return CellValue::Error(CellError::Value),
+                Ok(n) => indexes.push(n as u32 - 1),
+                Err(e) => return CellValue::Error(e),
+            }
+        }
+        let (row, col) = match indexes[..] {
+            [row, col] => (row, col),
+            [n] if range.width() == 1 => (n, 0),
+            [n] if range.height() == 1 => (0, n),
+            _ => return CellValue::Error(CellError::Ref),
+        };
+
+        if row >= range.height() || col >= range.width() {
+            return CellValue::Error(CellError::Ref);
+        }
+        self.eval_cell(&range.cell_at(row, col))
+    }
+
+    /// SUMIF(range, criterion[, sum_range]) and COUNTIF(range, criterion)
+    fn eval_conditional(&mut self, name: &str, args: &[Expr]) -> CellValue {
+        let max_args = if name == "SUMIF" { 3 } else { 2 };
+        if args.len() < 2 || args.len() > max_args {
+            return CellValue::Error(CellError::Value);
+        }
+
+        let range = match &args[0] {
+            Expr::Range(start, end) => CellRange::new(*start, *end),
+            Expr::CellRef(cell) => CellRange::new(*cell, *cell),
+            _ => return CellValue::Error(CellError::Value),
+        };
+
+        // SUMIF can sum a different range of the same shape, anchored at its top-left
+        let sum_start = match args.get(2) {
+            Some(Expr::Range(start, end)) => CellRange::new(*start, *end).start,
+            Some(Expr::CellRef(cell)) => *cell,
+            Some(_) => return CellValue::Error(CellError::Value),
+            None => range.start,
+        };
+
+        let criterion = match self.eval(&args[1]) {
+            CellValue::Error(e) => return CellValue::Error(e),
+            value => Criterion::parse(&value),
+        };
+
+        let mut total = 0.0;
+        let mut count = 0;
+        for row in 0..range.height() {
+            for col in 0..range.width() {
+                if !criterion.matches(&self.eval_cell(&range.cell_at(row, col))) {
+                    continue;
+                }
+                count += 1;
+
+                let target = CellRef::new(sum_start.row + row, sum_start.col + col);
+                match self.eval_cell(&target) {
+                    CellValue::Number(n) => total += n,
+                    CellValue::Error(e) => return CellValue::Error(e),
+                    CellValue::Text(_) | CellValue::Empty => {}
+                }
+            }
+        }
+
+        if name == "SUMIF" {
+            CellValue::Number(total)
+        } else {
+            CellValue::Number(count as f64)
+        }
+    }
+
+    /// Evaluate to a number (empty counts as 0, text follows the coercion policy)
+    fn number(&mut self, expr: &Expr) -> Result<f64, CellError> {
+        let value = self.eval(expr);
+        self.to_number(value)
+    }
+
+    fn to_number(&self, value: CellValue) -> Result<f64, CellError> {
+        match value {
+            CellValue::Number(n) => Ok(n),
+            CellValue::Empty => Ok(0.0),
+            CellValue::Text(text) => match self.coercion {
+                TextCoercion::Strict => Err(CellError::Value),
+                TextCoercion::Coerce => text.trim().parse::<f64>().map_err(|_| CellError::Value),
+            },
+            CellValue::Error(e) => Err(e),
+        }
+    }
+
+    /// Numbers from function arguments, expanding ranges and array expressions.
+    /// Like Excel, text and blank cells inside a range (or a lone cell reference)
+    /// are skipped, while a literal text argument is `#VALUE!`.
+    fn collect_numbers(&mut self, args: &[Expr]) -> Result<Vec<f64>, CellError> {
+        let mut numbers = Vec::new();
+
+        for arg in args {
+            if contains_range(arg) || matches!(arg, Expr::CellRef(_)) {
+                for value in self.eval_array(arg) {
+                    match value {
+                        CellValue::Number(n) => numbers.push(n),
+                        CellValue::Error(e) => return Err(e),
+                        CellValue::Text(_) | CellValue::Empty => {}
+                    }
+                }
+            } else {
+                numbers.push(self.number(arg)?);
+            }
+        }
+
+        Ok(numbers)
+    }
+}
+
+/// Lookup equality: numbers with numbers and text with text (case-insensitively)
+fn exact_match(key: &CellValue, candidate: &CellValue) -> bool {
+    let same_kind = matches!(
+        (key, candidate),
+        (CellValue::Number(_), CellValue::Number(_)) | (CellValue::Text(_), CellValue::Text(_))
+    );
+    same_kind && compare(key, candidate) == Ok(Ordering::Equal)
+}
+
+/// Round to `digits` decimal places, halves away from zero. The scaled value is
+/// first cut to 15 significant digits (Excel's precision), so 1.005 rounds to 1.01
+/// even though 1.005 * 100 is 100.49999999999999 in binary floating point.
+fn round_half_up(x: f64, digits: i32) -> f64 {
+    let factor = 10f64.powi(digits);
+    let scaled: f64 = format!("{:.14e}", x * factor).parse().unwrap_or(x * factor);
+    if !scaled.is_finite() {
+        return x;
+    }
+    scaled.round() / factor
+}
+
+/// Order two values for comparison operators.
+/// Blanks act as 0 (or "" against text), text compares case-insensitively,
+/// and numbers sort before text like in Excel.
+fn compare(a: &CellValue, b: &CellValue) -> Result<Ordering, CellError> {
+    match (a, b) {
+        (CellValue::Error(e), _) | (_, CellValue::Error(e)) => Err(*e),
+        (CellValue::Text(x), CellValue::Text(y)) => Ok(x.to_lowercase().cmp(&y.to_lowercase())),
+        (CellValue::Text(x), CellValue::Empty) => Ok(if x.is_empty() { Ordering::Equal } else { Ordering::Greater }),
+        (CellValue::Empty, CellValue::Text(y)) => Ok(if y.is_empty() { Ordering::Equal } else { Ordering::Less }),
+        (CellValue::Text(_), _) => Ok(Ordering::Greater),
+        (_, CellValue::Text(_)) => Ok(Ordering::Less),
+        _ => {
+            let number = |value: &CellValue| match value {
+                CellValue::Number(n) => *n,
+                _ => 0.0,
+            };
+            number(a).partial_cmp(&number(b)).ok_or(CellError::Num)
+        }
+    }
+}
+
+/// Truth value of a condition: non-zero numbers are true, blanks are false
+fn truthy(value: &CellValue) -> Result<bool, CellError> {
+    match value {
+        CellValue::Number(n) => Ok(*n != 0.0),
+        CellValue::Empty => Ok(false),
+        CellValue::Text(_) => Err(CellError::Value),
+        CellValue::Error(e) => Err(*e),
+    }
+}
+
+/// Whether an expression refers to a range, and so evaluates to an array
+fn contains_range(expr: &Expr) -> bool {
+    match expr {
+        Expr::Range(_, _) => true,
+        Expr::Group(inner) => contains_range(inner),
+        Expr::Binary(left, _, right) => contains_range(left) || contains_range(right),
+        Expr::Function(name, args) => name == "IF" && args.iter().any(contains_range),
+        _ => false,
+    }
+}
+
+/// Zip arrays element-wise, repeating single values against longer arrays.
+/// Positions past the end of a shorter array are #N/A, as in Excel.
+fn broadcast(arrays: &[Vec<CellValue>]) -> Vec<Vec<CellValue>> {
+    let len = arrays.iter().map(Vec::len).max().unwrap_or(0);
+
+    (0..len)
+        .map(|i| {
+            arrays.iter()
+                .map(|array| match array.len() {
+                    1 => array[0].clone(),
+                    _ => array.get(i).cloned().unwrap_or(CellValue::Error(CellError::NotAvailable)),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_plus_one() -> Expr {
+        Expr::Binary(
+            Box::new(Expr::Text("5".to_string())),
+            BinaryOp::Add,
+            Box::new(Expr::Number(1.0)),
+        )
+    }
+
+    #[test]
+    fn test_text_coercion_policy() {
+        let mut grid = QuantumGrid::new();
+
+        let strict = Evaluator::new(&grid).eval(&text_plus_one());
+        assert_eq!(strict, CellValue::Error(CellError::Value));
+
+        let coerced = Evaluator::new(&grid)
+            .with_coercion(TextCoercion::Coerce)
+            .eval(&text_plus_one());
+        assert_eq!(coerced, CellValue::Number(6.0));
+
+        // Non-numeric text is still an error when coercing
+        let word = Expr::Binary(
+            Box::new(Expr::Text("abc".to_string())),
+            BinaryOp::Add,
+            Box::new(Expr::Number(1.0)),
+        );
+        let mut evaluator = Evaluator::new(&grid).with_coercion(TextCoercion::Coerce);
+        assert_eq!(evaluator.eval(&word), CellValue::Error(CellError::Value));
+
+        // The grid's preference is picked up by default
+        grid.set_text_coercion(TextCoercion::Coerce);
+        assert_eq!(Evaluator::new(&grid).eval(&text_plus_one()), CellValue::Number(6.0));
+    }
+
+    fn call(name: &str, range: &str, criterion: Expr) -> Expr {
+        let range = CellRange::parse(range).unwrap();
+        Expr::Function(name.to_string(), vec![Expr::Range(range.start, range.end), criterion])
+    }
+
+    fn text(value: &str) -> Expr {
+        Expr::Text(value.to_string())
+    }
+
+    #[test]
+    fn test_sumif_countif_numeric_criteria() {
+        let mut grid = QuantumGrid::new();
+        for (row, value) in [50.0, 150.0, 100.0, 250.0].iter().enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *value).unwrap();
+        }
+
+        let eval = |expr: Expr| Evaluator::new(&grid).eval(&expr);
+        assert_eq!(eval(call("SUMIF", "A1:A4", text(">100"))), CellValue::Number(400.0));
+        assert_eq!(eval(call("SUMIF", "A1:A4", text(">=100"))), CellValue::Number(500.0));
+        assert_eq!(eval(call("COUNTIF", "A1:A4", text("<100"))), CellValue::Number(1.0));
+        assert_eq!(eval(call("COUNTIF", "A1:A4", text("<>100"))), CellValue::Number(3.0));
+        assert_eq!(eval(call("COUNTIF", "A1:A4", Expr::Number(150.0))), CellValue::Number(1.0));
+
+        // Nothing populated in the range
+        assert_eq!(eval(call("SUMIF", "C1:C10", text(">0"))), CellValue::Number(0.0));
+        assert_eq!(eval(call("COUNTIF", "C1:C10", text(">0"))), CellValue::Number(0.0));
+    }
+
+    #[test]
+    fn test_countif_text_equality() {
+        let mut grid = QuantumGrid::new();
+        for (row, gender) in ["Male", "Female", "male", "Other"].iter().enumerate() {
+            grid.set_text(&format!("B{}", row + 1), gender).unwrap();
+            grid.set_cell(&format!("C{}", row + 1), (row + 1) as f64).unwrap();
+        }
+
+        let count = Evaluator::new(&grid).eval(&call("COUNTIF", "B1:B4", text("Male")));
+        assert_eq!(count, CellValue::Number(2.0));
+
+        // SUMIF with a separate sum range: rows 1 and 3
+        let c_range = CellRange::parse("C1:C4").unwrap();
+        let mut sum = call("SUMIF", "B1:B4", text("male"));
+        if let Expr::Function(_, args) = &mut sum {
+            args.push(Expr::Range(c_range.start, c_range.end));
+        }
+        assert_eq!(Evaluator::new(&grid).eval(&sum), CellValue::Number(4.0));
+    }
+
+    fn eval_text(grid: &QuantumGrid, formula: &str) -> CellValue {
+        crate::formula::parser::evaluate_formula(formula, grid).unwrap()
+    }
+
+    #[test]
+    fn test_array_if_inside_sum_matches_sumif() {
+        let mut grid = QuantumGrid::new();
+        for (row, value) in [50.0, 150.0, 100.0, 250.0, 80.0].iter().enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *value).unwrap();
+        }
+        grid.set_text("A6", "n/a").unwrap();
+
+        let array_sum = eval_text(&grid, "=SUM(IF(A1:A6>100, A1:A6, 0))");
+        assert_eq!(array_sum, CellValue::Number(400.0));
+        assert_eq!(array_sum, eval_text(&grid, "=SUMIF(A1:A6, \">100\")"));
+
+        // Scalar IF still works outside an array context
+        assert_eq!(eval_text(&grid, "=IF(2<1, \"yes\", \"no\")"), CellValue::Text("no".to_string()));
+    }
+
+    #[test]
+    fn test_comparisons_bind_loosest() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 2.0).unwrap();
+
+        assert_eq!(eval_text(&grid, "=IF(A1>1, \"big\", \"small\")"), CellValue::Text("big".to_string()));
+        assert_eq!(eval_text(&grid, "=IF(A1+1<=2, 1, 0)"), CellValue::Number(0.0));
+        assert_eq!(eval_text(&grid, "=IF(A1&\"\"=\"2\", 1, 0)"), CellValue::Number(1.0));
+    }
+
+    #[test]
+    fn test_cagr() {
+        let mut grid = QuantumGrid::new();
+        let cagr = |args: Vec<Expr>| Evaluator::new(&grid).eval(&Expr::Function("CAGR".to_string(), args));
+
+        match cagr(vec![Expr::Number(100.0), Expr::Number(200.0), Expr::Number(2.0)]) {
+            CellValue::Number(rate) => assert!((rate - 0.4142).abs() < 1e-4, "{}", rate),
+            other => panic!("expected a rate, got {:?}", other),
+        }
+        assert_eq!(cagr(vec![Expr::Number(0.0), Expr::Number(200.0), Expr::Number(2.0)]), CellValue::Error(CellError::Num));
+        assert_eq!(cagr(vec![Expr::Number(100.0), Expr::Number(200.0), Expr::Number(0.0)]), CellValue::Error(CellError::Num));
+
+        // Range form: 100 → 150 → 200 is two periods
+        for (row, value) in [100.0, 150.0, 200.0].iter().enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *value).unwrap();
+        }
+        let range = CellRange::parse("A1:A3").unwrap();
+        let rate = Evaluator::new(&grid).eval(&Expr::Function("CAGR".to_string(), vec![Expr::Range(range.start, range.end)]));
+        assert_eq!(rate, CellValue::Number(2f64.sqrt() - 1.0));
+    }
+
+    #[test]
+    fn test_blank_cells_are_empty_not_zero() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 10.0).unwrap();
+        grid.set_cell("A3", 20.0).unwrap(); // A2 is gap-filled in storage but never set
+
+        let a2 = CellRef::parse("A2").unwrap();
+        assert_eq!(Evaluator::new(&grid).eval_cell(&a2), CellValue::Empty);
+
+        let range = CellRange::parse("A1:A3").unwrap();
+        let eval = |name: &str| {
+            Evaluator::new(&grid).eval(&Expr::Function(name.to_string(), vec![Expr::Range(range.start, range.end)]))
+        };
+        assert_eq!(eval("AVERAGE"), CellValue::Number(15.0));
+        assert_eq!(eval("COUNT"), CellValue::Number(2.0));
+        assert_eq!(eval("SUM"), CellValue::Number(30.0));
+    }
+
+    #[test]
+    fn test_sum_skips_text_cells_but_not_text_arguments() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 10.0).unwrap();
+        grid.set_text("A2", "n/a").unwrap();
+        grid.set_cell("A4", 5.0).unwrap();
+
+        let cell = |reference: &str| Expr::CellRef(CellRef::parse(reference).unwrap());
+        let range = CellRange::parse("A1:A4").unwrap();
+        let sum = |args: Vec<Expr>| Evaluator::new(&grid).eval(&Expr::Function("SUM".to_string(), args));
+        assert_eq!(sum(vec![Expr::Range(range.start, range.end)]), CellValue::Number(15.0));
+        assert_eq!(sum(vec![cell("A1"), cell("A2"), Expr::Number(1.0)]), CellValue::Number(11.0));
+        assert_eq!(sum(vec![Expr::Text("abc".to_string())]), CellValue::Error(CellError::Value));
+        assert_eq!(sum(vec![cell("A1"), Expr::Text("abc".to_string())]), CellValue::Error(CellError::Value));
+    }
+
+    #[test]
+    fn test_row_and_column() {
+        let mut grid = QuantumGrid::new();
+        let eval = |name: &str, args: Vec<Expr>| Evaluator::new(&grid).eval(&Expr::Function(name.to_string(), args));
+        let cell = |reference: &str| CellRef::parse(reference).unwrap();
+        assert_eq!(eval("ROW", vec![Expr::CellRef(cell("A5"))]), CellValue::Number(5.0));
+        assert_eq!(eval("COLUMN", vec![Expr::CellRef(cell("C1"))]), CellValue::Number(3.0));
+        assert_eq!(eval("COLUMN", vec![Expr::Range(cell("AA2"), cell("AB9"))]), CellValue::Number(27.0));
+        assert_eq!(eval("ROW", vec![Expr::Number(5.0)]), CellValue::Error(CellError::Value));
+        assert_eq!(eval("ROW", vec![]), CellValue::Error(CellError::Value));
+
+        // Without an argument, the coordinate of the cell holding the formula
+        grid.set_formula("D7", "=ROW()*10+COLUMN()").unwrap();
+        assert_eq!(grid.get_cell("D7").unwrap(), 74.0);
+    }
+
+    #[test]
+    fn test_trim_and_clean_text() {
+        let mut grid = QuantumGrid::new();
+        grid.set_text("A1", "  north \t").unwrap();
+        grid.set_text("A2", "line\nbreak\u{7}").unwrap();
+        grid.set_cell("A3", 5.0).unwrap();
+
+        let eval = |name: &str, cell: &str| {
+            Evaluator::new(&grid).eval(&Expr::Function(name.to_string(), vec![Expr::CellRef(CellRef::parse(cell).unwrap())]))
+        };
+        assert_eq!(eval("TRIM", "A1"), CellValue::Text("north".to_string()));
+        assert_eq!(eval("CLEAN", "A2"), CellValue::Text("linebreak".to_string()));
+        assert_eq!(eval("TRIM", "A3"), CellValue::Number(5.0));
+        assert_eq!(eval("CLEAN", "A4"), CellValue::Empty);
+    }
+
+    #[test]
+    fn test_text_case_functions() {
+        let grid = QuantumGrid::new();
+        let eval = |name: &str, arg: Expr| Evaluator::new(&grid).eval(&Expr::Function(name.to_string(), vec![arg]));
+        let text = |text: &str| Expr::Text(text.to_string());
+        let result = |text: &str| CellValue::Text(text.to_string());
+
+        assert_eq!(eval("UPPER", text("MiXeD case")), result("MIXED CASE"));
+        assert_eq!(eval("LOWER", text("MiXeD Ünïcode")), result("mixed ünïcode"));
+        assert_eq!(eval("UPPER", text("straße")), result("STRASSE"));
+        assert_eq!(eval("PROPER", text("the qUICK brown-fox o'neil")), result("The Quick Brown-Fox O'Neil"));
+        assert_eq!(eval("UPPER", Expr::Number(2.5)), result("2.5"));
+        assert_eq!(eval("PROPER", Expr::CellRef(CellRef::parse("A1").unwrap())), result(""));
+    }
+
+    #[test]
+    fn test_substring_functions_clamp_to_the_text() {
+        let mut grid = QuantumGrid::new();
+        grid.set_text("A1", "Kathmandu").unwrap();
+        grid.set_text("A2", "").unwrap();
+
+        let eval = |name: &str, cell: &str, numbers: &[f64]| {
+            let mut args = vec![Expr::CellRef(CellRef::parse(cell).unwrap())];
+            args.extend(numbers.iter().map(|&n| Expr::Number(n)));
+            Evaluator::new(&grid).eval(&Expr::Function(name.to_string(), args))
+        };
+        let text = |text: &str| CellValue::Text(text.to_string());
+
+        assert_eq!(eval("LEN", "A1", &[]), CellValue::Number(9.0));
+        assert_eq!(eval("LEFT", "A1", &[4.0]), text("Kath"));
+        assert_eq!(eval("LEFT", "A1", &[]), text("K"));
+        assert_eq!(eval("RIGHT", "A1", &[5.0]), text("mandu"));
+        assert_eq!(eval("MID", "A1", &[5.0, 3.0]), text("man"));
+
+        // Past the end is cut short, never a panic
+        assert_eq!(eval("RIGHT", "A1", &[50.0]), text("Kathmandu"));
+        assert_eq!(eval("MID", "A1", &[8.0, 10.0]), text("du"));
+        assert_eq!(eval("MID", "A1", &[20.0, 2.0]), text(""));
+        assert_eq!(eval("MID", "A1", &[0.0, 2.0]), CellValue::Error(CellError::Value));
+        assert_eq!(eval("LEFT", "A1", &[-1.0]), CellValue::Error(CellError::Value));
+
+        assert_eq!(eval("LEN", "A2", &[]), CellValue::Number(0.0));
+        assert_eq!(eval("LEN", "A3", &[]), CellValue::Number(0.0)); // Blank
+        assert_eq!(eval("RIGHT", "A2", &[3.0]), text(""));
+    }
+
+    #[test]
+    fn test_correl_of_linear_ranges() {
+        let mut grid = QuantumGrid::new();
+        for row in 1..=5 {
+            grid.set_cell(&format!("A{}", row), row as f64).unwrap();
+            grid.set_cell(&format!("B{}", row), row as f64 * 3.0 + 2.0).unwrap();
+            grid.set_cell(&format!("C{}", row), 10.0 - row as f64).unwrap();
+        }
+        grid.set_cell("D1", 4.0).unwrap(); // Constant column
+        grid.set_cell("D5", 4.0).unwrap();
+
+        let correl = |a: &str, b: &str| {
+            let (a, b) = (CellRange::parse(a).unwrap(), CellRange::parse(b).unwrap());
+            Evaluator::new(&grid).eval(&Expr::Function(
+                "CORREL".to_string(),
+                vec![Expr::Range(a.start, a.end), Expr::Range(b.start, b.end)],
+            ))
+        };
+        assert_eq!(correl("A1:A5", "B1:B5"), CellValue::Number(1.0));
+        assert_eq!(correl("A1:A5", "C1:C5"), CellValue::Number(-1.0));
+        assert_eq!(correl("A1:A5", "B1:B4"), CellValue::Error(CellError::NotAvailable));
+        assert_eq!(correl("A1:A5", "D1:D5"), CellValue::Error(CellError::DivZero));
+    }
+
+    #[test]
+    fn test_slope_and_intercept_fit_a_line() {
+        let mut grid = QuantumGrid::new();
+        let (x, y): (Vec<f64>, Vec<f64>) = (1..=6).map(|n| (n as f64, 2.0 * n as f64 + 1.0)).unzip();
+        for (row, (x, y)) in x.iter().zip(&y).enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *x).unwrap();
+            grid.set_cell(&format!("B{}", row + 1), *y).unwrap();
+            grid.set_cell(&format!("C{}", row + 1), 3.0).unwrap();
+        }
+        assert_eq!(crate::compute::linear_regression(&x, &y), Some((2.0, 1.0, 1.0)));
+
+        let fit = |name: &str, known_y: &str, known_x: &str| {
+            let (y, x) = (CellRange::parse(known_y).unwrap(), CellRange::parse(known_x).unwrap());
+            Evaluator::new(&grid).eval(&Expr::Function(
+                name.to_string(),
+                vec![Expr::Range(y.start, y.end), Expr::Range(x.start, x.end)],
+            ))
+        };
+        assert_eq!(fit("SLOPE", "B1:B6", "A1:A6"), CellValue::Number(2.0));
+        assert_eq!(fit("INTERCEPT", "B1:B6", "A1:A6"), CellValue::Number(1.0));
+        assert_eq!(fit("SLOPE", "B1:B6", "C1:C6"), CellValue::Error(CellError::DivZero)); // All x equal
+    }
+
+    #[test]
+    fn test_count_and_counta_on_mixed_range() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 1.0).unwrap();
+        grid.set_text("A2", "north").unwrap();
+        grid.set_cell("A3", 3.0).unwrap();
+        grid.set_text("A4", "").unwrap();
+        // A5:A6 blank
+
+        let range = CellRange::parse("A1:A6").unwrap();
+        let count = |name: &str| {
+            Evaluator::new(&grid).eval(&Expr::Function(name.to_string(), vec![Expr::Range(range.start, range.end)]))
+        };
+        assert_eq!(count("COUNT"), CellValue::Number(2.0));
+        assert_eq!(count("COUNTA"), CellValue::Number(4.0)); // Empty text still counts, like Excel
+    }
+
+    #[test]
+    fn test_concat_text_and_numbers() {
+        let mut grid = QuantumGrid::new();
+        grid.set_text("A1", "Kath").unwrap();
+        grid.set_text("B1", "mandu").unwrap();
+        grid.set_cell("C1", 5.0).unwrap();
+        grid.set_cell("D1", 2.5).unwrap();
+        let cell = |reference| Box::new(Expr::CellRef(CellRef::parse(reference).unwrap()));
+        let eval = |expr: &Expr| Evaluator::new(&grid).eval(expr);
+
+        let concat = Expr::Function("CONCAT".to_string(), vec![*cell("A1"), *cell("B1")]);
+        assert_eq!(eval(&concat), CellValue::Text("Kathmandu".to_string()));
+
+        let amp = Expr::Binary(cell("A1"), BinaryOp::Concat, cell("C1"));
+        assert_eq!(eval(&amp), CellValue::Text("Kath5".to_string()));
+        let amp = Expr::Binary(cell("D1"), BinaryOp::Concat, Box::new(Expr::Text(" kg".to_string())));
+        assert_eq!(eval(&amp), CellValue::Text("2.5 kg".to_string()));
+        let with_error = Expr::Binary(cell("A1"), BinaryOp::Concat, Box::new(Expr::RefError));
+        assert_eq!(eval(&with_error), CellValue::Error(CellError::Ref));
+    }
+
+    #[test]
+    fn test_rounding_functions() {
+        let grid = QuantumGrid::new();
+        let call = |name: &str, args: &[f64]| {
+            let args = args.iter().map(|&n| Expr::Number(n)).collect();
+            Evaluator::new(&grid).eval(&Expr::Function(name.to_string(), args))
+        };
+
+        assert_eq!(call("ROUND", &[2.345, 2.0]), CellValue::Number(2.35));
+        assert_eq!(call("ROUND", &[1.005, 2.0]), CellValue::Number(1.01));
+        assert_eq!(call("ROUND", &[-2.345, 2.0]), CellValue::Number(-2.35));
+        assert_eq!(call("ROUND", &[2.5, 0.0]), CellValue::Number(3.0));
+        assert_eq!(call("ROUND", &[-2.5, 0.0]), CellValue::Number(-3.0));
+        assert_eq!(call("ROUND", &[1250.0, -2.0]), CellValue::Number(1300.0));
+        assert_eq!(call("ROUND", &[2.5]), CellValue::Error(CellError::Value));
+
+        assert_eq!(call("FLOOR", &[2.7]), CellValue::Number(2.0));
+        assert_eq!(call("FLOOR", &[-2.2]), CellValue::Number(-3.0));
+        assert_eq!(call("FLOOR", &[0.0]), CellValue::Number(0.0));
+        assert_eq!(call("CEILING", &[2.2]), CellValue::Number(3.0));
+        assert_eq!(call("CEILING", &[-2.7]), CellValue::Number(-2.0));
+        assert_eq!(call("CEILING", &[0.0]), CellValue::Number(0.0));
+        assert_eq!(call("ABS", &[-4.5]), CellValue::Number(4.5));
+        assert_eq!(call("ABS", &[4.5]), CellValue::Number(4.5));
+        assert_eq!(call("ABS", &[0.0]), CellValue::Number(0.0));
+    }
+
+    /// A1:B3 holds (101, Pune), (102, Delhi), (103, Patna)
+    fn city_grid() -> QuantumGrid {
+        let mut grid = QuantumGrid::new();
+        for (row, (id, city)) in [(101.0, "Pune"), (102.0, "Delhi"), (103.0, "Patna")].iter().enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *id).unwrap();
+            grid.set_text(&format!("B{}", row + 1), city).unwrap();
+        }
+        grid
+    }
+
+    fn range(text: &str) -> Expr {
+        let range = CellRange::parse(text).unwrap();
+        Expr::Range(range.start, range.end)
+    }
+
+    #[test]
+    fn test_index_into_range() {
+        let grid = city_grid();
+        let index = |args: Vec<Expr>| Evaluator::new(&grid).eval(&Expr::Function("INDEX".to_string(), args));
+
+        assert_eq!(index(vec![range("A1:B3"), Expr::Number(2.0), Expr::Number(2.0)]), CellValue::Text("Delhi".to_string()));
+        assert_eq!(index(vec![range("A1:A3"), Expr::Number(3.0)]), CellValue::Number(103.0));
+        assert_eq!(index(vec![range("A2:B2"), Expr::Number(2.0)]), CellValue::Text("Delhi".to_string()));
+
+        // Outside the range, or a single offset into a block
+        assert_eq!(index(vec![range("A1:B3"), Expr::Number(4.0), Expr::Number(1.0)]), CellValue::Error(CellError::Ref));
+        assert_eq!(index(vec![range("A1:B3"), Expr::Number(1.0), Expr::Number(3.0)]), CellValue::Error(CellError::Ref));
+        assert_eq!(index(vec![range("A1:B3"), Expr::Number(2.0)]), CellValue::Error(CellError::Ref));
+        assert_eq!(index(vec![range("A1:B3"), Expr::Number(0.0), Expr::Number(1.0)]), CellValue::Error(CellError::Value));
+
+        // A NaN offset is #VALUE!, not a panic
+        assert_eq!(index(vec![range("A1:A3"), Expr::Number(f64::NAN)]), CellValue::Error(CellError::Value));
+        assert_eq!(index(vec![range("A1:B3"), Expr::Number(1.0), Expr::Number(f64::NAN)]), CellValue::Error(CellError::Value));
+        assert_eq!(eval_text(&grid, "=INDEX(A1:A3,(-1)^0.5)"), CellValue::Error(CellError::Value));
+    }
+
+    #[test]
+    fn test_match_in_row_or_column() {
+        let mut grid = city_grid();
+        grid.set_text("D1", "x").unwrap();
+        grid.set_text("E1", "y").unwrap();
+        let exact = Expr::Number(0.0);
+        let find = |args: Vec<Expr>| Evaluator::new(&grid).eval(&Expr::Function("MATCH".to_string(), args));
+
+        assert_eq!(find(vec![Expr::Number(103.0), range("A1:A3"), exact.clone()]), CellValue::Number(3.0));
+        assert_eq!(find(vec![Expr::Text("DELHI".to_string()), range("B1:B3"), exact.clone()]), CellValue::Number(2.0));
+        assert_eq!(find(vec![Expr::Text("y".to_string()), range("D1:E1")]), CellValue::Number(2.0));
+
+        assert_eq!(find(vec![Expr::Number(999.0), range("A1:A3"), exact.clone()]), CellValue::Error(CellError::NotAvailable));
+        assert_eq!(find(vec![Expr::Text("101".to_string()), range("A1:A3"), exact.clone()]), CellValue::Error(CellError::NotAvailable));
+        assert_eq!(find(vec![Expr::Number(101.0), range("A1:B3"), exact]), CellValue::Error(CellError::NotAvailable));
+        assert_eq!(find(vec![Expr::Number(101.0), range("A1:A3"), Expr::Number(1.0)]), CellValue::Error(CellError::Value));
+    }
+
+    #[test]
+    fn test_vlookup() {
+        let mut grid = QuantumGrid::new();
+        for (row, (id, city, score)) in [(101.0, "Pune", 90.0), (102.0, "Delhi", 75.0), (103.0, "Patna", 60.0)].iter().enumerate() {
+            let row = row + 1;
+            grid.set_cell(&format!("A{}", row), *id).unwrap();
+            grid.set_text(&format!("B{}", row), city).unwrap();
+            grid.set_cell(&format!("C{}", row), *score).unwrap();
+        }
+        grid.set_text("E1", "delhi").unwrap();
+        let range = CellRange::parse("A1:C3").unwrap();
+        let table = Expr::Range(range.start, range.end);
+        let text_table = Expr::Range(CellRef::parse("B1").unwrap(), CellRef::parse("C3").unwrap());
+        let vlookup = |args: Vec<Expr>| Evaluator::new(&grid).eval(&Expr::Function("VLOOKUP".to_string(), args));
+
+        // Hits: numeric key, and a text key from a cell
+        assert_eq!(vlookup(vec![Expr::Number(102.0), table.clone(), Expr::Number(2.0)]), CellValue::Text("Delhi".to_string()));
+        let e1 = Expr::CellRef(CellRef::parse("E1").unwrap());
+        assert_eq!(vlookup(vec![e1, text_table, Expr::Number(2.0), Expr::Text("FALSE".to_string())]), CellValue::Number(75.0));
+
+        // Miss, then column index outside the range
+        assert_eq!(vlookup(vec![Expr::Number(999.0), table.clone(), Expr::Number(2.0)]), CellValue::Error(CellError::NotAvailable));
+        assert_eq!(vlookup(vec![Expr::Number(101.0), table.clone(), Expr::Number(4.0)]), CellValue::Error(CellError::Ref));
+        assert_eq!(vlookup(vec![Expr::Number(101.0), table.clone(), Expr::Number(0.0)]), CellValue::Error(CellError::Value));
+
+        // A NaN column is #VALUE!, not a panic
+        assert_eq!(vlookup(vec![Expr::Number(101.0), table, Expr::Number(f64::NAN)]), CellValue::Error(CellError::Value));
+        assert_eq!(eval_text(&grid, "=VLOOKUP(101,A1:C3,(-1)^0.5)"), CellValue::Error(CellError::Value));
+    }
+
+    #[test]
+    fn test_percentile_interpolates_between_ranks() {
+        let data = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        assert_eq!(crate::compute::percentile(&data, 0.0), Some(1.0));
+        assert_eq!(crate::compute::percentile(&data, 0.25), Some(2.75));
+        assert_eq!(crate::compute::percentile(&data, 0.5), Some(4.5));
+        assert_eq!(crate::compute::percentile(&data, 0.75), Some(6.25));
+        assert_eq!(crate::compute::percentile(&data, 1.0), Some(8.0));
+        assert_eq!(crate::compute::percentile(&data, 1.5), None);
+        assert_eq!(crate::compute::percentile(&[], 0.5), None);
+
+        let mut grid = QuantumGrid::new();
+        for (row, value) in [8.0, 3.0, 1.0, 6.0, 2.0, 7.0, 5.0, 4.0].iter().enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *value).unwrap();
+        }
+        let range = CellRange::parse("A1:A8").unwrap();
+        let percentile = |k: f64| Evaluator::new(&grid).eval(&Expr::Function(
+            "PERCENTILE".to_string(),
+            vec![Expr::Range(range.start, range.end), Expr::Number(k)],
+        ));
+        assert_eq!(percentile(0.25), CellValue::Number(2.75));
+        assert_eq!(percentile(-0.1), CellValue::Error(CellError::Num));
+    }
+
+    #[test]
+    fn test_product() {
+        let mut grid = QuantumGrid::new();
+        for (row, value) in [2.0, 3.0, 4.0].iter().enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *value).unwrap();
+        }
+        grid.set_text("A4", "skip").unwrap();
+        let product = |grid: &QuantumGrid, range: &str| {
+            let range = CellRange::parse(range).unwrap();
+            Evaluator::new(grid).eval(&Expr::Function("PRODUCT".to_string(), vec![Expr::Range(range.start, range.end)]))
+        };
+
+        assert_eq!(product(&grid, "A1:A4"), CellValue::Number(24.0)); // Text is skipped
+        assert_eq!(product(&grid, "C1:C5"), CellValue::Number(1.0)); // Empty range
+        let many: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_eq!(crate::compute::optimized_product(&many), 3628800.0);
+    }
+
+    #[test]
+    fn test_let_binds_names_in_order() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 2.0).unwrap();
+
+        assert_eq!(eval_text(&grid, "=LET(x, A1+1, x*x)"), CellValue::Number(9.0));
+        // Later bindings see earlier ones, and names ignore case
+        assert_eq!(eval_text(&grid, "=LET(x, A1, y, x*10, Y+x)"), CellValue::Number(22.0));
+
+        // Names don't outlive the LET
+        assert_eq!(eval_text(&grid, "=LET(x, 1, x)+x"), CellValue::Error(CellError::Name));
+        assert_eq!(eval_text(&grid, "=LET(1, 2, 3)"), CellValue::Error(CellError::Value));
+
+        // Stored formulas accept LET too, and recalculate when A1 changes
+        grid.set_formula("B1", "=LET(total, A1*3, total-1)").unwrap();
+        grid.set_cell("A1", 4.0).unwrap();
+        assert_eq!(grid.get_cell_value(&CellRef::parse("B1").unwrap()), CellValue::Number(11.0));
+    }
+}
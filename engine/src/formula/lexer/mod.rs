@@ -0,0 +1,202 @@
+//! Formula tokenizer: splits "=SUM(A1:A10)+B1" into tokens with source spans,
+//! for the parser and for editors that highlight or underline parts of a formula
+
+use crate::excel::{CellRange, CellRef};
+
+/// What a token is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Number literal: 42, 3.14
+    Number,
+    /// Quoted text literal, quotes included: "kg"
+    Text,
+    /// Single cell: A1, $B$2
+    CellRef,
+    /// Cell range: A1:A10
+    Range,
+    /// Name directly followed by '(': SUM
+    FunctionName,
+    /// Any other bare name, such as one bound by LET: x, net_total
+    Name,
+    /// + - * / ^ & = <> < <= > >=
+    Operator,
+    Comma,
+    LParen,
+    RParen,
+}
+
+/// Byte offsets of a token in the source, end exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// One token and where it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+/// A formula that doesn't parse, and where: "unexpected ')' at position 7"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset into the formula, counting its leading '='
+    pub position: usize,
+    pub message: String,
+    /// The formula text from `position` on, cut to a few characters
+    pub near: String,
+}
+
+impl ParseError {
+    pub(crate) fn at(source: &str, position: usize, message: impl Into<String>) -> Self {
+        let near = source.get(position..).unwrap_or_default().chars().take(10).collect();
+        Self { position, message: message.into(), near }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl From<ParseError> for String {
+    fn from(error: ParseError) -> Self {
+        error.to_string()
+    }
+}
+
+const OPERATORS: [&str; 12] = ["<=", ">=", "<>", "+", "-", "*", "/", "^", "&", "=", "<", ">"];
+
+/// Tokenize a formula. A leading '=' is skipped, but spans still count from the
+/// start of `formula`, so in "=SUM(A1)" the function name spans 1..4.
+pub fn tokenize(formula: &str) -> Result<Vec<Token<'_>>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut pos = if formula.starts_with('=') { 1 } else { 0 };
+    while pos < formula.len() {
+        let rest = &formula[pos..];
+        let next = rest.chars().next().unwrap_or_default();
+        if next.is_whitespace() {
+            pos += next.len_utf8();
+            continue;
+        }
+
+        let (kind, len) = if next == '"' {
+            (TokenKind::Text, text_len(rest).ok_or_else(|| ParseError::at(formula, pos, "unterminated text"))?)
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            (TokenKind::Operator, op.len())
+        } else if next == ',' {
+            (TokenKind::Comma, 1)
+        } else if next == '(' {
+            (TokenKind::LParen, 1)
+        } else if next == ')' {
+            (TokenKind::RParen, 1)
+        } else if is_word_char(next) {
+            word(rest).map_err(|message| ParseError::at(formula, pos, message))?
+        } else {
+            return Err(ParseError::at(formula, pos, format!("unexpected '{}'", next)));
+        };
+
+        tokens.push(Token { kind, text: &rest[..len], span: Span { start: pos, end: pos + len } });
+        pos += len;
+    }
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '$' || c == '.' || c == '_'
+}
+
+/// Letters, digits and `_`, not starting with a digit
+fn is_name(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn word_len(text: &str) -> usize {
+    text.find(|c: char| !is_word_char(c)).unwrap_or(text.len())
+}
+
+/// Classify the cell reference, range, number, function name or name at the start of `rest`
+fn word(rest: &str) -> Result<(TokenKind, usize), String> {
+    let len = word_len(rest);
+    let word = &rest[..len];
+    let after = &rest[len..];
+
+    if after.starts_with('(') && is_name(word) && CellRef::parse(word).is_err() {
+        return Ok((TokenKind::FunctionName, len));
+    }
+    if let Some(end) = after.strip_prefix(':') {
+        let range_len = len + 1 + word_len(end);
+        CellRange::parse(&rest[..range_len])?;
+        return Ok((TokenKind::Range, range_len));
+    }
+    if CellRef::parse(word).is_ok() {
+        return Ok((TokenKind::CellRef, len));
+    }
+    if word.parse::<f64>().is_ok() {
+        return Ok((TokenKind::Number, len));
+    }
+    if is_name(word) {
+        return Ok((TokenKind::Name, len));
+    }
+    Err(format!("could not parse '{}' as cell or number", word))
+}
+
+/// Byte length of the "quoted text" at the start of `rest`, where "" is an escaped quote
+fn text_len(rest: &str) -> Option<usize> {
+    let mut chars = rest.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '"' && chars.next_if(|(_, c)| *c == '"').is_none() {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_function_call_with_spans() {
+        let tokens = tokenize("=SUM(A1:A10)+B1").unwrap();
+        let expected = [
+            (TokenKind::FunctionName, "SUM", 1, 4),
+            (TokenKind::LParen, "(", 4, 5),
+            (TokenKind::Range, "A1:A10", 5, 11),
+            (TokenKind::RParen, ")", 11, 12),
+            (TokenKind::Operator, "+", 12, 13),
+            (TokenKind::CellRef, "B1", 13, 15),
+        ];
+        let actual: Vec<_> = tokens.iter()
+            .map(|t| (t.kind, t.text, t.span.start, t.span.end))
+            .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_tokenize_literals_and_operators() {
+        let tokens = tokenize("ROUND(A1, 2) <> \"say \"\"hi\"\"\" & 3.5").unwrap();
+        let kinds: Vec<_> = tokens.iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, [
+            TokenKind::FunctionName, TokenKind::LParen, TokenKind::CellRef, TokenKind::Comma,
+            TokenKind::Number, TokenKind::RParen, TokenKind::Operator, TokenKind::Text,
+            TokenKind::Operator, TokenKind::Number,
+        ]);
+        assert_eq!(tokens[6].text, "<>");
+        assert_eq!(tokens[7].text, "\"say \"\"hi\"\"\"");
+
+        let tokens = tokenize("=LET(net_total, A1*2, net_total+1)").unwrap();
+        let kinds: Vec<_> = tokens.iter().map(|t| (t.kind, t.text)).collect();
+        assert_eq!(kinds[2], (TokenKind::Name, "net_total"));
+        assert_eq!(kinds[8], (TokenKind::Name, "net_total"));
+
+        let error = tokenize("=A1 # 2").unwrap_err();
+        assert_eq!((error.position, error.near.as_str()), (4, "# 2"));
+        assert_eq!(tokenize("=1 + \"open").unwrap_err().position, 5);
+    }
+}
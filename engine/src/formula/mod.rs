@@ -0,0 +1,6 @@
+//! Formula system - AST, lexer, parsers and evaluator
+
+pub mod ast;
+pub mod lexer;
+pub mod parser;
+pub mod evaluator;
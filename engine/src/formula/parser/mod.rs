@@ -7,14 +7,17 @@ use nom::{
     IResult,
     branch::alt,
     character::complete::{alpha1, digit1, char, one_of, multispace0},
-    combinator::{map, opt, recognize},
-    multi::{separated_list0, many1},
-    sequence::{delimited, pair, tuple},
+    combinator::{map, map_res, opt, recognize},
+    multi::{separated_list0, many0, many1},
+    sequence::{delimited, tuple},
 };
 
-use crate::excel::CellRef;
+use crate::excel::{CellRange, CellRef};
 use crate::formula::ast::{Expr, BinaryOp, Formula};
-use crate::operations::OperationRegistry;
+use crate::formula::evaluator::{CellValue, Evaluator};
+use crate::formula::lexer::{tokenize, Token, TokenKind};
+pub use crate::formula::lexer::ParseError;
+use crate::operations::{OperationRegistry, OperationType};
 
 /// Parse a complete Excel formula (starts with '=')
 pub fn parse_formula(input: &str) -> IResult<&str, Formula> {
@@ -28,7 +31,7 @@ pub fn parse_formula(input: &str) -> IResult<&str, Formula> {
 fn parse_expression(input: &str) -> IResult<&str, Expr> {
     let (input, first_term) = parse_term(input)?;
     
-    let (input, operations) = many1(
+    let (input, operations) = many0(
         tuple((
             delimited(multispace0, alt((char('+'), char('-'))), multispace0),
             parse_term,
@@ -52,7 +55,7 @@ fn parse_expression(input: &str) -> IResult<&str, Expr> {
 fn parse_term(input: &str) -> IResult<&str, Expr> {
     let (input, first_factor) = parse_factor(input)?;
     
-    let (input, operations) = many1(
+    let (input, operations) = many0(
         tuple((
             delimited(multispace0, alt((char('*'), char('/'))), multispace0),
             parse_factor,
@@ -72,10 +75,11 @@ fn parse_term(input: &str) -> IResult<&str, Expr> {
     Ok((input, expr))
 }
 
-/// Parse a factor (number, cell reference, function call, or parenthesized expression)
+/// Parse a factor (number, range, cell reference, function call, or parenthesized expression)
 fn parse_factor(input: &str) -> IResult<&str, Expr> {
     alt((
         parse_number,
+        parse_range,
         parse_cell_reference,
         parse_function_call,
         parse_parenthesized,
@@ -94,13 +98,28 @@ fn parse_number(input: &str) -> IResult<&str, Expr> {
     )(input)
 }
 
-/// Parse a cell reference (e.g., A1, B2, AA100)
+/// Recognize the text of a cell reference: letters then digits, each optionally `$`-anchored
+fn cell_text(input: &str) -> IResult<&str, &str> {
+    recognize(tuple((
+        opt(char('$')),
+        many1(one_of("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz")),
+        opt(char('$')),
+        digit1,
+    )))(input)
+}
+
+/// Parse a range of cells (e.g., A1:A10, B2:C5)
+fn parse_range(input: &str) -> IResult<&str, Expr> {
+    map_res(
+        recognize(tuple((cell_text, char(':'), cell_text))),
+        |range_str: &str| CellRange::parse(range_str).map(|range| Expr::Range(range.start, range.end)),
+    )(input)
+}
+
+/// Parse a cell reference (e.g., A1, B2, AA100, $A$1)
 fn parse_cell_reference(input: &str) -> IResult<&str, Expr> {
     map(
-        recognize(pair(
-            many1(one_of("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz")),
-            digit1,
-        )),
+        cell_text,
         |cell_str: &str| {
             CellRef::parse(cell_str)
                 .map(Expr::CellRef)
@@ -132,17 +151,33 @@ fn parse_parenthesized(input: &str) -> IResult<&str, Expr> {
 }
 
 
-/// Parse formula with better error handling
-pub fn parse_formula_safe(formula_str: &str) -> Result<Formula, String> {
+/// Parse formula, reporting where it stops making sense
+pub fn parse_formula_safe(formula_str: &str) -> Result<Formula, ParseError> {
+    let unexpected = |remaining: &str| {
+        let position = formula_str.len() - remaining.len();
+        let message = match remaining.chars().next() {
+            Some(c) => format!("unexpected '{}'", c),
+            None => "unexpected end of formula".to_string(),
+        };
+        ParseError::at(formula_str, position, message)
+    };
     match parse_formula(formula_str) {
         Ok(("", formula)) => Ok(formula),
-        Ok((remaining, _)) => Err(format!("Could not parse entire formula. Remaining: '{}'", remaining)),
-        Err(e) => Err(format!("Parse error: {:?}", e)),
+        Ok((remaining, _)) => Err(unexpected(remaining)),
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => Err(unexpected(e.input)),
+        Err(nom::Err::Incomplete(_)) => Err(unexpected("")),
     }
 }
+
+/// Parse a formula, treating calls to registered operations as operation calls
 pub fn parse_formula_with_ops(formula: &str, registry: &OperationRegistry) -> Result<Formula, String> {
+    Ok(parse_formula_checked(formula, registry)?)
+}
+
+/// `parse_formula_with_ops`, keeping the error's position for editors
+pub fn parse_formula_checked(formula: &str, registry: &OperationRegistry) -> Result<Formula, ParseError> {
     if !formula.starts_with('=') {
-        return Err("Formula must start with '='".to_string());
+        return Err(ParseError::at(formula, 0, "Formula must start with '='"));
     }
     
     let expr_str = &formula[1..];
@@ -155,10 +190,8 @@ pub fn parse_formula_with_ops(formula: &str, registry: &OperationRegistry) -> Re
         if rest.ends_with(')') {
             let args_str = &rest[1..rest.len()-1]; // Remove parentheses
             
-            // Check if this is a registered operation
-            if registry.list_operations()
-                .iter()
-                .any(|op_name| op_name == &func_name.to_uppercase()) {
+            // Check if this is a registered operation; nested calls need the full parser
+            if registry.get(&func_name.to_uppercase()).is_some() && !args_str.contains('(') {
                 
                 // Parse arguments (split by comma)
                 let args: Vec<String> = args_str.split(',')
@@ -168,66 +201,229 @@ pub fn parse_formula_with_ops(formula: &str, registry: &OperationRegistry) -> Re
                 // Create function expression
                 return Ok(Formula::new(Expr::Function(
                     func_name.to_uppercase(),
-                    args.into_iter().map(|arg| {
-                        // Try to parse each argument as cell reference or number
-                        if let Ok(cell) = CellRef::parse(&arg) {
-                            Expr::CellRef(cell)
-                        } else if let Ok(num) = arg.parse::<f64>() {
-                            Expr::Number(num)
-                        } else {
-                            // Keep as string for operations that need it
-                            Expr::Number(0.0) // Placeholder
-                        }
-                    }).collect(),
+                    args.iter().map(|arg| parse_argument(arg)).collect(),
                 )));
             }
         }
     }
     
     // Try binary operations
-    parse_binary_operation(expr_str)
+    parse_binary_operation(formula)
 }
-/// Parse binary operations (A1+B2, A1-B2, etc.)
-fn parse_binary_operation(expr: &str) -> Result<Formula, String> {
-    // Operator precedence: */ before +-
-    let operators = [('+', BinaryOp::Add), ('-', BinaryOp::Subtract), 
-                     ('*', BinaryOp::Multiply), ('/', BinaryOp::Divide)];
-    
-    // Find operator (handle multiple operators later)
-    for (op_char, op_type) in operators {
-        if let Some(pos) = expr.find(op_char) {
-            let left = &expr[..pos];
-            let right = &expr[pos+1..];
-            
-            let left_expr = parse_cell_or_number(left)?;
-            let right_expr = parse_cell_or_number(right)?;
-            
-            return Ok(Formula::new(Expr::Binary(
-                Box::new(left_expr),
-                op_type,
-                Box::new(right_expr),
-            )));
-        }
+/// Parse an operation argument as a range, cell reference, number or text
+pub fn parse_argument(arg: &str) -> Expr {
+    let arg = arg.trim();
+    if let Ok(range) = CellRange::parse(arg) {
+        Expr::Range(range.start, range.end)
+    } else if let Ok(cell) = CellRef::parse(arg) {
+        Expr::CellRef(cell)
+    } else if let Ok(num) = arg.parse::<f64>() {
+        Expr::Number(num)
+    } else {
+        // Keep as string for operations that need it
+        Expr::Text(arg.trim_matches('"').to_string())
     }
-    
-    // Try single cell/number
-    parse_cell_or_number(expr).map(|expr| Formula::new(expr))
 }
 
-fn parse_cell_or_number(text: &str) -> Result<Expr, String> {
-    let text = text.trim();
-    
-    // Try cell reference
-    if let Ok(cell) = CellRef::parse(text) {
-        return Ok(Expr::CellRef(cell));
+/// Parse arithmetic on cells, numbers and "text" (A1+B2*C3, (A1-1)/2, A1 & " kg", A1>0) with
+/// Excel's precedence: unary minus, then `^`, then `* /`, then `+ -`, then `&`, then
+/// comparisons. Operators on the same level group left to right, except `^` which groups
+/// right to left. Stored formulas (`Formula::parse`) use this grammar too.
+pub(crate) fn parse_binary_operation(formula: &str) -> Result<Formula, ParseError> {
+    let mut parser = BinaryParser { text: formula, tokens: tokenize(formula)?, pos: 0, depth: 0 };
+    let parsed = parser.comparison()?;
+
+    if parser.peek().is_some() {
+        return Err(parser.unexpected());
     }
-    
-    // Try number
-    if let Ok(num) = text.parse::<f64>() {
-        return Ok(Expr::Number(num));
+    Ok(Formula::new(parsed))
+}
+
+/// Deepest nesting of parentheses, arguments, minus signs and powers a formula may use,
+/// so a pathological formula is a parse error rather than a stack overflow
+const MAX_NESTING: usize = 100;
+
+/// Recursive-descent state for `parse_binary_operation`: one method per precedence level
+struct BinaryParser<'a> {
+    text: &'a str, // The whole formula, '=' included, so positions match token spans
+    tokens: Vec<Token<'a>>,
+    pos: usize, // Index of the next unread token
+    depth: usize, // Levels currently open, see `nested`
+}
+
+impl<'a> BinaryParser<'a> {
+    /// Concatenations compared with = <> < <= > >=
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        const COMPARISONS: [(&str, BinaryOp); 6] = [
+            ("=", BinaryOp::Equal), ("<>", BinaryOp::NotEqual),
+            ("<", BinaryOp::Less), ("<=", BinaryOp::LessEqual),
+            (">", BinaryOp::Greater), (">=", BinaryOp::GreaterEqual),
+        ];
+        let mut expr = self.concat()?;
+        while let Some(op) = self.eat_op(&COMPARISONS) {
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.concat()?));
+        }
+        Ok(expr)
+    }
+
+    /// Sums joined by &
+    fn concat(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.sum()?;
+        while let Some(op) = self.eat_op(&[("&", BinaryOp::Concat)]) {
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.sum()?));
+        }
+        Ok(expr)
+    }
+
+    /// Terms joined by + and -
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.product()?;
+        while let Some(op) = self.eat_op(&[("+", BinaryOp::Add), ("-", BinaryOp::Subtract)]) {
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    /// Factors joined by * and /
+    fn product(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.power()?;
+        while let Some(op) = self.eat_op(&[("*", BinaryOp::Multiply), ("/", BinaryOp::Divide)]) {
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.power()?));
+        }
+        Ok(expr)
+    }
+
+    /// Base, optionally raised to a power: 2^3^2 is 2^(3^2)
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let base = self.unary()?;
+        match self.eat_op(&[("^", BinaryOp::Power)]) {
+            Some(op) => Ok(Expr::Binary(Box::new(base), op, Box::new(self.nested(Self::power)?))),
+            None => Ok(base),
+        }
+    }
+
+    /// Optional leading minus: -A1 is (0 - A1), so -A1^2 squares the negated value like Excel
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.eat_op(&[("-", BinaryOp::Subtract)]).is_none() {
+            return self.operand();
+        }
+        Ok(match self.nested(Self::unary)? {
+            Expr::Number(n) => Expr::Number(-n),
+            operand => Expr::Group(Box::new(Expr::Binary(
+                Box::new(Expr::Number(0.0)),
+                BinaryOp::Subtract,
+                Box::new(operand),
+            ))),
+        })
+    }
+
+    /// Cell reference, range, number, "text", name, function call or parenthesized expression
+    fn operand(&mut self) -> Result<Expr, ParseError> {
+        let Some(token) = self.peek() else {
+            return Err(self.unexpected());
+        };
+        let invalid = |message: String| ParseError::at(self.text, token.span.start, message);
+        let expr = match token.kind {
+            TokenKind::Text => {
+                let quoted = &token.text[1..token.text.len() - 1];
+                Expr::Text(quoted.replace("\"\"", "\""))
+            }
+            TokenKind::LParen => {
+                self.pos += 1;
+                let inner = self.nested(Self::comparison)?;
+                self.expect_close()?;
+                return Ok(Expr::Group(Box::new(inner)));
+            }
+            TokenKind::FunctionName => {
+                self.pos += 2; // The name and the '(' the lexer saw after it
+                return Ok(Expr::Function(token.text.to_uppercase(), self.arguments()?));
+            }
+            TokenKind::Range => {
+                let range = CellRange::parse(token.text).map_err(invalid)?;
+                Expr::Range(range.start, range.end)
+            }
+            TokenKind::Name => Expr::Name(token.text.to_string()),
+            TokenKind::CellRef => Expr::CellRef(CellRef::parse(token.text).map_err(invalid)?),
+            TokenKind::Number => Expr::Number(token.text.parse()
+                .map_err(|_| invalid(format!("could not parse '{}' as a number", token.text)))?),
+            TokenKind::Operator | TokenKind::Comma | TokenKind::RParen => return Err(self.unexpected()),
+        };
+        self.pos += 1;
+        Ok(expr)
+    }
+
+    /// Comma-separated function arguments, after the opening '(' up to and including ')'
+    fn arguments(&mut self) -> Result<Vec<Expr>, ParseError> {
+        let mut args = Vec::new();
+        if self.peek().is_some_and(|token| token.kind == TokenKind::RParen) {
+            self.pos += 1;
+            return Ok(args);
+        }
+        loop {
+            args.push(self.nested(Self::comparison)?);
+            match self.peek().map(|token| token.kind) {
+                Some(TokenKind::Comma) => self.pos += 1,
+                Some(TokenKind::RParen) => {
+                    self.pos += 1;
+                    return Ok(args);
+                }
+                Some(_) => return Err(self.unexpected()),
+                None => return Err(ParseError::at(self.text, self.text.len(), "missing ')'")),
+            }
+        }
+    }
+
+    /// Run `parse` one level deeper (inside parentheses, an argument, a minus or an
+    /// exponent), failing once `MAX_NESTING` levels are open
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Expr, ParseError>) -> Result<Expr, ParseError> {
+        if self.depth == MAX_NESTING {
+            let position = self.peek().map_or(self.text.len(), |token| token.span.start);
+            return Err(ParseError::at(
+                self.text, position, format!("formula is nested more than {} levels deep", MAX_NESTING),
+            ));
+        }
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    fn expect_close(&mut self) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::RParen => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(_) => Err(self.unexpected()),
+            None => Err(ParseError::at(self.text, self.text.len(), "missing ')'")),
+        }
+    }
+
+    /// Error for the token at the cursor, or for running out of formula
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(token) => ParseError::at(self.text, token.span.start, format!("unexpected '{}'", token.text)),
+            None => ParseError::at(self.text, self.text.len(), "unexpected end of formula"),
+        }
+    }
+
+    /// Consume the next token if it's one of the operators in `ops`
+    fn eat_op(&mut self, ops: &[(&str, BinaryOp)]) -> Option<BinaryOp> {
+        let token = self.peek().filter(|token| token.kind == TokenKind::Operator)?;
+        let (_, op) = ops.iter().find(|(text, _)| *text == token.text)?;
+        self.pos += 1;
+        Some(*op)
+    }
+
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.pos).copied()
     }
-    
-    Err(format!("Could not parse '{}' as cell or number", text))
+}
+
+/// Parse a formula and evaluate it against the grid, without running operations
+pub fn evaluate_formula(formula: &str, grid: &QuantumGrid) -> Result<CellValue, String> {
+    let parsed = parse_formula_with_ops(formula, &OperationRegistry::new())?;
+    Ok(Evaluator::new(grid).eval(&parsed.expression))
 }
 
 /// Parse and execute formula with operations
@@ -235,19 +431,197 @@ pub fn execute_formula(formula: &str, grid: &mut QuantumGrid) -> Result<String,
     let registry = OperationRegistry::new();
     let formula_parsed = parse_formula_with_ops(formula, &registry)?;
     
-    // Check if it's a registered operation
+    // Check if it's a registered operation; other functions, operations whose
+    // arguments are nested expressions, and Custom operations (which write to the
+    // grid) go to the evaluator, so a formula never changes the sheet
     if let Expr::Function(name, args) = &formula_parsed.expression {
+        let nested = args.iter().any(|arg| {
+            !matches!(arg, Expr::CellRef(_) | Expr::Range(_, _) | Expr::Number(_) | Expr::Text(_))
+        });
+        let writes = registry.get(name).is_some_and(|op| op.op_type == OperationType::Custom);
+        if registry.get(name).is_none() || nested || writes {
+            return Ok(Evaluator::new(grid).eval(&formula_parsed.expression).to_string());
+        }
         let arg_strings: Vec<String> = args.iter()
             .map(|arg| match arg {
                 Expr::CellRef(cell) => cell.to_excel(),
+                Expr::Range(start, end) => format!("{}:{}", start.to_excel(), end.to_excel()),
                 Expr::Number(n) => n.to_string(),
+                Expr::Text(text) => text.clone(),
                 _ => "".to_string(),
             })
             .collect();
         
-        return registry.execute(name, grid, &arg_strings);
+        // Only pure calculations are callable from a formula, never generators
+        return registry.execute_typed(name, OperationType::Calculation, grid, &arg_strings);
     }
     
-    // For simple formulas, return the parsed form
-    Ok(format!("Parsed: {}", formula_parsed.to_excel()))
+    // Everything else is evaluated against the grid
+    let value = Evaluator::new(grid).eval(&formula_parsed.expression);
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(formula: &str, grid: &mut QuantumGrid) -> String {
+        execute_formula(formula, grid).unwrap()
+    }
+
+    #[test]
+    fn test_binary_operations_respect_precedence() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 2.0).unwrap();
+        grid.set_cell("B2", 3.0).unwrap();
+        grid.set_cell("C3", 4.0).unwrap();
+
+        assert_eq!(eval("=A1+B2*C3", &mut grid), "14");
+        assert_eq!(eval("=B2*C3+A1", &mut grid), "14");
+        assert_eq!(eval("=(A1+B2)*C3", &mut grid), "20");
+        assert_eq!(eval("=C3-A1*B2/2", &mut grid), "1");
+        assert_eq!(eval("=2^3^2", &mut grid), "512");
+        assert_eq!(eval("=-A1^2", &mut grid), "4");
+        assert_eq!(eval("=A1*-B2", &mut grid), "-6");
+    }
+
+    #[test]
+    fn test_nested_rounding() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 10.0).unwrap();
+        grid.set_cell("B1", 3.0).unwrap();
+
+        assert_eq!(eval("=ROUND(A1/B1, 2)", &mut grid), "3.33");
+        assert_eq!(eval("=ROUND(-A1/B1, 0)", &mut grid), "-3");
+        assert_eq!(eval("=CEILING(A1/B1)*2", &mut grid), "8");
+        assert_eq!(eval("=ABS(FLOOR(-A1/B1))", &mut grid), "4");
+        assert_eq!(eval("=ROUND(SUM(A1:B1)/4, 1)", &mut grid), "3.3");
+    }
+
+    #[test]
+    fn test_concat_operator() {
+        let mut grid = QuantumGrid::new();
+        grid.set_text("A1", "Total: ").unwrap();
+        grid.set_cell("B1", 40.0).unwrap();
+
+        assert_eq!(eval("=A1 & B1", &mut grid), "Total: 40");
+        assert_eq!(eval("=A1&B1+2&\" kg\"", &mut grid), "Total: 42 kg");
+        assert_eq!(eval("=\"say \"\"hi\"\"\" & 1", &mut grid), "say \"hi\"1");
+    }
+
+    #[test]
+    fn test_equality_tolerates_rounding() {
+        use crate::compute::{approx_eq, DEFAULT_EPSILON};
+
+        assert_ne!(0.1 + 0.2, 0.3);
+        assert!(approx_eq(0.1 + 0.2, 0.3, DEFAULT_EPSILON));
+        assert!(!approx_eq(0.1 + 0.2, 0.3, 0.0));
+        assert!(approx_eq(1e15 + 0.1, 1e15, DEFAULT_EPSILON));
+        assert!(!approx_eq(1.0, 1.001, DEFAULT_EPSILON));
+
+        let mut grid = QuantumGrid::new();
+        assert_eq!(grid.comparison_epsilon(), DEFAULT_EPSILON);
+        assert_eq!(eval("=0.1+0.2=0.3", &mut grid), "1");
+        assert_eq!(eval("=0.1+0.2<>0.3", &mut grid), "0");
+        assert_eq!(eval("=0.1+0.2>0.3", &mut grid), "0");
+        assert_eq!(eval("=1.001=1", &mut grid), "0");
+
+        grid.set_comparison_epsilon(0.0);
+        assert_eq!(eval("=0.1+0.2=0.3", &mut grid), "0");
+        grid.set_comparison_epsilon(0.01);
+        assert_eq!(eval("=1.001=1", &mut grid), "1");
+    }
+
+    #[test]
+    fn test_binary_operations_chain_same_level() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 10.0).unwrap();
+        grid.set_cell("B2", 3.0).unwrap();
+        grid.set_cell("C3", 2.0).unwrap();
+
+        assert_eq!(eval("=A1+B2+C3", &mut grid), "15");
+        assert_eq!(eval("=A1-B2-C3", &mut grid), "5");
+        assert_eq!(eval("=A1 / C3 / C3", &mut grid), "2.5");
+        assert_eq!(eval("=1+2+3+4", &mut grid), "10");
+
+        for bad in ["=A1+", "=(A1+B2", "=A1 B2", "=A1+*B2", "=A1 & \"open"] {
+            assert!(execute_formula(bad, &mut grid).is_err(), "{} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn test_nom_parser_reads_ranges() {
+        let cell = |reference: &str| CellRef::parse(reference).unwrap();
+        let range = Expr::Range(cell("A1"), cell("A10"));
+
+        let (rest, formula) = parse_formula("=SUM(A1:A10)").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(formula.expression, Expr::Function("SUM".to_string(), vec![range.clone()]));
+
+        let (rest, formula) = parse_formula("=A1:A10").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(formula.expression, range);
+
+        // A cell that isn't followed by ':' is still a plain reference
+        let formula = parse_formula_safe("=SUM(A1:B2)*C3").unwrap();
+        assert_eq!(formula.expression, Expr::Binary(
+            Box::new(Expr::Function("SUM".to_string(), vec![Expr::Range(cell("A1"), cell("B2"))])),
+            BinaryOp::Multiply,
+            Box::new(Expr::CellRef(cell("C3"))),
+        ));
+    }
+
+    #[test]
+    fn test_parse_errors_report_positions() {
+        let error_at = |formula: &str| {
+            let error = parse_binary_operation(formula).unwrap_err();
+            (error.position, error.to_string())
+        };
+
+        assert_eq!(error_at("=A1+"), (4, "unexpected end of formula at position 4".to_string()));
+        assert_eq!(error_at("=A1+*B2"), (4, "unexpected '*' at position 4".to_string()));
+        assert_eq!(error_at("=(A1+B2"), (7, "missing ')' at position 7".to_string()));
+        assert_eq!(error_at("=SUM(A1)+B2)"), (11, "unexpected ')' at position 11".to_string()));
+        assert_eq!(parse_binary_operation("=A1 B2*2").unwrap_err().near, "B2*2");
+    }
+
+    #[test]
+    fn test_deep_nesting_is_an_error_not_a_crash() {
+        let nested = |depth: usize| format!("={}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse_binary_operation(&nested(50)).is_ok());
+
+        let error = parse_binary_operation(&nested(20_000)).unwrap_err();
+        assert_eq!(error.message, "formula is nested more than 100 levels deep");
+        assert!(parse_binary_operation(&format!("={}1", "-".repeat(20_000))).is_err());
+        assert!(parse_binary_operation(&format!("=2{}", "^2".repeat(20_000))).is_err());
+
+        // The same from a command and from a stored formula
+        let mut grid = QuantumGrid::new();
+        assert!(execute_formula(&nested(20_000), &mut grid).is_err());
+        assert!(grid.set_formula("A1", &format!("=SUM({})", nested(20_000).trim_start_matches('='))).is_err());
+    }
+
+    #[test]
+    fn test_formulas_cannot_change_the_grid() {
+        let mut grid = QuantumGrid::new();
+        for (row, value) in [3.0, 1.0, 3.0].iter().enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *value).unwrap();
+        }
+        let unchanged = |grid: &QuantumGrid| {
+            let column: Vec<CellValue> = (1..=3).map(|row| grid.get_cell_value(&CellRef::new(row, 1))).collect();
+            assert_eq!(column, [3.0, 1.0, 3.0].map(CellValue::Number));
+            assert_eq!(grid.used_range().map(|used| used.to_excel()), Some("A1:A3".to_string()));
+        };
+
+        // Operations that write to the grid aren't run from a formula
+        for formula in ["=UNIQUE(A1:A3, C1)", "=FILTER(A1:A3, \">1\", C1)", "=CORRMATRIX(A1:A3, C1)", "=MOVAVG(A1:A3, 2)", "=CUMSUM(A1:A3)", "=SORT(A1:A3)"] {
+            assert_eq!(eval(formula, &mut grid), "#NAME?", "{}", formula);
+            unchanged(&grid);
+        }
+
+        // TRIM and CLEAN return the tidied text instead of rewriting the cell
+        grid.set_text("B1", "  north ").unwrap();
+        assert_eq!(eval("=TRIM(B1)", &mut grid), "north");
+        assert_eq!(grid.get_cell_value(&CellRef::new(1, 2)), CellValue::Text("  north ".to_string()));
+    }
 }
@@ -0,0 +1,91 @@
+//! Cell-by-cell comparison of two grids
+
+use std::collections::BTreeSet;
+
+use super::QuantumGrid;
+use crate::excel::CellRef;
+use crate::formula::evaluator::CellValue;
+
+/// One difference between two grids. Values are evaluated, so a formula cell
+/// whose result moved shows up as `Changed`; a different formula reporting the
+/// same result only shows up as `FormulaChanged`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellDiff {
+    /// Empty in the old grid, set in the new one
+    Added { cell: CellRef, value: CellValue },
+    /// Set in the old grid, empty in the new one
+    Removed { cell: CellRef, value: CellValue },
+    /// Set in both, to different values
+    Changed { cell: CellRef, old: CellValue, new: CellValue },
+    /// Formula source differs; None where the cell holds no formula
+    FormulaChanged { cell: CellRef, old: Option<String>, new: Option<String> },
+}
+
+impl QuantumGrid {
+    /// What changed going from `self` to `other`, row by row then column by column.
+    /// A cell whose formula and value both changed reports the value first.
+    pub fn diff(&self, other: &QuantumGrid) -> Vec<CellDiff> {
+        let cells: BTreeSet<(u32, u32)> = self.iter_cells(false)
+            .chain(other.iter_cells(false))
+            .map(|(cell, _)| (cell.row, cell.col))
+            .collect();
+
+        let mut diffs = Vec::new();
+        for (row, col) in cells {
+            let cell = CellRef::new(row, col);
+            let old = self.get_cell_value(&cell);
+            let new = other.get_cell_value(&cell);
+            match (old, new) {
+                (old, new) if old == new => {}
+                (CellValue::Empty, value) => diffs.push(CellDiff::Added { cell, value }),
+                (value, CellValue::Empty) => diffs.push(CellDiff::Removed { cell, value }),
+                (old, new) => diffs.push(CellDiff::Changed { cell, old, new }),
+            }
+
+            let old = self.formula_text(&cell);
+            let new = other.formula_text(&cell);
+            if old != new {
+                diffs.push(CellDiff::FormulaChanged { cell, old, new });
+            }
+        }
+        diffs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_against_mutated_copy() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 1.0).unwrap();
+        grid.set_cell("A2", 2.0).unwrap();
+        grid.set_text("B1", "north").unwrap();
+        grid.set_formula("C1", "=A1+A2").unwrap();
+
+        let mut copy = QuantumGrid::from_json(&grid.to_json()).unwrap();
+        assert_eq!(grid.diff(&copy), Vec::new());
+
+        copy.set_cell("A2", 5.0).unwrap();
+        copy.set_text("B1", "south").unwrap();
+        copy.set_cell("D4", 9.0).unwrap();
+        copy.set_formula("C2", "=A1+A1").unwrap();
+
+        let cell = |reference: &str| CellRef::parse(reference).unwrap();
+        let text = |text: &str| CellValue::Text(text.to_string());
+        assert_eq!(grid.diff(&copy), vec![
+            CellDiff::Changed { cell: cell("B1"), old: text("north"), new: text("south") },
+            CellDiff::Changed { cell: cell("C1"), old: CellValue::Number(3.0), new: CellValue::Number(6.0) },
+            CellDiff::Changed { cell: cell("A2"), old: CellValue::Number(2.0), new: CellValue::Number(5.0) },
+            CellDiff::Added { cell: cell("C2"), value: CellValue::Number(2.0) },
+            CellDiff::FormulaChanged { cell: cell("C2"), old: None, new: Some("=A1 + A1".to_string()) },
+            CellDiff::Added { cell: cell("D4"), value: CellValue::Number(9.0) },
+        ]);
+
+        // The other way round, additions become removals
+        let reverse = copy.diff(&grid);
+        assert_eq!(reverse[3], CellDiff::Removed { cell: cell("C2"), value: CellValue::Number(2.0) });
+        assert_eq!(reverse[4], CellDiff::FormulaChanged { cell: cell("C2"), old: Some("=A1 + A1".to_string()), new: None });
+    }
+}
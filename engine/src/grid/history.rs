@@ -0,0 +1,189 @@
+//! Undo/redo journal - records what each edit replaced
+
+use std::collections::{HashSet, VecDeque};
+
+use super::QuantumGrid;
+use crate::excel::CellRef;
+use crate::formula::ast::Formula;
+
+/// What a single cell holds
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum CellContent {
+    Empty,
+    Number(f64),
+    Text(String),
+    Formula(Formula),
+}
+
+/// One cell's contents before and after an edit
+#[derive(Debug, Clone)]
+struct Change {
+    cell: CellRef,
+    before: CellContent,
+    after: CellContent,
+}
+
+/// Bounded undo stack plus the redo stack it feeds
+#[derive(Debug)]
+pub(super) struct History {
+    undo: VecDeque<Vec<Change>>,
+    redo: Vec<Vec<Change>>,
+    depth: usize,
+    transaction: Option<Vec<(CellRef, CellContent)>>, // Contents replaced inside `transaction`, in order
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self { undo: VecDeque::new(), redo: Vec::new(), depth: 100, transaction: None }
+    }
+}
+
+impl History {
+    /// Forget every recorded edit (e.g. after rows/columns move)
+    pub(super) fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl QuantumGrid {
+    /// Undo the most recent edit. Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(changes) = self.history.undo.pop_back() else {
+            return false;
+        };
+
+        for change in changes.iter().rev() {
+            self.restore(&change.cell, change.before.clone());
+        }
+        self.notify(&changes.iter().map(|change| change.cell).collect::<Vec<_>>());
+        self.history.redo.push(changes);
+        true
+    }
+
+    /// Re-apply the most recently undone edit. Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(changes) = self.history.redo.pop() else {
+            return false;
+        };
+
+        for change in &changes {
+            self.restore(&change.cell, change.after.clone());
+        }
+        self.notify(&changes.iter().map(|change| change.cell).collect::<Vec<_>>());
+        self.history.undo.push_back(changes);
+        true
+    }
+
+    /// Run `edit` all-or-nothing: if it returns `Err`, every cell it wrote is put
+    /// back as it was; on `Ok` its writes become one undoable edit, reported to the
+    /// change callback once. Reads inside `edit` see its own writes. A transaction
+    /// inside another joins the outer one. Row and column inserts and deletes
+    /// aren't journaled, so they are not rolled back.
+    pub fn transaction<T>(
+        &mut self,
+        edit: impl FnOnce(&mut QuantumGrid) -> Result<T, String>,
+    ) -> Result<T, String> {
+        if self.history.transaction.is_some() {
+            return edit(self);
+        }
+
+        self.history.transaction = Some(Vec::new());
+        let result = edit(self);
+        let replaced = self.history.transaction.take().unwrap_or_default();
+
+        if result.is_err() {
+            for (cell_ref, content) in replaced.into_iter().rev() {
+                self.restore(&cell_ref, content);
+            }
+            return result;
+        }
+
+        // Each cell's contents from before its first write
+        let mut seen = HashSet::new();
+        let before = replaced.into_iter().filter(|(cell_ref, _)| seen.insert(*cell_ref)).collect();
+        self.record(before);
+        result
+    }
+
+    /// Keep at most `depth` edits in the undo history (default 100)
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history.depth = depth;
+        while self.history.undo.len() > depth {
+            self.history.undo.pop_front();
+        }
+    }
+
+    /// Current contents of a cell
+    pub(super) fn content(&self, cell_ref: &CellRef) -> CellContent {
+        if let Some(formula) = self.formulas.get(cell_ref) {
+            CellContent::Formula(formula.clone())
+        } else if let Some(text) = self.texts.get(cell_ref) {
+            CellContent::Text(text.clone())
+        } else if let Some(value) = self.get_value(cell_ref) {
+            CellContent::Number(value)
+        } else {
+            CellContent::Empty
+        }
+    }
+
+    /// Journal an edit, given each touched cell's contents from before it,
+    /// and report the cells that actually changed.
+    /// A new edit makes the redo stack meaningless, so it's dropped.
+    pub(super) fn record(&mut self, before: Vec<(CellRef, CellContent)>) {
+        // Inside a transaction, journaling waits for it to commit
+        if let Some(replaced) = self.history.transaction.as_mut() {
+            replaced.extend(before);
+            return;
+        }
+
+        let changes: Vec<Change> = before
+            .into_iter()
+            .map(|(cell, before)| Change { cell, after: self.content(&cell), before })
+            .filter(|change| change.before != change.after)
+            .collect();
+        if changes.is_empty() {
+            return;
+        }
+
+        self.notify(&changes.iter().map(|change| change.cell).collect::<Vec<_>>());
+        if self.history.depth == 0 {
+            return;
+        }
+
+        self.history.redo.clear();
+        self.history.undo.push_back(changes);
+        if self.history.undo.len() > self.history.depth {
+            self.history.undo.pop_front();
+        }
+    }
+
+    /// Put a cell back to recorded contents without journaling it
+    pub(super) fn restore(&mut self, cell_ref: &CellRef, content: CellContent) {
+        match content {
+            CellContent::Number(value) => self.set_value(cell_ref, value),
+            CellContent::Text(text) => self.store_text(cell_ref, &text),
+            CellContent::Formula(formula) => self.store_formula(cell_ref, formula),
+            CellContent::Empty => {
+                self.formulas.remove(cell_ref);
+                self.texts.remove(cell_ref);
+                self.clear_value(cell_ref);
+                self.invalidate(cell_ref);
+            }
+        }
+    }
+
+    /// Drop a cell's number, leaving the cell empty
+    fn clear_value(&mut self, cell_ref: &CellRef) {
+        let (row_idx, col_idx) = cell_ref.to_zero_based();
+        let Some(column) = self.columns.get_mut(&(col_idx as u32)) else {
+            return;
+        };
+
+        column.unset(row_idx);
+
+        if column.is_empty() {
+            self.columns.remove(&(col_idx as u32));
+        }
+    }
+}
@@ -1,14 +1,135 @@
 //! QuantumGrid - Multiple columns spreadsheet with formula support
 
-use crate::excel::CellRef;
-use crate::formula::ast::Formula;
+mod diff;
+mod history;
+mod snapshot;
+
+use crate::excel::{column_letters, CellRange, CellRef, ReferenceStyle, MAX_COLS, MAX_ROWS};
+use crate::export::NumberFormat;
+use crate::formula::ast::{Axis, Formula};
+use crate::formula::evaluator::{CellError, CellValue, Evaluator, TextCoercion};
 use crate::storage::QuantumColumn; // Updated import
-use std::collections::HashMap; // Updated import
+use history::{CellContent, History};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet}; // Updated import
+
+pub use diff::CellDiff;
 
 /// Main spreadsheet grid
 pub struct QuantumGrid {
     columns: HashMap<u32, QuantumColumn>,
     formulas: HashMap<CellRef, Formula>,
+    texts: HashMap<CellRef, String>,
+    meta: HashMap<CellRef, BTreeMap<String, String>>, // Frontend tags, never interpreted
+    headers: HashMap<u32, String>, // 1-based column → header name
+    text_coercion: TextCoercion,
+    epsilon: f64, // Tolerance of `=` and the other comparison operators
+    reference_style: ReferenceStyle, // Notation formulas are displayed in
+    number_format: NumberFormat,     // How numbers are displayed and exported
+    limits: GridLimits,
+    functions: HashMap<String, UserFunction>, // User-defined functions by uppercase name
+    value_cache: RefCell<HashMap<CellRef, CellValue>>, // Evaluated formula results
+    recompute_mode: RecomputeMode,
+    stale: HashSet<CellRef>, // Edited cells whose dependents await recompute() (Manual mode)
+    evaluations: Cell<usize>, // Formula evaluations that missed the cache
+    history: History,         // Undo/redo journal
+    on_change: Option<ChangeCallback>, // Fired with edited cells and their dependents
+}
+
+/// Callback fired with the cells an edit changed
+type ChangeCallback = Box<dyn FnMut(&[CellRef])>;
+
+/// When formulas downstream of an edit are recalculated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecomputeMode {
+    /// Dependents are recalculated as part of every edit
+    #[default]
+    Eager,
+    /// Dependents keep their previous results until `recompute()` is called
+    Manual,
+}
+
+/// What `fill_range` writes into each cell
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FillSpec {
+    /// The same number everywhere
+    Constant(f64),
+    /// start, start + step, start + 2 * step, ... along the range
+    Series { start: f64, step: f64 },
+}
+
+/// Largest row/column a grid accepts writes to (1-based, inclusive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridLimits {
+    pub max_rows: u32,
+    pub max_cols: u32,
+}
+
+impl Default for GridLimits {
+    /// Excel's own limits: 1,048,576 rows x 16,384 columns (XFD)
+    fn default() -> Self {
+        Self {
+            max_rows: MAX_ROWS,
+            max_cols: MAX_COLS,
+        }
+    }
+}
+
+/// Formula with named parameters, callable like a built-in: TAX(x) = "=x*0.13"
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserFunction {
+    pub params: Vec<String>, // Uppercase
+    pub body: Formula,
+}
+
+/// Excel uses ~40 bytes per cell
+pub const EXCEL_BYTES_PER_CELL: f64 = 40.0;
+
+/// Measured bytes per cell against Excel's baseline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EfficiencyReport {
+    pub our_bytes_per_cell: f64,
+    pub excel_bytes_per_cell: f64,
+    pub ratio: f64, // excel / ours; 0 for an empty grid
+}
+
+/// Memory used by a grid's cell storage
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryReport {
+    pub total_cells: usize,
+    pub total_bytes: usize,
+    pub bytes_per_cell: f64, // 0 for an empty grid
+}
+
+/// Cell and formula counts for a grid, as `print_stats` shows them
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct GridStats {
+    pub columns: usize,
+    pub total_cells: usize, // Numeric values written, formulas not included
+    pub formula_count: usize,
+    pub per_column: Vec<ColumnSummary>, // Left to right
+}
+
+/// One column's entry in `GridStats`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ColumnSummary {
+    pub name: String, // "A", "B", ...
+    pub count: usize,
+    pub sum: f64,
+}
+
+impl MemoryReport {
+    /// Excel uses ~40 bytes per cell
+    pub const EXCEL_BYTES_PER_CELL: f64 = EXCEL_BYTES_PER_CELL;
+
+    /// How many times less memory per cell than Excel (0 for an empty grid)
+    pub fn vs_excel(&self) -> f64 {
+        if self.bytes_per_cell > 0.0 {
+            Self::EXCEL_BYTES_PER_CELL / self.bytes_per_cell
+        } else {
+            0.0
+        }
+    }
 }
 
 impl QuantumGrid {
@@ -17,18 +138,379 @@ impl QuantumGrid {
         Self {
             columns: HashMap::new(),
             formulas: HashMap::new(),
+            texts: HashMap::new(),
+            meta: HashMap::new(),
+            headers: HashMap::new(),
+            text_coercion: TextCoercion::default(),
+            epsilon: crate::compute::DEFAULT_EPSILON,
+            reference_style: ReferenceStyle::default(),
+            number_format: NumberFormat::default(),
+            limits: GridLimits::default(),
+            functions: HashMap::new(),
+            value_cache: RefCell::new(HashMap::new()),
+            recompute_mode: RecomputeMode::default(),
+            stale: HashSet::new(),
+            evaluations: Cell::new(0),
+            history: History::default(),
+            on_change: None,
+        }
+    }
+
+    /// Build a grid from CSV text (see `Importer::csv_to_grid`)
+    pub fn from_csv(text: &str) -> Result<Self, String> {
+        let mut grid = Self::new();
+        crate::import::Importer::csv_to_grid(&mut grid, text)?;
+        Ok(grid)
+    }
+
+    /// Remove every value, text cell and formula (settings are kept).
+    /// Can be undone.
+    pub fn clear(&mut self) {
+        let mut cells: Vec<CellRef> = self.formulas.keys().chain(self.texts.keys()).copied().collect();
+        for (col_idx, column) in &self.columns {
+            cells.extend(column.entries().map(|(row_idx, _)| CellRef::new(row_idx as u32 + 1, col_idx + 1)));
+        }
+        // Text cells also have a placeholder number underneath
+        cells.sort_by_key(|cell| (cell.row, cell.col));
+        cells.dedup();
+        let before: Vec<_> = cells.into_iter().map(|cell| (cell, self.content(&cell))).collect();
+
+        self.columns.clear();
+        self.formulas.clear();
+        self.texts.clear();
+        self.meta.clear();
+        self.headers.clear();
+        self.clear_cache();
+        self.record(before);
+    }
+
+    /// How text is treated in arithmetic when formulas are evaluated
+    pub fn text_coercion(&self) -> TextCoercion {
+        self.text_coercion
+    }
+
+    /// Choose Strict (text → #VALUE!) or Coerce (numeric-looking text is parsed)
+    pub fn set_text_coercion(&mut self, coercion: TextCoercion) {
+        self.text_coercion = coercion;
+        self.clear_cache();
+    }
+
+    /// Tolerance formulas compare numbers with (`compute::DEFAULT_EPSILON` by default)
+    pub fn comparison_epsilon(&self) -> f64 {
+        self.epsilon
+    }
+
+    /// Set the tolerance of `=`, `<>`, `<` and the other comparisons; 0 compares exactly
+    pub fn set_comparison_epsilon(&mut self, epsilon: f64) {
+        self.epsilon = epsilon.abs();
+        self.clear_cache();
+    }
+
+    /// Make `name` callable from formulas, replacing any earlier definition.
+    /// Checking the name and body is up to the caller (see `QuantumAPI::define_function`).
+    pub fn define_function(&mut self, name: &str, function: UserFunction) {
+        self.functions.insert(name.to_uppercase(), function);
+        self.clear_cache();
+    }
+
+    /// The user-defined function called `name` (case-insensitive)
+    pub fn user_function(&self, name: &str) -> Option<&UserFunction> {
+        self.functions.get(&name.to_uppercase())
+    }
+
+    /// Notation formulas are displayed in
+    pub fn reference_style(&self) -> ReferenceStyle {
+        self.reference_style
+    }
+
+    /// Display formulas with A1 ("=C5+1") or R1C1 ("=R5C3+1") references
+    pub fn set_reference_style(&mut self, style: ReferenceStyle) {
+        self.reference_style = style;
+    }
+
+    /// How numbers are displayed and exported
+    pub fn number_format(&self) -> &NumberFormat {
+        &self.number_format
+    }
+
+    /// Change how numbers are displayed and exported; stored values are unaffected
+    pub fn set_number_format(&mut self, format: NumberFormat) {
+        self.number_format = format;
+    }
+
+    /// A cell's evaluated value as display text, numbers in the grid's number format
+    pub fn display_value(&self, cell_ref: &CellRef) -> String {
+        match self.get_cell_value(cell_ref) {
+            CellValue::Number(n) => self.number_format.format(n),
+            value => value.to_string(),
         }
     }
 
+    /// A cell's formula as text, in the grid's reference style. The grid stores parsed
+    /// formulas, not what was typed, and shows them spaced like `Formula::to_excel_pretty`.
+    pub fn formula_text(&self, cell_ref: &CellRef) -> Option<String> {
+        self.formulas.get(cell_ref).map(|formula| formula.to_style(self.reference_style))
+    }
+
+    /// How many times a formula cell has actually been evaluated (cache misses)
+    pub fn formula_evaluations(&self) -> usize {
+        self.evaluations.get()
+    }
+
+    pub(crate) fn record_evaluation(&self) {
+        self.evaluations.set(self.evaluations.get() + 1);
+    }
+
+    pub(crate) fn cached_value(&self, cell_ref: &CellRef) -> Option<CellValue> {
+        self.value_cache.borrow().get(cell_ref).cloned()
+    }
+
+    pub(crate) fn cache_value(&self, cell_ref: CellRef, value: CellValue) {
+        self.value_cache.borrow_mut().insert(cell_ref, value);
+    }
+
+    /// Drop every cached result, including any pending Manual-mode recompute
+    fn clear_cache(&mut self) {
+        self.value_cache.get_mut().clear();
+        self.stale.clear();
+    }
+
+    /// Drop cached results for `cell_ref` and every formula downstream of it.
+    /// Eager mode recalculates those formulas straight away; Manual mode leaves
+    /// them showing their old results until `recompute()`.
+    fn invalidate(&mut self, cell_ref: &CellRef) {
+        let cache = self.value_cache.get_mut();
+        if self.recompute_mode == RecomputeMode::Manual {
+            // The edited cell itself is always current
+            cache.remove(cell_ref);
+            self.stale.insert(*cell_ref);
+            return;
+        }
+        self.recalculate(&[*cell_ref]);
+    }
+
+    /// Drop cached results downstream of `edited` and evaluate each dropped
+    /// formula once, however many of the edited cells it reads
+    fn recalculate(&mut self, edited: &[CellRef]) {
+        let cache = self.value_cache.get_mut();
+        if cache.is_empty() {
+            return;
+        }
+
+        let mut dropped = Vec::new();
+        let mut pending = edited.to_vec();
+        while let Some(changed) = pending.pop() {
+            cache.remove(&changed);
+            for (dependent, formula) in &self.formulas {
+                if cache.contains_key(dependent) && formula.references_cell(&changed) {
+                    pending.push(*dependent);
+                    dropped.push(*dependent);
+                }
+            }
+        }
+
+        for dependent in dropped {
+            self.get_cell_value(&dependent);
+        }
+    }
+
+    /// When dependents of an edit are recalculated
+    pub fn recompute_mode(&self) -> RecomputeMode {
+        self.recompute_mode
+    }
+
+    /// Choose Eager or Manual recalculation; switching to Eager recomputes
+    /// anything left stale
+    pub fn set_recompute_mode(&mut self, mode: RecomputeMode) {
+        self.recompute_mode = mode;
+        if mode == RecomputeMode::Eager {
+            self.recompute();
+        }
+    }
+
+    /// Recalculate the formulas downstream of every edit made since the last
+    /// recompute (only needed in Manual mode)
+    pub fn recompute(&mut self) {
+        let stale: Vec<CellRef> = self.stale.drain().collect();
+        self.recalculate(&stale);
+    }
+
+    /// Throw away every cached result and evaluate all formulas again.
+    /// Returns how many formulas were evaluated.
+    pub fn recalculate_all(&mut self) -> usize {
+        self.clear_cache();
+        let cells: Vec<CellRef> = self.formulas.keys().copied().collect();
+        for cell_ref in &cells {
+            self.get_cell_value(cell_ref);
+        }
+        cells.len()
+    }
+
+    /// Call `callback` after every edit (set_cell, set_text, set_formula, fills,
+    /// clear, undo/redo) with the edited cells followed by the formulas that
+    /// recalculate because of them
+    pub fn set_on_change(&mut self, callback: impl FnMut(&[CellRef]) + 'static) {
+        self.on_change = Some(Box::new(callback));
+    }
+
+    /// Report edited cells and every formula downstream of them to the change callback
+    fn notify(&mut self, edited: &[CellRef]) {
+        let Some(callback) = self.on_change.as_mut() else {
+            return;
+        };
+
+        let mut seen: HashSet<CellRef> = edited.iter().copied().collect();
+        let mut dependents = Vec::new();
+        let mut pending = edited.to_vec();
+        while let Some(changed) = pending.pop() {
+            for (dependent, formula) in &self.formulas {
+                if formula.references_cell(&changed) && seen.insert(*dependent) {
+                    dependents.push(*dependent);
+                    pending.push(*dependent);
+                }
+            }
+        }
+        dependents.sort_by_key(|cell| (cell.row, cell.col));
+
+        let mut affected = edited.to_vec();
+        affected.extend(dependents);
+        callback(&affected);
+    }
+
     /// Set a cell value by Excel reference (e.g., "A1", "B2")
     pub fn set_cell(&mut self, reference: &str, value: f64) -> Result<(), String> {
         let cell_ref = CellRef::parse(reference)?;
+        self.check_bounds(&cell_ref)?;
+
+        let before = self.content(&cell_ref);
+        self.set_value(&cell_ref, value);
+        self.record(vec![(cell_ref, before)]);
+        Ok(())
+    }
+
+    /// Write many numbers as one undoable edit, recalculating dependents once at
+    /// the end. Returns how many cells were written, plus each reference that
+    /// failed and why; a bad reference doesn't stop the rest.
+    pub fn set_cells(&mut self, entries: &[(&str, f64)]) -> (usize, Vec<(String, String)>) {
+        self.write_cells(entries.iter().map(|&(reference, value)| (reference, CellContent::Number(value))))
+    }
+
+    /// Text counterpart of `set_cells`
+    pub fn set_texts(&mut self, entries: &[(&str, &str)]) -> (usize, Vec<(String, String)>) {
+        self.write_cells(entries.iter().map(|&(reference, text)| (reference, CellContent::Text(text.to_string()))))
+    }
+
+    fn write_cells<'a>(
+        &mut self,
+        entries: impl Iterator<Item = (&'a str, CellContent)>,
+    ) -> (usize, Vec<(String, String)>) {
+        let mut contents = Vec::new();
+        let mut errors = Vec::new();
+        for (reference, content) in entries {
+            let parsed = CellRef::parse(reference).and_then(|cell_ref| {
+                self.check_bounds(&cell_ref)?;
+                Ok(cell_ref)
+            });
+            match parsed {
+                Ok(cell_ref) => contents.push((cell_ref, content)),
+                Err(error) => errors.push((reference.to_string(), error)),
+            }
+        }
+        let written = contents.len();
+        self.write_contents(contents);
+        (written, errors)
+    }
 
+    /// Store each cell's contents as one undoable edit, recalculating once at the end
+    fn write_contents(&mut self, contents: Vec<(CellRef, CellContent)>) {
+        // Hold recalculation until every cell is in, then run it once
+        let mode = std::mem::replace(&mut self.recompute_mode, RecomputeMode::Manual);
+        let mut before = Vec::new();
+        let mut seen = HashSet::new();
+        for (cell_ref, content) in contents {
+            if seen.insert(cell_ref) {
+                before.push((cell_ref, self.content(&cell_ref)));
+            }
+            self.restore(&cell_ref, content);
+        }
+
+        self.recompute_mode = mode;
+        if mode == RecomputeMode::Eager {
+            self.recompute();
+        }
+        self.record(before);
+    }
+
+    /// Fill a range with a constant or an arithmetic series, as one undoable edit.
+    /// A series runs across a single-row range and down the columns of any other,
+    /// each column starting again from `start`.
+    pub fn fill_range(&mut self, range: &str, spec: FillSpec) -> Result<(), String> {
+        let range = CellRange::parse(range)?;
+        self.check_bounds(&range.end)?;
+
+        let across = range.height() == 1;
+        let contents = range.cells()
+            .map(|cell_ref| {
+                let value = match spec {
+                    FillSpec::Constant(value) => value,
+                    FillSpec::Series { start, step } => {
+                        let index = if across { cell_ref.col - range.start.col } else { cell_ref.row - range.start.row };
+                        start + step * index as f64
+                    }
+                };
+                (cell_ref, CellContent::Number(value))
+            })
+            .collect();
+        self.write_contents(contents);
+        Ok(())
+    }
+
+    /// Maximum size writes are allowed to reach
+    pub fn limits(&self) -> GridLimits {
+        self.limits
+    }
+
+    /// Lower the writable area, e.g. to keep a browser tab from allocating huge columns
+    pub fn set_limits(&mut self, limits: GridLimits) {
+        self.limits = limits;
+    }
+
+    /// Reject cells outside Excel's sheet or past the configured limits
+    fn check_bounds(&self, cell_ref: &CellRef) -> Result<(), String> {
         if !cell_ref.is_valid() {
-            return Err(format!("Cell reference out of Excel bounds: {}", reference));
+            return Err(format!("Cell reference out of Excel bounds: {}", cell_ref));
         }
 
-        let (_row_idx, col_idx) = cell_ref.to_zero_based();
+        if cell_ref.row > self.limits.max_rows || cell_ref.col > self.limits.max_cols {
+            return Err(format!(
+                "Cell {} is beyond the grid limit of {} rows x {} columns",
+                cell_ref, self.limits.max_rows, self.limits.max_cols
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reject an insert that would push occupied cells past the configured limits
+    fn check_room_to_insert(&self, axis: Axis, at: u32) -> Result<(), String> {
+        let Some(used) = self.used_range() else {
+            return Ok(());
+        };
+        let (name, last, limit) = match axis {
+            Axis::Row => ("row", used.end.row, self.limits.max_rows),
+            Axis::Column => ("column", used.end.col, self.limits.max_cols),
+        };
+        if last >= at && last >= limit {
+            return Err(format!(
+                "Inserting {} {} would push cells beyond the grid limit of {} rows x {} columns",
+                name, at, self.limits.max_rows, self.limits.max_cols
+            ));
+        }
+        Ok(())
+    }
+
+    /// Store a number in a cell, replacing any formula there
+    fn set_value(&mut self, cell_ref: &CellRef, value: f64) {
+        let (row_idx, col_idx) = cell_ref.to_zero_based();
 
         // Get or create column
         let column = self
@@ -36,26 +518,112 @@ impl QuantumGrid {
             .entry(col_idx as u32)
             .or_insert_with(|| QuantumColumn::new(&format!("Col{}", col_idx)));
 
-        // For simplicity, just push (real implementation would insert at row)
-        column.push(value);
+        column.set(row_idx, value);
+        self.formulas.remove(cell_ref);
+        self.texts.remove(cell_ref);
+        self.invalidate(cell_ref);
+    }
+
+    /// Set a text cell by Excel reference (e.g., "B1" = "Male")
+    pub fn set_text(&mut self, reference: &str, text: &str) -> Result<(), String> {
+        let cell_ref = CellRef::parse(reference)?;
+        self.check_bounds(&cell_ref)?;
 
+        let before = self.content(&cell_ref);
+        self.store_text(&cell_ref, text);
+        self.record(vec![(cell_ref, before)]);
         Ok(())
     }
 
-    /// Set a formula in a cell
-    pub fn set_formula(&mut self, reference: &str, formula: &str) -> Result<(), String> {
+    /// Store text in a cell, replacing any number or formula there
+    fn store_text(&mut self, cell_ref: &CellRef, text: &str) {
+        // Zero any number underneath so column sums don't count it
+        if self.get_value(cell_ref).is_some() {
+            self.set_value(cell_ref, 0.0);
+        }
+        self.formulas.remove(cell_ref);
+        self.texts.insert(CellRef::new(cell_ref.row, cell_ref.col), text.to_string());
+        self.invalidate(cell_ref);
+    }
+
+    /// Text stored in a cell, if it's a text cell
+    pub fn get_text(&self, cell_ref: &CellRef) -> Option<&str> {
+        self.texts.get(cell_ref).map(|text| text.as_str())
+    }
+
+    /// Attach a metadata key/value to a cell (e.g. "color" → "#ff0000").
+    /// The engine only stores and serializes these for the frontend.
+    pub fn set_cell_meta(&mut self, reference: &str, key: &str, value: &str) -> Result<(), String> {
         let cell_ref = CellRef::parse(reference)?;
+        self.check_bounds(&cell_ref)?;
 
-        if !cell_ref.is_valid() {
-            return Err(format!("Cell reference out of Excel bounds: {}", reference));
+        self.meta
+            .entry(CellRef::new(cell_ref.row, cell_ref.col))
+            .or_default()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    /// Metadata value stored on a cell under `key`
+    pub fn get_cell_meta(&self, cell_ref: &CellRef, key: &str) -> Option<&str> {
+        self.meta.get(cell_ref)?.get(key).map(|value| value.as_str())
+    }
+
+    /// Name a column (1-based, A=1), e.g. for keys in `rows_as_records`
+    pub fn set_header(&mut self, col: u32, name: &str) -> Result<(), String> {
+        check_index(Axis::Column, col)?;
+        self.headers.insert(col, name.to_string());
+        Ok(())
+    }
+
+    /// Header name of a column (1-based), if one was set
+    pub fn header(&self, col: u32) -> Option<&str> {
+        self.headers.get(&col).map(|name| name.as_str())
+    }
+
+    /// Smallest range from A1 covering every number, text and formula cell
+    pub fn used_range(&self) -> Option<CellRange> {
+        let mut rows = 0;
+        let mut cols = 0;
+        for (col_idx, column) in &self.columns {
+            if !column.is_empty() {
+                rows = rows.max(column.len() as u32);
+                cols = cols.max(col_idx + 1);
+            }
         }
+        for cell_ref in self.formulas.keys().chain(self.texts.keys()) {
+            rows = rows.max(cell_ref.row);
+            cols = cols.max(cell_ref.col);
+        }
+
+        if rows == 0 {
+            None
+        } else {
+            Some(CellRange::new(CellRef::new(1, 1), CellRef::new(rows, cols)))
+        }
+    }
+
+    /// Set a formula in a cell
+    pub fn set_formula(&mut self, reference: &str, formula: &str) -> Result<(), String> {
+        let cell_ref = CellRef::parse(reference)?;
+        self.check_bounds(&cell_ref)?;
 
         let parsed_formula = Formula::parse(formula)?;
-        self.formulas.insert(cell_ref, parsed_formula);
+        let before = self.content(&cell_ref);
+        self.store_formula(&cell_ref, parsed_formula);
+        self.record(vec![(cell_ref, before)]);
 
         Ok(())
     }
 
+    /// Store a formula in a cell, replacing any text there
+    fn store_formula(&mut self, cell_ref: &CellRef, formula: Formula) {
+        // Store under the plain address; `$` anchors only matter inside formulas
+        self.formulas.insert(CellRef::new(cell_ref.row, cell_ref.col), formula);
+        self.texts.remove(cell_ref);
+        self.invalidate(cell_ref);
+    }
+
     /// Set formula from natural language
     pub fn set_formula_natural(
         &mut self,
@@ -74,50 +642,72 @@ impl QuantumGrid {
     }
 
     /// Get sum of a column by letter
+    #[deprecated(note = "only reaches columns A-Z; use column_sum_ref or column_sum_by_index")]
     pub fn column_sum(&self, col_letter: char) -> Option<f64> {
-        let col_upper = col_letter.to_ascii_uppercase();
-        if !col_upper.is_ascii_alphabetic() {
+        if !col_letter.is_ascii_alphabetic() {
             return None;
         }
+        self.column_sum_ref(&col_letter.to_string())
+    }
 
-        let col_idx = (col_upper as u32) - ('A' as u32);
+    /// Sum of a column by its 1-based index (A=1, AA=27)
+    pub fn column_sum_by_index(&self, col: u32) -> Option<f64> {
+        let col_idx = col.checked_sub(1)?;
         self.columns.get(&col_idx).map(|c| c.sum())
     }
 
-    /// Get sum of a range
+    /// Sum of a column by its letters, e.g. "B" or "AA"
+    pub fn column_sum_ref(&self, col_str: &str) -> Option<f64> {
+        let col_str = col_str.trim();
+        if col_str.is_empty() || !col_str.chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let col = CellRef::parse(&format!("{}1", col_str)).ok()?.col;
+        self.column_sum_by_index(col)
+    }
+
+    /// Sum the stored numbers inside a range like "A1:B10", or a whole column given
+    /// just its letters ("B"). Cells without a number count as 0.
     pub fn sum_range(&self, range: &str) -> Result<f64, String> {
-        if let Some(col_letter) = range.chars().next() {
-            if col_letter.is_ascii_alphabetic() {
-                self.column_sum(col_letter)
-                    .ok_or_else(|| format!("Column {} not found or empty", col_letter))
-            } else {
-                Err(format!("Invalid range format: '{}'", range))
-            }
-        } else {
-            Err("Empty range".to_string())
+        let range = range.trim();
+        if range.is_empty() {
+            return Err("Empty range".to_string());
         }
+        if range.chars().all(|c| c.is_ascii_alphabetic()) {
+            return self.column_sum_ref(range)
+                .ok_or_else(|| format!("Column {} not found or empty", range.to_uppercase()));
+        }
+
+        let range = CellRange::parse(range)?;
+        let rows = (range.start.row - 1) as usize..range.end.row as usize;
+        let total = (range.start.col - 1..range.end.col)
+            .filter_map(|col_idx| self.columns.get(&col_idx))
+            .map(|column| {
+                // A range over the whole column can use its cached sum instead of a rescan
+                if rows.start == 0 && column.len() <= rows.end {
+                    column.stats_snapshot().0
+                } else {
+                    column.entries().filter(|(row_idx, _)| rows.contains(row_idx)).map(|(_, value)| value).sum()
+                }
+            })
+            .sum();
+        Ok(total)
     }
 
     /// Print statistics
     pub fn print_stats(&self) {
+        let stats = self.stats();
         println!("📊 Quantum Grid Statistics:");
-        println!("   Columns: {}", self.columns.len());
-        println!("   Formulas: {}", self.formulas.len());
+        println!("   Columns: {}", stats.columns);
+        println!("   Formulas: {}", stats.formula_count);
+        println!("   Total cells: {}", stats.total_cells);
 
-        let total_cells: usize = self.columns.values().map(|c| c.count()).sum();
-        println!("   Total cells: {}", total_cells);
-
-        for (col_idx, column) in &self.columns {
-            let col_name = if *col_idx < 26 {
-                ((b'A' + *col_idx as u8) as char).to_string()
-            } else {
-                format!("Col{}", col_idx)
-            };
+        for column in &stats.per_column {
             println!(
-                "   Column {}: {} cells, sum={:.1}",
-                col_name,
-                column.count(),
-                column.sum()
+                "   Column {}: {} cells, sum={}",
+                column.name,
+                column.count,
+                self.number_format.format_with_decimals(column.sum, 1)
             );
         }
 
@@ -125,9 +715,93 @@ impl QuantumGrid {
         if !self.formulas.is_empty() {
             println!("\n   Formulas:");
             for (cell_ref, formula) in &self.formulas {
-                println!("     {}: {}", cell_ref, formula.to_excel());
+                println!("     {}: {}", cell_ref.to_style(self.reference_style), formula.to_style(self.reference_style));
+            }
+        }
+    }
+
+    /// Column, cell and formula counts, for callers that can't read `print_stats`
+    pub fn stats(&self) -> GridStats {
+        let mut per_column: Vec<(u32, ColumnSummary)> = self.columns.iter()
+            .map(|(col_idx, column)| (*col_idx, ColumnSummary {
+                name: column_letters(col_idx + 1),
+                count: column.count(),
+                sum: column.sum(),
+            }))
+            .collect();
+        per_column.sort_by_key(|(col_idx, _)| *col_idx);
+
+        GridStats {
+            columns: self.columns.len(),
+            total_cells: per_column.iter().map(|(_, column)| column.count).sum(),
+            formula_count: self.formulas.len(),
+            per_column: per_column.into_iter().map(|(_, column)| column).collect(),
+        }
+    }
+
+    /// Measure memory used by column storage plus the formula map
+    pub fn memory_report(&self) -> MemoryReport {
+        let values: usize = self.columns.values().map(|c| c.count()).sum();
+        let column_bytes: usize = self.columns.values().map(|c| c.memory_used()).sum();
+        let formula_bytes = self.formulas.capacity()
+            * (std::mem::size_of::<CellRef>() + std::mem::size_of::<Formula>());
+
+        let total_cells = values + self.formulas.len();
+        let total_bytes = column_bytes + formula_bytes;
+        let bytes_per_cell = if total_cells > 0 {
+            total_bytes as f64 / total_cells as f64
+        } else {
+            0.0
+        };
+
+        MemoryReport { total_cells, total_bytes, bytes_per_cell }
+    }
+
+    /// Compare measured bytes per cell with Excel's
+    pub fn efficiency_report(&self) -> EfficiencyReport {
+        let memory = self.memory_report();
+        EfficiencyReport {
+            our_bytes_per_cell: memory.bytes_per_cell,
+            excel_bytes_per_cell: EXCEL_BYTES_PER_CELL,
+            ratio: memory.vs_excel(),
+        }
+    }
+
+    /// Print memory report
+    pub fn print_memory_report(&self) {
+        let report = self.memory_report();
+
+        if report.total_cells > 0 {
+            println!("\n📊 MEMORY EFFICIENCY REPORT:");
+            println!("   Total cells: {}", report.total_cells);
+            println!("   Total memory: {} bytes", report.total_bytes);
+            println!("   Our memory per cell: {:.1} bytes", report.bytes_per_cell);
+            println!("   Excel memory per cell: {} bytes", EXCEL_BYTES_PER_CELL);
+            println!("   Improvement: {:.1}x more efficient", report.vs_excel());
+        }
+    }
+
+    /// Every number, text and formula cell, ordered by column then row. With `evaluate`
+    /// a formula cell yields its result; without, its source text (e.g. "=A1 + 1").
+    pub fn iter_cells(&self, evaluate: bool) -> impl Iterator<Item = (CellRef, CellValue)> + '_ {
+        let mut cells: BTreeMap<(u32, u32), CellValue> = BTreeMap::new();
+        for (col_idx, column) in &self.columns {
+            for (row_idx, value) in column.entries() {
+                cells.insert((col_idx + 1, row_idx as u32 + 1), CellValue::Number(value));
             }
         }
+        for (cell_ref, text) in &self.texts {
+            cells.insert((cell_ref.col, cell_ref.row), CellValue::Text(text.clone()));
+        }
+        for cell_ref in self.formulas.keys() {
+            let value = if evaluate {
+                self.get_cell_value(cell_ref)
+            } else {
+                CellValue::Text(self.formula_text(cell_ref).unwrap_or_default())
+            };
+            cells.insert((cell_ref.col, cell_ref.row), value);
+        }
+        cells.into_iter().map(|((col, row), value)| (CellRef::new(row, col), value))
     }
 
     pub fn columns(&self) -> &HashMap<u32, QuantumColumn> {
@@ -138,25 +812,1024 @@ impl QuantumGrid {
     pub fn formulas(&self) -> &HashMap<CellRef, Formula> {
         &self.formulas
     }
-    /// Get cell value
+
+    /// (dependent, precedent) edges across all formulas, sorted by dependent then precedent
+    pub fn dependency_edges(&self) -> Vec<(CellRef, CellRef)> {
+        let mut edges: Vec<(CellRef, CellRef)> = self.formulas.iter()
+            .flat_map(|(dependent, formula)| {
+                formula.dependencies().into_iter().map(move |precedent| (*dependent, precedent))
+            })
+            .collect();
+        edges.sort_by_key(|(dependent, precedent)| {
+            (dependent.row, dependent.col, precedent.row, precedent.col)
+        });
+        edges
+    }
+
+    /// Dependency graph in Graphviz DOT, arrows pointing from precedent to dependent
+    pub fn dependency_dot(&self) -> String {
+        let mut dot = String::from("digraph dependencies {\n");
+        for (dependent, precedent) in self.dependency_edges() {
+            dot.push_str(&format!("    \"{}\" -> \"{}\";\n", precedent.to_excel(), dependent.to_excel()));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+    /// Get cell value (formula cells are evaluated)
     pub fn get_cell(&self, reference: &str) -> Result<f64, String> {
-        // Parse cell reference like "A1"
-        let (col_str, row_str) = reference.split_at(1);
-        let col = col_str.chars().next().unwrap() as usize - 'A' as usize;
-        let row: usize = row_str.parse().map_err(|e| format!("Invalid row: {}", e))?;
-
-        // Convert usize to u32 for HashMap lookup
-        let col_u32 = col as u32;
-
-        // Get column
-        if let Some(column) = self.columns.get(&col_u32) {
-            if row < column.data.len() {
-                Ok(column.data[row])
-            } else {
-                Err(format!("Row {} out of bounds", row))
+        let cell_ref = CellRef::parse(reference)?;
+
+        if self.formulas.contains_key(&cell_ref) {
+            return match self.get_cell_value(&cell_ref) {
+                CellValue::Number(n) => Ok(n),
+                CellValue::Empty => Ok(0.0),
+                other => Err(format!("{} evaluates to {}", reference, other)),
+            };
+        }
+
+        if !self.columns.contains_key(&(cell_ref.col - 1)) {
+            return Err(format!("Column {} not found", reference));
+        }
+
+        // An empty cell inside the column reads as 0, like in a formula
+        let (row_idx, col_idx) = cell_ref.to_zero_based();
+        self.columns[&(col_idx as u32)].get(row_idx)
+            .ok_or_else(|| format!("Row {} out of bounds", cell_ref.row))
+    }
+
+    /// Get the stored value of a cell, if it has one (None for empty cells)
+    pub fn get_value(&self, cell_ref: &CellRef) -> Option<f64> {
+        let (row_idx, col_idx) = cell_ref.to_zero_based();
+        self.columns
+            .get(&(col_idx as u32))
+            .filter(|column| !column.is_null(row_idx))
+            .and_then(|column| column.get(row_idx))
+    }
+
+    /// Evaluated values of a range, row by row (never-set cells are Empty)
+    pub fn get_range(&self, range: &CellRange) -> Vec<Vec<CellValue>> {
+        (0..range.height())
+            .map(|row| (0..range.width()).map(|col| self.get_cell_value(&range.cell_at(row, col))).collect())
+            .collect()
+    }
+
+    /// Evaluated value of a cell (formulas are computed, never-set cells are Empty)
+    pub fn get_cell_value(&self, cell_ref: &CellRef) -> CellValue {
+        Evaluator::new(self).eval_cell(cell_ref)
+    }
+
+    /// Copy `source` into each cell below it through `through_row` (1-based),
+    /// adjusting relative references like dragging the fill handle
+    pub fn fill_down(&mut self, source: CellRef, through_row: u32) -> Result<(), String> {
+        if through_row < source.row {
+            return Err(format!("Cannot fill {} down to row {}", source, through_row));
+        }
+        // Fail before writing anything if the fill would run past the limits
+        self.check_bounds(&CellRef::new(through_row, source.col))?;
+
+        let targets: Vec<CellRef> = (source.row + 1..=through_row).map(|row| CellRef::new(row, source.col)).collect();
+        self.copy_cells(&source, targets)
+    }
+
+    /// Copy `source` into each cell to its right through `through_col` (1-based, A=1),
+    /// adjusting relative references
+    pub fn fill_right(&mut self, source: CellRef, through_col: u32) -> Result<(), String> {
+        if through_col < source.col {
+            return Err(format!("Cannot fill {} right to column {}", source, through_col));
+        }
+        self.check_bounds(&CellRef::new(source.row, through_col))?;
+
+        let targets: Vec<CellRef> = (source.col + 1..=through_col).map(|col| CellRef::new(source.row, col)).collect();
+        self.copy_cells(&source, targets)
+    }
+
+    /// Copy `source` into each target as a single undoable edit
+    fn copy_cells(&mut self, source: &CellRef, targets: Vec<CellRef>) -> Result<(), String> {
+        let before: Vec<_> = targets.iter().map(|target| (*target, self.content(target))).collect();
+        let result = targets.iter().try_for_each(|target| self.copy_cell(source, target));
+        self.record(before);
+        result
+    }
+
+    /// Copy one cell's formula (shifted by the move) or value to another cell
+    fn copy_cell(&mut self, source: &CellRef, target: &CellRef) -> Result<(), String> {
+        self.check_bounds(target)?;
+
+        if let Some(formula) = self.formulas.get(source) {
+            let row_delta = target.row as i64 - source.row as i64;
+            let col_delta = target.col as i64 - source.col as i64;
+            let moved = formula.offset(row_delta, col_delta);
+            self.store_formula(target, moved);
+        } else if let Some(text) = self.texts.get(source).cloned() {
+            self.store_text(target, &text);
+        } else if let Some(value) = self.get_value(source) {
+            self.set_value(target, value);
+        }
+        Ok(())
+    }
+
+    /// Write a block of values with its top-left corner at `dest`, as one undoable edit.
+    /// Fails with #SPILL! (writing nothing) if any target cell isn't empty.
+    /// Errors are stored as their text, e.g. "#N/A".
+    pub fn spill(&mut self, dest: &CellRef, rows: &[Vec<CellValue>]) -> Result<CellRange, String> {
+        let height = rows.len();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        if height == 0 || width == 0 {
+            return Err("Nothing to spill".to_string());
+        }
+
+        // The far corner, unless the block runs past the largest row/column a u32 can hold
+        let last = |start: u32, len: usize| u32::try_from(len - 1).ok().and_then(|extra| start.checked_add(extra));
+        let end = match (last(dest.row, height), last(dest.col, width)) {
+            (Some(row), Some(col)) => CellRef::new(row, col),
+            _ => return Err(format!("A {}x{} block at {} runs past the end of the sheet", height, width, dest)),
+        };
+        self.check_bounds(&end)?;
+        let block = CellRange::new(CellRef::new(dest.row, dest.col), end);
+
+        let mut before = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            for j in 0..row.len() {
+                let target = block.cell_at(i as u32, j as u32);
+                let content = self.content(&target);
+                if content != CellContent::Empty {
+                    return Err(format!(
+                        "{}: {} would overwrite {}",
+                        CellError::Spill, block.to_excel(), target
+                    ));
+                }
+                before.push((target, content));
             }
-        } else {
-            Err(format!("Column {} not found", col_str))
         }
+
+        for (i, row) in rows.iter().enumerate() {
+            for (j, value) in row.iter().enumerate() {
+                let target = block.cell_at(i as u32, j as u32);
+                match value {
+                    CellValue::Number(n) => self.set_value(&target, *n),
+                    CellValue::Text(text) => self.store_text(&target, text),
+                    CellValue::Error(e) => self.store_text(&target, &e.to_string()),
+                    CellValue::Empty => {}
+                }
+            }
+        }
+        self.record(before);
+        Ok(block)
+    }
+
+    /// Sort a single-column range in place, as one undoable edit.
+    /// Numbers come before text (compared case-insensitively), then errors;
+    /// blanks always go last. Formula cells sort by, and are replaced with,
+    /// their evaluated value.
+    pub fn sort_range(&mut self, range: &CellRange, ascending: bool) -> Result<(), String> {
+        if range.width() != 1 {
+            return Err(format!("Can only sort a single column, not {}", range.to_excel()));
+        }
+        self.check_bounds(&range.end)?;
+
+        let cells: Vec<CellRef> = range.cells().collect();
+        let mut values: Vec<CellValue> = cells.iter().map(|cell| self.get_cell_value(cell)).collect();
+        values.sort_by(|a, b| {
+            let rank = |value: &CellValue| match value {
+                CellValue::Number(_) => 0,
+                CellValue::Text(_) => 1,
+                CellValue::Error(_) => 2,
+                CellValue::Empty => 3,
+            };
+            let order = rank(a).cmp(&rank(b)).then_with(|| match (a, b) {
+                (CellValue::Number(x), CellValue::Number(y)) => x.total_cmp(y),
+                (CellValue::Text(x), CellValue::Text(y)) => x.to_lowercase().cmp(&y.to_lowercase()),
+                _ => std::cmp::Ordering::Equal,
+            });
+            if ascending || rank(a) == 3 || rank(b) == 3 { order } else { order.reverse() }
+        });
+
+        let before: Vec<_> = cells.iter().map(|cell| (*cell, self.content(cell))).collect();
+        // Bottom-up, so trailing blanks can shrink the column
+        for (cell, value) in cells.iter().zip(values).rev() {
+            let content = match value {
+                CellValue::Number(n) => CellContent::Number(n),
+                CellValue::Text(text) => CellContent::Text(text),
+                CellValue::Error(e) => CellContent::Text(e.to_string()),
+                CellValue::Empty => CellContent::Empty,
+            };
+            self.restore(cell, content);
+        }
+        self.record(before);
+        Ok(())
+    }
+
+    /// Insert an empty row above `at` (1-based), shifting cells and formula references down
+    pub fn insert_row(&mut self, at: u32) -> Result<(), String> {
+        check_index(Axis::Row, at)?;
+        self.check_room_to_insert(Axis::Row, at)?;
+
+        for column in self.columns.values_mut() {
+            // Columns that end above the insertion point have nothing to shift
+            if column.len() >= at as usize {
+                column.insert_gap((at - 1) as usize);
+            }
+        }
+
+        self.shift_formulas(Axis::Row, at, true);
+        Ok(())
+    }
+
+    /// Delete row `at` (1-based), shifting cells up; references to it become #REF!
+    pub fn delete_row(&mut self, at: u32) -> Result<(), String> {
+        check_index(Axis::Row, at)?;
+
+        for column in self.columns.values_mut() {
+            column.remove((at - 1) as usize);
+        }
+
+        self.shift_formulas(Axis::Row, at, false);
+        Ok(())
+    }
+
+    /// Insert an empty column before `at` (1-based, A=1), shifting cells and references right
+    pub fn insert_column(&mut self, at: u32) -> Result<(), String> {
+        check_index(Axis::Column, at)?;
+        self.check_room_to_insert(Axis::Column, at)?;
+
+        let at_idx = at - 1;
+        self.columns = self.columns
+            .drain()
+            .map(|(col, column)| (if col >= at_idx { col + 1 } else { col }, column))
+            .collect();
+
+        self.shift_formulas(Axis::Column, at, true);
+        Ok(())
+    }
+
+    /// Delete column `at` (1-based, A=1), shifting cells left; references to it become #REF!
+    pub fn delete_column(&mut self, at: u32) -> Result<(), String> {
+        check_index(Axis::Column, at)?;
+
+        let at_idx = at - 1;
+        self.columns.remove(&at_idx);
+        self.columns = self.columns
+            .drain()
+            .map(|(col, column)| (if col > at_idx { col - 1 } else { col }, column))
+            .collect();
+
+        self.shift_formulas(Axis::Column, at, false);
+        Ok(())
+    }
+
+    /// Move formula and text cells, and rewrite the references inside formulas,
+    /// after a structural edit
+    fn shift_formulas(&mut self, axis: Axis, at: u32, insert: bool) {
+        // Every address may have moved, so cached results and journaled edits no longer line up
+        self.clear_cache();
+        self.history.clear();
+
+        let mut shifted = HashMap::new();
+        for (cell_ref, mut formula) in self.formulas.drain() {
+            if let Some(new_ref) = shift_cell(&cell_ref, axis, at, insert) {
+                if insert {
+                    formula.shift_for_insert(axis, at);
+                } else {
+                    formula.shift_for_delete(axis, at);
+                }
+                shifted.insert(new_ref, formula);
+            }
+        }
+        self.formulas = shifted;
+
+        self.texts = self.texts
+            .drain()
+            .filter_map(|(cell_ref, text)| shift_cell(&cell_ref, axis, at, insert).map(|new_ref| (new_ref, text)))
+            .collect();
+
+        self.meta = self.meta
+            .drain()
+            .filter_map(|(cell_ref, meta)| shift_cell(&cell_ref, axis, at, insert).map(|new_ref| (new_ref, meta)))
+            .collect();
+
+        if axis == Axis::Column {
+            self.headers = self.headers
+                .drain()
+                .filter_map(|(col, name)| shift_cell(&CellRef::new(1, col), axis, at, insert).map(|new_ref| (new_ref.col, name)))
+                .collect();
+        }
+    }
+
+    /// Join two ranges on a key column (1-based column index within each range).
+    ///
+    /// Each output row is the left row followed by the right row without its key
+    /// column. Blank cells come back as `None` (unmatched rows in a left join).
+    pub fn join_ranges(
+        &self,
+        left: &CellRange,
+        right: &CellRange,
+        left_key_col: u32,
+        right_key_col: u32,
+        kind: JoinKind,
+    ) -> Result<Vec<Vec<Option<f64>>>, String> {
+        if left_key_col == 0 || left_key_col > left.width() {
+            return Err(format!("Left key column {} is outside {}", left_key_col, left.to_excel()));
+        }
+        if right_key_col == 0 || right_key_col > right.width() {
+            return Err(format!("Right key column {} is outside {}", right_key_col, right.to_excel()));
+        }
+
+        let read_row = |range: &CellRange, row: u32| -> Vec<Option<f64>> {
+            (0..range.width())
+                .map(|col| self.get_value(&range.cell_at(row, col)))
+                .collect()
+        };
+
+        // Index the right side by key so each left row is matched in O(1)
+        let mut right_index: HashMap<u64, Vec<Vec<Option<f64>>>> = HashMap::new();
+        for row in 0..right.height() {
+            let mut cells = read_row(right, row);
+            if let Some(key) = cells.remove((right_key_col - 1) as usize) {
+                right_index.entry(join_key(key)).or_default().push(cells);
+            }
+        }
+
+        let blank_right = vec![None; (right.width() - 1) as usize];
+        let mut joined = Vec::new();
+
+        for row in 0..left.height() {
+            let left_cells = read_row(left, row);
+            let matches = left_cells[(left_key_col - 1) as usize]
+                .and_then(|key| right_index.get(&join_key(key)));
+
+            match (matches, kind) {
+                (Some(rows), _) => {
+                    for right_cells in rows {
+                        let mut out = left_cells.clone();
+                        out.extend(right_cells.iter().copied());
+                        joined.push(out);
+                    }
+                }
+                (None, JoinKind::Left) => {
+                    let mut out = left_cells;
+                    out.extend(blank_right.iter().copied());
+                    joined.push(out);
+                }
+                (None, JoinKind::Inner) => {}
+            }
+        }
+
+        Ok(joined)
+    }
+}
+
+/// How unmatched rows are handled by `QuantumGrid::join_ranges`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    /// Only rows with a key on both sides
+    Inner,
+    /// Every left row, with blanks where the right side has no match
+    Left,
+}
+
+impl JoinKind {
+    /// Parse "inner" / "left" (case-insensitive)
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.trim().trim_matches('"').to_lowercase().as_str() {
+            "inner" => Ok(JoinKind::Inner),
+            "left" => Ok(JoinKind::Left),
+            other => Err(format!("Unknown join type '{}' (expected inner or left)", other)),
+        }
+    }
+}
+
+/// Where a cell moves when a row/column is inserted or deleted at `at`.
+/// None if the cell was deleted or pushed off the edge of the sheet.
+fn shift_cell(cell_ref: &CellRef, axis: Axis, at: u32, insert: bool) -> Option<CellRef> {
+    let index = match axis {
+        Axis::Row => cell_ref.row,
+        Axis::Column => cell_ref.col,
+    };
+
+    let new_index = if index < at {
+        index
+    } else if insert {
+        index + 1
+    } else if index == at {
+        return None;
+    } else {
+        index - 1
+    };
+
+    let new_ref = match axis {
+        Axis::Row => CellRef::new(new_index, cell_ref.col),
+        Axis::Column => CellRef::new(cell_ref.row, new_index),
+    };
+    if new_ref.is_valid() { Some(new_ref) } else { None }
+}
+
+/// Validate a 1-based row/column index for structural edits
+fn check_index(axis: Axis, at: u32) -> Result<(), String> {
+    let (name, limit) = match axis {
+        Axis::Row => ("Row", MAX_ROWS),
+        Axis::Column => ("Column", MAX_COLS),
+    };
+    if at == 0 || at > limit {
+        return Err(format!("{} {} is outside the sheet", name, at));
+    }
+    Ok(())
+}
+
+/// Hashable form of a numeric key (-0.0 and 0.0 must match)
+fn join_key(value: f64) -> u64 {
+    if value == 0.0 { 0.0f64.to_bits() } else { value.to_bits() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inner_join_on_shared_key() {
+        let mut grid = QuantumGrid::new();
+
+        // Left table A1:B3 = (id, qty), right table D1:E3 = (id, price)
+        for (cell, value) in [("A1", 1.0), ("B1", 10.0), ("A2", 2.0), ("B2", 20.0), ("A3", 3.0), ("B3", 30.0)] {
+            grid.set_cell(cell, value).unwrap();
+        }
+        for (cell, value) in [("D1", 3.0), ("E1", 300.0), ("D2", 1.0), ("E2", 100.0), ("D3", 4.0), ("E3", 400.0)] {
+            grid.set_cell(cell, value).unwrap();
+        }
+
+        let left = CellRange::parse("A1:B3").unwrap();
+        let right = CellRange::parse("D1:E3").unwrap();
+
+        let inner = grid.join_ranges(&left, &right, 1, 1, JoinKind::Inner).unwrap();
+        assert_eq!(inner, vec![
+            vec![Some(1.0), Some(10.0), Some(100.0)],
+            vec![Some(3.0), Some(30.0), Some(300.0)],
+        ]);
+
+        let left_join = grid.join_ranges(&left, &right, 1, 1, JoinKind::Left).unwrap();
+        assert_eq!(left_join.len(), 3);
+        assert_eq!(left_join[1], vec![Some(2.0), Some(20.0), None]);
+
+        assert!(grid.join_ranges(&left, &right, 3, 1, JoinKind::Inner).is_err());
+    }
+
+    #[test]
+    fn test_fill_down_adjusts_relative_references() {
+        let mut grid = QuantumGrid::new();
+        for row in 1..=3 {
+            grid.set_cell(&format!("A{}", row), row as f64).unwrap();
+            grid.set_cell(&format!("B{}", row), row as f64 * 10.0).unwrap();
+        }
+        grid.set_formula("C1", "=A1+B1").unwrap();
+
+        grid.fill_down(CellRef::parse("C1").unwrap(), 3).unwrap();
+
+        let formula_at = |grid: &QuantumGrid, cell: &str| grid.formulas()[&CellRef::parse(cell).unwrap()].to_excel();
+        assert_eq!(formula_at(&grid, "C2"), "=A2 + B2");
+        assert_eq!(formula_at(&grid, "C3"), "=A3 + B3");
+        assert_eq!(grid.get_cell("C2").unwrap(), 22.0);
+        assert_eq!(grid.get_cell("C3").unwrap(), 33.0);
+
+        // Anchored parts stay fixed
+        grid.set_formula("D1", "=$A$1+B1").unwrap();
+        grid.fill_down(CellRef::parse("D1").unwrap(), 3).unwrap();
+        assert_eq!(formula_at(&grid, "D3"), "=$A$1 + B3");
+        assert_eq!(grid.get_cell("D3").unwrap(), 31.0);
+    }
+
+    #[test]
+    fn test_fill_right_adjusts_relative_references() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 1.0).unwrap();
+        grid.set_cell("B1", 2.0).unwrap();
+        grid.set_cell("C1", 3.0).unwrap();
+        grid.set_formula("A2", "=A1+A$1").unwrap();
+
+        grid.fill_right(CellRef::parse("A2").unwrap(), 3).unwrap();
+
+        let c2 = CellRef::parse("C2").unwrap();
+        assert_eq!(grid.formulas()[&c2].to_excel(), "=C1 + C$1");
+        assert_eq!(grid.get_cell_value(&c2), CellValue::Number(6.0));
+        assert!(grid.fill_right(c2, 1).is_err());
+    }
+
+    #[test]
+    fn test_writes_beyond_soft_limit_error() {
+        let mut grid = QuantumGrid::new();
+        assert_eq!(grid.limits(), GridLimits::default());
+        assert!(grid.set_cell("XFD1", 1.0).is_ok());
+
+        grid.set_limits(GridLimits { max_rows: 1000, max_cols: 26 });
+
+        let err = grid.set_cell("A1001", 1.0).unwrap_err();
+        assert!(err.contains("beyond the grid limit of 1000 rows x 26 columns"), "{}", err);
+        assert!(grid.set_cell("AA1", 1.0).is_err());
+        assert!(grid.set_text("A5000", "x").is_err());
+        assert!(grid.set_cell("Z1000", 1.0).is_ok());
+
+        // Fills are rejected up front
+        grid.set_cell("B1", 2.0).unwrap();
+        assert!(grid.fill_down(CellRef::parse("B1").unwrap(), 2000).is_err());
+        assert_eq!(grid.get_cell("B2").ok(), None);
+
+        // A spill whose far corner would overflow the row number errors instead of panicking
+        let err = grid.spill(&CellRef::new(u32::MAX, 1), &vec![vec![CellValue::Number(1.0)]; 2]).unwrap_err();
+        assert!(err.contains("runs past the end of the sheet"), "{}", err);
+    }
+
+    #[test]
+    fn test_over_limit_writes_allocate_nothing() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 1.0).unwrap();
+        let before = grid.memory_report();
+
+        // Past Excel's sheet, then past a tighter configured limit
+        assert!(grid.set_cell("A100000000", 1.0).is_err());
+        grid.set_limits(GridLimits { max_rows: 100, max_cols: 5 });
+        assert!(grid.set_cell("A101", 1.0).is_err());
+        let err = grid.fill_range("B1:B500", FillSpec::Constant(1.0)).unwrap_err();
+        assert!(err.contains("beyond the grid limit of 100 rows x 5 columns"), "{}", err);
+        let (written, errors) = grid.set_cells(&[("C1", 1.0), ("C5000", 2.0)]);
+        assert_eq!((written, errors.len()), (1, 1));
+        grid.set_cell("C1", 0.0).unwrap();
+        grid.undo();
+        grid.undo();
+        assert_eq!(grid.memory_report(), before);
+
+        // Inserts can't push occupied cells past the limit either
+        grid.set_cell("A100", 2.0).unwrap();
+        grid.set_cell("E1", 3.0).unwrap();
+        assert!(grid.insert_row(1).is_err());
+        assert!(grid.insert_column(2).is_err());
+        assert!(grid.insert_row(101).is_ok()); // Nothing at or below row 101 to move
+        assert_eq!(grid.get_cell("A100").unwrap(), 2.0);
+    }
+
+    #[test]
+    fn test_set_formula_accepts_every_operator() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 6.0).unwrap();
+        grid.set_cell("B1", 3.0).unwrap();
+        for (cell, formula, text, value) in [
+            ("C1", "=A1+B1", "=A1 + B1", 9.0),
+            ("C2", "=A1-B1", "=A1 - B1", 3.0),
+            ("C3", "=A1*B1", "=A1 * B1", 18.0),
+            ("C4", "=A1/B1", "=A1 / B1", 2.0),
+            ("C5", "=SUM(A1:B1)", "=SUM(A1:B1)", 9.0),
+            ("C6", "=(A1-B1)*2", "=(A1 - B1) * 2", 6.0),
+        ] {
+            grid.set_formula(cell, formula).unwrap();
+            let cell = CellRef::parse(cell).unwrap();
+            assert_eq!(grid.formulas()[&cell].to_excel(), text);
+            assert_eq!(grid.get_cell_value(&cell), CellValue::Number(value), "{}", formula);
+        }
+
+        assert_eq!(grid.set_formula("D1", "=A1+"), Err("Could not parse formula: =A1+".to_string()));
+        assert_eq!(grid.set_formula("D1", "A1+B1"), Err("Formula must start with '='".to_string()));
+    }
+
+    #[test]
+    fn test_set_formula_round_trips_full_grammar() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 6.0).unwrap();
+        for (formula, value) in [
+            ("=IF(A1>0,1,0)", CellValue::Number(1.0)),
+            ("=A1<>6", CellValue::Number(0.0)),
+            ("=A1^2", CellValue::Number(36.0)),
+            ("=A1&\"x\"", CellValue::Text("6x".to_string())),
+            ("=-A1", CellValue::Number(-6.0)),
+            ("=\"say \"\"hi\"\"\"", CellValue::Text("say \"hi\"".to_string())),
+        ] {
+            grid.set_formula("B1", formula).unwrap();
+            let b1 = CellRef::parse("B1").unwrap();
+            assert_eq!(grid.get_cell_value(&b1), value, "{}", formula);
+
+            // What the grid shows parses back to the same formula
+            let shown = grid.formula_text(&b1).unwrap();
+            grid.set_formula("C1", &shown).unwrap();
+            assert_eq!(grid.formulas()[&CellRef::parse("C1").unwrap()], grid.formulas()[&b1], "{}", shown);
+        }
+    }
+
+    #[test]
+    fn test_transaction_commits_as_one_edit() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 1.0).unwrap();
+        grid.set_formula("B1", "=A1*2").unwrap();
+
+        let doubled = grid.transaction(|tx| {
+            tx.set_cell("A1", 5.0)?;
+            tx.set_cell("A2", 6.0)?;
+            tx.set_cell("A1", 7.0)?;
+            tx.get_cell("B1")
+        });
+        assert_eq!(doubled, Ok(14.0));
+        assert_eq!(grid.get_cell("A2").unwrap(), 6.0);
+
+        // One undo step takes back every write in the transaction
+        assert!(grid.undo());
+        assert_eq!(grid.get_cell("A1").unwrap(), 1.0);
+        assert!(grid.get_value(&CellRef::parse("A2").unwrap()).is_none());
+        assert_eq!(grid.get_cell("B1").unwrap(), 2.0);
+
+        let failed: Result<(), String> = grid.transaction(|tx| {
+            tx.set_text("A1", "oops")?;
+            tx.set_cell("Z99", 1.0)?;
+            Err("changed my mind".to_string())
+        });
+        assert!(failed.is_err());
+        assert_eq!(grid.get_cell("A1").unwrap(), 1.0);
+        assert!(grid.get_value(&CellRef::parse("Z99").unwrap()).is_none());
+        assert_eq!(grid.get_cell("B1").unwrap(), 2.0);
+    }
+
+    #[test]
+    fn test_stats_count_values_and_formulas() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cells(&[("A1", 10.0), ("A2", 20.0), ("B1", 1.0), ("B2", 2.0), ("B3", 3.0)]);
+        grid.set_cell("AA1", 7.0).unwrap();
+        grid.set_formula("C1", "=A1+B1").unwrap();
+
+        let stats = grid.stats();
+        assert_eq!(stats.columns, 3);
+        assert_eq!(stats.total_cells, 6);
+        assert_eq!(stats.formula_count, 1);
+        let summary = |name: &str, count, sum| ColumnSummary { name: name.to_string(), count, sum };
+        assert_eq!(stats.per_column, vec![summary("A", 2, 30.0), summary("B", 3, 6.0), summary("AA", 1, 7.0)]);
+    }
+
+    #[test]
+    fn test_formula_results_are_cached_until_upstream_edit() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 1.0).unwrap();
+        grid.set_cell("B1", 2.0).unwrap();
+        grid.set_cell("D1", 100.0).unwrap();
+        grid.set_formula("C1", "=A1+B1").unwrap();
+        grid.set_formula("E1", "=C1+D1").unwrap();
+
+        assert_eq!(grid.get_cell("E1").unwrap(), 103.0);
+        assert_eq!(grid.formula_evaluations(), 2); // E1 and C1
+
+        // Second read is served from the cache
+        assert_eq!(grid.get_cell("E1").unwrap(), 103.0);
+        assert_eq!(grid.get_cell("C1").unwrap(), 3.0);
+        assert_eq!(grid.formula_evaluations(), 2);
+
+        // An edit upstream of C1 invalidates C1 and, transitively, E1
+        grid.set_cell("A1", 10.0).unwrap();
+        assert_eq!(grid.get_cell("E1").unwrap(), 112.0);
+        assert_eq!(grid.formula_evaluations(), 4);
+
+        // An edit E1 reads directly leaves C1 cached
+        grid.set_cell("D1", 0.0).unwrap();
+        assert_eq!(grid.get_cell("E1").unwrap(), 12.0);
+        assert_eq!(grid.formula_evaluations(), 5);
+    }
+
+    #[test]
+    fn test_range_formulas_are_invalidated_by_any_cell_in_range() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cells(&[("A1", 1.0), ("A2", 2.0), ("A3", 3.0), ("A4", 40.0)]);
+        grid.set_formula("B1", "=SUM(A1:A3)").unwrap();
+
+        assert_eq!(grid.get_cell("B1").unwrap(), 6.0);
+        assert_eq!(grid.get_cell("B1").unwrap(), 6.0);
+        assert_eq!(grid.formula_evaluations(), 1);
+
+        // A4 is outside the range, so the cached sum stands
+        grid.set_cell("A4", 0.0).unwrap();
+        assert_eq!(grid.get_cell("B1").unwrap(), 6.0);
+        assert_eq!(grid.formula_evaluations(), 1);
+
+        grid.set_cell("A2", 20.0).unwrap();
+        assert_eq!(grid.get_cell("B1").unwrap(), 24.0);
+        assert_eq!(grid.formula_evaluations(), 2);
+    }
+
+    #[test]
+    fn test_insert_row_shifts_formula_references() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A5", 50.0).unwrap();
+        grid.set_formula("C1", "=A5+B1").unwrap();
+
+        grid.insert_row(3).unwrap();
+
+        let formula = &grid.formulas()[&CellRef::parse("C1").unwrap()];
+        assert_eq!(formula.to_excel(), "=A6 + B1");
+        assert_eq!(grid.get_cell("A6").unwrap(), 50.0);
+
+        // Formula cells below the insertion point move too
+        grid.set_formula("D8", "=A1+A6").unwrap();
+        grid.insert_row(1).unwrap();
+        let moved = &grid.formulas()[&CellRef::parse("D9").unwrap()];
+        assert_eq!(moved.to_excel(), "=A2 + A7");
+    }
+
+    #[test]
+    fn test_delete_row_and_column_produce_ref_errors() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A5", 50.0).unwrap();
+        grid.set_cell("A6", 60.0).unwrap();
+        grid.set_formula("C1", "=A5+A6").unwrap();
+
+        grid.delete_row(5).unwrap();
+        let formula = &grid.formulas()[&CellRef::parse("C1").unwrap()];
+        assert_eq!(formula.to_excel(), "=#REF! + A5");
+        assert_eq!(grid.get_cell("A5").unwrap(), 60.0);
+
+        grid.set_formula("D1", "=A1+B1").unwrap();
+        grid.delete_column(1).unwrap();
+        // C1 moved to B1, D1 moved to C1 and lost its A1 reference
+        let formula = &grid.formulas()[&CellRef::parse("C1").unwrap()];
+        assert_eq!(formula.to_excel(), "=#REF! + A1");
+        assert!(grid.formulas().contains_key(&CellRef::parse("B1").unwrap()));
+    }
+
+    #[test]
+    fn test_memory_report_on_canonical_grid() {
+        let mut grid = QuantumGrid::new();
+        let empty = grid.memory_report();
+        assert_eq!(empty.total_cells, 0);
+        assert_eq!(empty.bytes_per_cell, 0.0);
+
+        for row in 1..=100 {
+            grid.set_cell(&format!("A{}", row), row as f64).unwrap();
+        }
+        let report = grid.memory_report();
+        assert_eq!(report.total_cells, 100);
+        assert_eq!(report.total_bytes, grid.columns()[&0].memory_used());
+        assert_eq!(report.bytes_per_cell, report.total_bytes as f64 / 100.0);
+        assert!(report.vs_excel() > 1.0);
+    }
+
+    #[test]
+    fn test_sort_range_orders_numbers_then_text() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 42.0).unwrap();
+        grid.set_text("A2", "pear").unwrap();
+        grid.set_cell("A3", -1.0).unwrap();
+        grid.set_text("A4", "Apple").unwrap();
+        grid.set_cell("A5", 7.0).unwrap();
+        grid.set_formula("A6", "=A1+A5").unwrap(); // Sorts as 49
+        let range = CellRange::parse("A1:A7").unwrap(); // A7 blank
+
+        let column = |grid: &QuantumGrid| -> Vec<String> {
+            range.cells().map(|cell| grid.get_cell_value(&cell).to_string()).collect()
+        };
+
+        grid.sort_range(&range, true).unwrap();
+        assert_eq!(column(&grid), ["-1", "7", "42", "49", "Apple", "pear", ""]);
+        assert!(grid.formulas().is_empty());
+
+        grid.sort_range(&range, false).unwrap();
+        assert_eq!(column(&grid), ["pear", "Apple", "49", "42", "7", "-1", ""]);
+
+        assert!(grid.undo());
+        assert_eq!(column(&grid)[0], "-1");
+    }
+
+    #[test]
+    fn test_formula_text_follows_reference_style() {
+        let mut grid = QuantumGrid::new();
+        grid.set_formula("C5", "=A1+B2").unwrap();
+        let c5 = CellRef::parse("C5").unwrap();
+
+        assert_eq!(grid.formula_text(&c5).as_deref(), Some("=A1 + B2"));
+        grid.set_reference_style(ReferenceStyle::R1C1);
+        assert_eq!(grid.formula_text(&c5).as_deref(), Some("=R1C1 + R2C2"));
+        assert_eq!(grid.formula_text(&CellRef::parse("A1").unwrap()), None);
+    }
+
+    #[test]
+    fn test_iter_cells_visits_every_populated_cell_in_order() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("B2", 5.0).unwrap();
+        grid.set_cell("A1", 1.0).unwrap();
+        grid.set_text("A2", "note").unwrap();
+        grid.set_formula("C1", "=A1+B2").unwrap();
+
+        let cell = |reference: &str| CellRef::parse(reference).unwrap();
+        let evaluated: Vec<_> = grid.iter_cells(true).collect();
+        assert_eq!(evaluated, vec![
+            (cell("A1"), CellValue::Number(1.0)),
+            (cell("A2"), CellValue::Text("note".to_string())),
+            (cell("B2"), CellValue::Number(5.0)),
+            (cell("C1"), CellValue::Number(6.0)),
+        ]);
+
+        let sources: Vec<_> = grid.iter_cells(false).collect();
+        assert_eq!(sources[3], (cell("C1"), CellValue::Text("=A1 + B2".to_string())));
+    }
+
+    #[test]
+    fn test_fill_range_with_constant_and_series() {
+        let mut grid = QuantumGrid::new();
+        grid.fill_range("A1:A100", FillSpec::Constant(7.0)).unwrap();
+        assert_eq!((grid.get_cell("A1"), grid.get_cell("A100")), (Ok(7.0), Ok(7.0)));
+
+        grid.fill_range("B1:B100", FillSpec::Series { start: 1.0, step: 1.0 }).unwrap();
+        assert_eq!((grid.get_cell("B1"), grid.get_cell("B100")), (Ok(1.0), Ok(100.0)));
+
+        // A single row counts across it
+        grid.fill_range("C1:F1", FillSpec::Series { start: 10.0, step: -2.5 }).unwrap();
+        assert_eq!((grid.get_cell("C1"), grid.get_cell("F1")), (Ok(10.0), Ok(2.5)));
+
+        assert!(grid.undo());
+        assert!(grid.get_value(&CellRef::parse("F1").unwrap()).is_none());
+        assert!(grid.fill_range("A1:A2000000", FillSpec::Constant(0.0)).is_err());
+    }
+
+    #[test]
+    fn test_get_range_returns_rows_of_values() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 1.0).unwrap();
+        grid.set_text("B1", "north").unwrap();
+        grid.set_formula("A2", "=A1+A1").unwrap();
+
+        let range = CellRange::parse("A1:B2").unwrap();
+        assert_eq!(grid.get_range(&range), vec![
+            vec![CellValue::Number(1.0), CellValue::Text("north".to_string())],
+            vec![CellValue::Number(2.0), CellValue::Empty],
+        ]);
+    }
+
+    #[test]
+    fn test_sum_range_respects_row_and_column_bounds() {
+        let mut grid = QuantumGrid::new();
+        for row in 1..=10 {
+            grid.set_cell(&format!("A{}", row), row as f64).unwrap();
+            grid.set_cell(&format!("B{}", row), 100.0).unwrap();
+        }
+
+        assert_eq!(grid.sum_range("A1:A3").unwrap(), 6.0);
+        assert_eq!(grid.sum_range("A1:A10").unwrap(), 55.0);
+        assert_eq!(grid.sum_range("B2:B5").unwrap(), 400.0);
+        assert_eq!(grid.sum_range("A9:B10").unwrap(), 219.0);
+        assert_eq!(grid.sum_range("A11:A20").unwrap(), 0.0);
+        assert_eq!(grid.sum_range("b").unwrap(), 1000.0);
+        assert!(grid.sum_range("C").is_err());
+        assert!(grid.sum_range("A1:").is_err());
+    }
+
+    #[test]
+    fn test_column_sum_reaches_columns_beyond_z() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("AA1", 4.0).unwrap();
+        grid.set_cell("AA2", 6.0).unwrap();
+        grid.set_cell("A1", 100.0).unwrap();
+
+        assert_eq!(grid.column_sum_ref("AA"), Some(10.0));
+        assert_eq!(grid.column_sum_ref("aa"), Some(10.0));
+        assert_eq!(grid.column_sum_by_index(27), Some(10.0));
+        assert_eq!(grid.column_sum_by_index(1), Some(100.0));
+        assert_eq!(grid.column_sum_by_index(0), None);
+        assert_eq!(grid.column_sum_ref("AB"), None);
+        assert_eq!(grid.column_sum_ref("A1"), None);
+        assert_eq!(grid.sum_range("AA").unwrap(), 10.0);
+    }
+
+    #[test]
+    fn test_recalculate_all_evaluates_every_formula_once() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 2.0).unwrap();
+        grid.set_formula("B1", "=A1+A1").unwrap();
+        grid.set_formula("C1", "=B1+A1").unwrap();
+        grid.get_cell_value(&CellRef::parse("C1").unwrap());
+
+        let before = grid.formula_evaluations();
+        assert_eq!(grid.recalculate_all(), 2);
+        assert_eq!(grid.formula_evaluations() - before, 2);
+        assert_eq!(grid.get_cell_value(&CellRef::parse("C1").unwrap()), CellValue::Number(6.0));
+    }
+
+    #[test]
+    fn test_far_apart_cells_use_sparse_storage() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 5.0).unwrap();
+        grid.set_cell("A1000000", 7.0).unwrap();
+
+        assert_eq!(grid.columns()[&0].storage_mode(), crate::storage::StorageMode::Sparse);
+        assert_eq!(grid.memory_report().total_cells, 2);
+        assert!(grid.memory_report().total_bytes < 1024);
+        assert_eq!(grid.get_cell("A1000000").unwrap(), 7.0);
+        assert_eq!(grid.get_cell("A500").unwrap(), 0.0);
+        assert_eq!(grid.sum_range("A1:A1000000").unwrap(), 12.0);
+
+        assert!(grid.undo());
+        assert_eq!(grid.used_range().map(|used| used.to_excel()), Some("A1:A1".to_string()));
+    }
+
+    #[test]
+    fn test_efficiency_ratio_tracks_measured_memory() {
+        let mut grid = QuantumGrid::new();
+        assert_eq!(grid.efficiency_report().ratio, 0.0);
+
+        grid.set_cell("A1", 1.0).unwrap();
+        let sparse = grid.efficiency_report();
+        for row in 2..=1000 {
+            grid.set_cell(&format!("A{}", row), row as f64).unwrap();
+        }
+        let dense = grid.efficiency_report();
+
+        let measured = grid.columns()[&0].memory_used() as f64 / 1000.0;
+        assert_eq!(dense.our_bytes_per_cell, measured);
+        assert_eq!(dense.excel_bytes_per_cell, EXCEL_BYTES_PER_CELL);
+        assert_eq!(dense.ratio, EXCEL_BYTES_PER_CELL / measured);
+        assert_ne!(sparse.ratio, dense.ratio); // Per-cell overhead changes with size
+    }
+
+    #[test]
+    fn test_undo_restores_overwritten_values() {
+        let mut grid = QuantumGrid::new();
+        let a1 = CellRef::parse("A1").unwrap();
+
+        grid.set_cell("A1", 10.0).unwrap();
+        grid.set_cell("A1", 20.0).unwrap();
+        grid.set_text("A1", "thirty").unwrap();
+
+        assert!(grid.undo());
+        assert_eq!(grid.get_text(&a1), None);
+        assert_eq!(grid.get_value(&a1), Some(20.0));
+        assert!(grid.undo());
+        assert_eq!(grid.get_value(&a1), Some(10.0));
+
+        assert!(grid.redo());
+        assert_eq!(grid.get_value(&a1), Some(20.0));
+
+        // A new edit drops the redo stack
+        grid.set_cell("B1", 1.0).unwrap();
+        assert!(!grid.redo());
+
+        // Undo through to the very first write
+        assert!(grid.undo());
+        assert!(grid.undo());
+        assert!(grid.undo());
+        assert_eq!(grid.get_value(&a1), None);
+        assert!(!grid.undo());
+    }
+
+    #[test]
+    fn test_undo_clear_and_history_depth() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 1.0).unwrap();
+        grid.set_text("B2", "note").unwrap();
+        grid.set_formula("C1", "=A1+A1").unwrap();
+
+        grid.clear();
+        assert!(grid.undo());
+        assert_eq!(grid.get_cell("C1"), Ok(2.0));
+        assert_eq!(grid.get_text(&CellRef::parse("B2").unwrap()), Some("note"));
+
+        grid.set_history_depth(2);
+        for value in 2..=5 {
+            grid.set_cell("A1", value as f64).unwrap();
+        }
+        assert!(grid.undo());
+        assert!(grid.undo());
+        assert!(!grid.undo()); // Older edits fell off the history
+        assert_eq!(grid.get_cell("A1"), Ok(3.0));
+    }
+
+    #[test]
+    fn test_on_change_reports_dependents() {
+        use std::rc::Rc;
+
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 1.0).unwrap();
+        grid.set_formula("B1", "=A1+A1").unwrap();
+        grid.set_formula("C1", "=B1+A2").unwrap();
+        grid.set_formula("D1", "=A2+A3").unwrap();
+
+        let events: Rc<RefCell<Vec<Vec<String>>>> = Rc::default();
+        let sink = Rc::clone(&events);
+        grid.set_on_change(move |cells| {
+            sink.borrow_mut().push(cells.iter().map(|cell| cell.to_excel()).collect());
+        });
+
+        grid.set_cell("A1", 5.0).unwrap();
+        grid.set_cell("A1", 5.0).unwrap(); // Unchanged, so no event
+        grid.undo();
+
+        let events = events.borrow();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], vec!["A1", "B1", "C1"]);
+        assert_eq!(events[1], vec!["A1", "B1", "C1"]);
+    }
+
+    #[test]
+    fn test_dependency_edges_follow_chain() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 1.0).unwrap();
+        grid.set_formula("B1", "=A1+A1").unwrap();
+        grid.set_formula("C1", "=B1+B1").unwrap();
+
+        let cell = |reference| CellRef::parse(reference).unwrap();
+        assert_eq!(grid.dependency_edges(), vec![
+            (cell("B1"), cell("A1")),
+            (cell("C1"), cell("B1")),
+        ]);
+        assert_eq!(
+            grid.dependency_dot(),
+            "digraph dependencies {\n    \"A1\" -> \"B1\";\n    \"B1\" -> \"C1\";\n}\n"
+        );
     }
 }
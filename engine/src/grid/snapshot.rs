@@ -0,0 +1,166 @@
+//! JSON views of a grid - full snapshots and per-row records
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::QuantumGrid;
+use crate::excel::{column_letters, CellRef};
+use crate::formula::evaluator::CellValue;
+
+/// Serialized grid: one entry per non-empty cell, row by row
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GridSnapshot {
+    cells: Vec<CellSnapshot>,
+}
+
+/// A single cell; exactly one of value/text/formula is set unless the cell only carries metadata
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CellSnapshot {
+    cell: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    formula: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    meta: BTreeMap<String, String>,
+}
+
+impl QuantumGrid {
+    /// Serialize cells, formulas and metadata to JSON
+    pub fn to_json(&self) -> String {
+        // Keyed by (row, col) so the output order is stable
+        let mut cells: BTreeMap<(u32, u32), CellSnapshot> = BTreeMap::new();
+
+        for (cell_ref, formula) in &self.formulas {
+            entry(&mut cells, *cell_ref).formula = Some(formula.to_excel());
+        }
+        for (cell_ref, text) in &self.texts {
+            entry(&mut cells, *cell_ref).text = Some(text.clone());
+        }
+        for (col_idx, column) in &self.columns {
+            for (row_idx, value) in column.entries() {
+                let cell_ref = CellRef::new(row_idx as u32 + 1, col_idx + 1);
+                // Text and formula cells keep a placeholder number underneath
+                if !self.formulas.contains_key(&cell_ref) && !self.texts.contains_key(&cell_ref) {
+                    entry(&mut cells, cell_ref).value = Some(value);
+                }
+            }
+        }
+        for (cell_ref, meta) in &self.meta {
+            entry(&mut cells, *cell_ref).meta = meta.clone();
+        }
+
+        let snapshot = GridSnapshot { cells: cells.into_values().collect() };
+        serde_json::to_string(&snapshot).expect("grid snapshots always serialize")
+    }
+
+    /// Rebuild a grid from `to_json` output
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let snapshot: GridSnapshot = serde_json::from_str(json)
+            .map_err(|e| format!("Invalid grid JSON: {}", e))?;
+
+        let mut grid = QuantumGrid::new();
+        for cell in snapshot.cells {
+            if let Some(formula) = &cell.formula {
+                grid.set_formula(&cell.cell, formula)?;
+            } else if let Some(text) = &cell.text {
+                grid.set_text(&cell.cell, text)?;
+            } else if let Some(value) = cell.value {
+                grid.set_cell(&cell.cell, value)?;
+            }
+
+            for (key, value) in &cell.meta {
+                grid.set_cell_meta(&cell.cell, key, value)?;
+            }
+        }
+        Ok(grid)
+    }
+}
+
+impl QuantumGrid {
+    /// One JSON object per row (from row 1 to the last used row), keyed by
+    /// column header or column letter. Formula cells are evaluated; blanks are null.
+    /// Rows are built lazily as the iterator is consumed.
+    pub fn rows_as_records(&self) -> impl Iterator<Item = Value> + '_ {
+        let mut used: BTreeSet<u32> = self.columns.iter()
+            .filter(|(_, column)| !column.is_empty())
+            .map(|(col_idx, _)| col_idx + 1)
+            .collect();
+        used.extend(self.formulas.keys().chain(self.texts.keys()).map(|cell_ref| cell_ref.col));
+
+        let keys: Vec<(u32, String)> = used.into_iter()
+            .map(|col| (col, self.header(col).map(str::to_string).unwrap_or_else(|| column_letters(col))))
+            .collect();
+        let rows = self.used_range().map(|range| range.height()).unwrap_or(0);
+
+        (1..=rows).map(move |row| {
+            let mut record = Map::new();
+            for (col, key) in &keys {
+                let value = match self.get_cell_value(&CellRef::new(row, *col)) {
+                    CellValue::Number(n) => Value::from(n),
+                    CellValue::Text(text) => Value::from(text),
+                    CellValue::Empty => Value::Null,
+                    CellValue::Error(e) => Value::from(e.to_string()),
+                };
+                record.insert(key.clone(), value);
+            }
+            Value::Object(record)
+        })
+    }
+}
+
+/// Snapshot entry for a cell, created on first use
+fn entry(cells: &mut BTreeMap<(u32, u32), CellSnapshot>, cell_ref: CellRef) -> &mut CellSnapshot {
+    cells.entry((cell_ref.row, cell_ref.col)).or_insert_with(|| CellSnapshot {
+        cell: CellRef::new(cell_ref.row, cell_ref.col).to_excel(),
+        ..CellSnapshot::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_meta_round_trips_through_json() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 1250.5).unwrap();
+        grid.set_text("B1", "Kathmandu").unwrap();
+        grid.set_cell_meta("A1", "format", "currency").unwrap();
+        grid.set_cell_meta("A1", "color", "#ff0000").unwrap();
+        grid.set_cell_meta("C3", "note", "empty but tagged").unwrap();
+
+        let json = grid.to_json();
+        let restored = QuantumGrid::from_json(&json).unwrap();
+
+        let a1 = CellRef::parse("A1").unwrap();
+        assert_eq!(restored.get_cell_meta(&a1, "color"), Some("#ff0000"));
+        assert_eq!(restored.get_cell_meta(&a1, "format"), Some("currency"));
+        assert_eq!(restored.get_cell_meta(&a1, "missing"), None);
+        assert_eq!(restored.get_cell_meta(&CellRef::parse("C3").unwrap(), "note"), Some("empty but tagged"));
+        assert_eq!(restored.get_value(&a1), Some(1250.5));
+        assert_eq!(restored.get_text(&CellRef::parse("B1").unwrap()), Some("Kathmandu"));
+
+        // Same content serializes identically
+        assert_eq!(restored.to_json(), json);
+    }
+
+    #[test]
+    fn test_rows_as_records_uses_headers_or_letters() {
+        let mut grid = QuantumGrid::new();
+        grid.set_header(1, "name").unwrap();
+        grid.set_text("A1", "Sita").unwrap();
+        grid.set_cell("B1", 31.0).unwrap();
+        grid.set_text("A2", "Ram").unwrap();
+        grid.set_cell("B2", 27.0).unwrap();
+
+        let records: Vec<Value> = grid.rows_as_records().collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0], serde_json::json!({ "name": "Sita", "B": 31.0 }));
+        assert_eq!(records[1], serde_json::json!({ "name": "Ram", "B": 27.0 }));
+    }
+}
@@ -0,0 +1,123 @@
+//! Import functionality for Quantum Sheets
+//! Loads CSV text (pasted or dropped files) into a grid
+
+use crate::excel::CellRef;
+use crate::grid::QuantumGrid;
+
+/// Import data from different formats
+pub struct Importer;
+
+impl Importer {
+    /// Split CSV text into records (RFC 4180: quoted fields may contain
+    /// commas, newlines and doubled `""` quotes)
+    pub fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+        Self::parse_csv_with_delimiter(text, ',')
+    }
+
+    /// Split CSV text separated by `delimiter` (e.g. `;` or `\t`) into records
+    pub fn parse_csv_with_delimiter(text: &str, delimiter: char) -> Result<Vec<Vec<String>>, String> {
+        let mut records = Vec::new();
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                match c {
+                    '"' if chars.peek() == Some(&'"') => {
+                        field.push('"');
+                        chars.next();
+                    }
+                    '"' => in_quotes = false,
+                    _ => field.push(c),
+                }
+                continue;
+            }
+
+            match c {
+                '"' if field.is_empty() => in_quotes = true,
+                c if c == delimiter => record.push(std::mem::take(&mut field)),
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                _ => field.push(c),
+            }
+        }
+
+        if in_quotes {
+            return Err("Unterminated quoted field in CSV".to_string());
+        }
+
+        // Last record without a trailing newline
+        if !field.is_empty() || !record.is_empty() {
+            record.push(field);
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+
+    /// Load CSV text into the grid starting at A1, merging with existing cells.
+    /// Numbers become numeric cells, `=...` fields become formulas, anything else text.
+    /// Returns the (rows, columns) covered by the import.
+    pub fn csv_to_grid(grid: &mut QuantumGrid, text: &str) -> Result<(u32, u32), String> {
+        let records = Self::parse_csv(text)?;
+        Self::records_to_grid(grid, &records)
+    }
+
+    /// Load already-parsed CSV records into the grid, like `csv_to_grid`
+    pub fn records_to_grid(grid: &mut QuantumGrid, records: &[Vec<String>]) -> Result<(u32, u32), String> {
+        let rows = records.len() as u32;
+        let cols = records.iter().map(|r| r.len()).max().unwrap_or(0) as u32;
+
+        for (row_idx, record) in records.iter().enumerate() {
+            for (col_idx, field) in record.iter().enumerate() {
+                if field.is_empty() {
+                    continue;
+                }
+
+                let cell = CellRef::new(row_idx as u32 + 1, col_idx as u32 + 1).to_excel();
+                if let Ok(number) = field.trim().parse::<f64>() {
+                    grid.set_cell(&cell, number)?;
+                } else if field.starts_with('=') && grid.set_formula(&cell, field).is_ok() {
+                    // Stored as a formula
+                } else {
+                    grid.set_text(&cell, field)?;
+                }
+            }
+        }
+
+        Ok((rows, cols))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_quoting() {
+        let records = Importer::parse_csv("name,city\n\"Devkota, A\",\"Say \"\"hi\"\"\"\r\nRam,Pune").unwrap();
+        assert_eq!(records, vec![
+            vec!["name".to_string(), "city".to_string()],
+            vec!["Devkota, A".to_string(), "Say \"hi\"".to_string()],
+            vec!["Ram".to_string(), "Pune".to_string()],
+        ]);
+
+        assert!(Importer::parse_csv("\"open").is_err());
+    }
+
+    #[test]
+    fn test_csv_to_grid_dimensions_and_values() {
+        let mut grid = QuantumGrid::new();
+        let (rows, cols) = Importer::csv_to_grid(&mut grid, "id,score\n1,90\n2,85,extra\n").unwrap();
+
+        assert_eq!((rows, cols), (3, 3));
+        assert_eq!(grid.get_cell("B2").unwrap(), 90.0);
+        assert_eq!(grid.get_text(&CellRef::parse("A1").unwrap()), Some("id"));
+        assert_eq!(grid.get_text(&CellRef::parse("C3").unwrap()), Some("extra"));
+    }
+}
@@ -9,6 +9,7 @@ pub mod grid;
 pub mod formula;
 pub mod ai;
 pub mod export;
+pub mod import;
 pub mod excel;
 pub mod storage;
 
@@ -17,6 +18,7 @@ pub use grid::QuantumGrid;
 pub use excel::CellRef;
 pub use storage::QuantumColumn;
 pub use formula::ast::Formula;
+pub use formula::evaluator::CellValue;
 pub use api::QuantumAPI;  // NEW: Main user API
 
 
@@ -1,6 +1,8 @@
+use crate::excel::CellRef;
+use crate::formula::evaluator::{CellError, CellValue};
 use crate::grid::QuantumGrid;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OperationType {
     Calculation,
     AIPrompt,
@@ -8,6 +10,18 @@ pub enum OperationType {
     Custom,
 }
 
+impl OperationType {
+    /// Lowercase name for UIs and logs, e.g. "data_generation"
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            OperationType::Calculation => "calculation",
+            OperationType::AIPrompt => "ai_prompt",
+            OperationType::DataGeneration => "data_generation",
+            OperationType::Custom => "custom",
+        }
+    }
+}
+
 pub struct Operation {
     pub name: String,
     pub op_type: OperationType,
@@ -15,14 +29,40 @@ pub struct Operation {
     pub execute: Box<dyn Fn(&mut QuantumGrid, &[String]) -> Result<String, String>>,
 }
 
+impl Operation {
+    /// Run the operation as a grid transaction, so an error leaves none of its writes behind
+    pub fn atomic(mut self) -> Self {
+        let execute = self.execute;
+        self.execute = Box::new(move |grid, args| grid.transaction(|grid| execute(grid, args)));
+        self
+    }
+}
+
+/// Rows of values an array operation spills into the grid
+pub type SpillRows = Vec<Vec<CellValue>>;
+
+/// Computes an array operation's result from the grid and its arguments
+pub type ArrayExecutor = Box<dyn Fn(&QuantumGrid, &[String]) -> Result<SpillRows, String>>;
+
+/// Operation that returns a block of cells (SEQUENCE, SORT, ...) rather than one string.
+/// The registry spills the block into the grid starting at a destination cell.
+pub struct ArrayOperation {
+    pub name: String,
+    pub op_type: OperationType,
+    pub description: String,
+    pub execute: ArrayExecutor,
+}
+
 pub struct OperationRegistry {
     operations: std::collections::HashMap<String, Operation>,
+    array_operations: std::collections::HashMap<String, ArrayOperation>,
 }
 
 impl OperationRegistry {
     pub fn new() -> Self {
         let mut registry = Self {
             operations: std::collections::HashMap::new(),
+            array_operations: std::collections::HashMap::new(),
         };
         registry.register_builtins();
         registry
@@ -43,33 +83,76 @@ impl OperationRegistry {
         }
     }
     
+    /// Like `execute`, but refuses to run an operation of a different type,
+    /// e.g. a data generator reached from a formula
+    pub fn execute_typed(
+        &self,
+        name: &str,
+        op_type: OperationType,
+        grid: &mut QuantumGrid,
+        args: &[String],
+    ) -> Result<String, String> {
+        match self.get(name) {
+            Some(op) if op.op_type != op_type => Err(format!(
+                "'{}' is a {} operation, not a {} one",
+                name, op.op_type.to_str(), op_type.to_str()
+            )),
+            _ => self.execute(name, grid, args),
+        }
+    }
+    
+    /// Operations of one type, sorted by name
+    pub fn operations_by_type(&self, op_type: OperationType) -> Vec<&Operation> {
+        let mut operations: Vec<&Operation> = self.operations.values()
+            .filter(|op| op.op_type == op_type)
+            .collect();
+        operations.sort_by(|a, b| a.name.cmp(&b.name));
+        operations
+    }
+    
+    pub fn register_array(&mut self, operation: ArrayOperation) {
+        self.array_operations.insert(operation.name.clone(), operation);
+    }
+    
+    pub fn get_array(&self, name: &str) -> Option<&ArrayOperation> {
+        self.array_operations.get(name)
+    }
+    
+    /// Run an array operation and spill its result with the top-left corner at `dest`.
+    /// Errors with #SPILL! if any cell in the way isn't empty.
+    pub fn spill(&self, name: &str, grid: &mut QuantumGrid, args: &[String], dest: &CellRef) -> Result<String, String> {
+        let op = self.get_array(name)
+            .ok_or_else(|| format!("Array operation '{}' not found", name))?;
+        let rows = (op.execute)(grid, args)?;
+        let block = grid.spill(dest, &rows)?;
+        Ok(format!("{} spilled {}x{} into {}", name, block.height(), block.width(), block.to_excel()))
+    }
+    
     // NEW METHOD: List all operations
     pub fn list_operations(&self) -> Vec<String> {
         self.operations.values()
             .map(|op| format!("{} - {}", op.name, op.description))
+            .chain(self.array_operations.values().map(|op| format!("{} - {}", op.name, op.description)))
             .collect()
     }
     
+    /// (name, description, type) for every operation, sorted by name
+    pub fn describe_operations(&self) -> Vec<(&str, &str, OperationType)> {
+        let mut described: Vec<_> = self.operations.values()
+            .map(|op| (op.name.as_str(), op.description.as_str(), op.op_type))
+            .chain(self.array_operations.values().map(|op| (op.name.as_str(), op.description.as_str(), op.op_type)))
+            .collect();
+        described.sort_by_key(|(name, _, _)| *name);
+        described
+    }
+    
     fn register_builtins(&mut self) {
-        // SUM operation - FIXED VERSION
+        // SUM operation - numbers, cells and ranges; text in cells is skipped, literal text is #VALUE!
         self.register(Operation {
             name: "SUM".to_string(),
             op_type: OperationType::Calculation,
             description: "Sum numbers".to_string(),
-            execute: Box::new(|_grid, args| {
-                let mut total = 0.0;
-                for arg in args {
-                    // First try to parse as number directly
-                    if let Ok(num) = arg.parse::<f64>() {
-                        total += num;
-                    } else {
-                        // Try to get from grid (convert cell reference to value)
-                        // For now, just skip if not a number
-                        // In a full implementation, we'd parse cell references like "A1"
-                    }
-                }
-                Ok(format!("{}", total))
-            }),
+            execute: Box::new(|grid, args| evaluate_function(grid, "SUM", args)),
         });
         
         // DOUBLE operation - WORKING
@@ -89,26 +172,44 @@ impl OperationRegistry {
             }),
         });
         
+        // SET operation - write a number or text into a cell
+        self.register(Operation {
+            name: "SET".to_string(),
+            op_type: OperationType::Custom,
+            description: "Set a cell, e.g. SET A1 42".to_string(),
+            execute: Box::new(|grid, args| {
+                if args.len() < 2 {
+                    return Err("SET requires a cell and a value".to_string());
+                }
+
+                let value = args[1..].join(" ");
+                match value.parse::<f64>() {
+                    Ok(num) => grid.set_cell(&args[0], num)?,
+                    Err(_) => grid.set_text(&args[0], &value)?,
+                }
+                Ok(format!("{} = {}", args[0].to_uppercase(), value))
+            }),
+        });
+        
         // NEPAL_PHONE operation - WORKING
         self.register(Operation {
             name: "NEPAL_PHONE".to_string(),
             op_type: OperationType::DataGeneration,
             description: "Generate Nepal phone number".to_string(),
             execute: Box::new(|_grid, _args| {
-                use rand::Rng;
-                let mut rng = rand::thread_rng();
-                let number = format!("98{:08}", rng.gen_range(0..100000000));
-                Ok(number)
+                use crate::ai::data_generator::AIDataGenerator;
+                Ok(AIDataGenerator::new().generate_nepal_phone())
             }),
         });
         
-        // GENERATE_DATA operation - FIXED (handle DataRecord type)
+        // GENERATE_DATA operation - one record per row, one column per requested field;
+        // atomic, so a failed write partway leaves no half-filled rows
         self.register(Operation {
             name: "GENERATE_DATA".to_string(),
             op_type: OperationType::DataGeneration,
             description: "Generate test data".to_string(),
             execute: Box::new(|grid, args| {
-                use crate::ai::data_generator::AIDataGenerator;
+                use crate::ai::data_generator::{AIDataGenerator, Field};
                 
                 let count = args.get(0).and_then(|s| s.parse::<u32>().ok()).unwrap_or(10);
                 let request = if args.len() > 1 {
@@ -118,26 +219,508 @@ impl OperationRegistry {
                 };
                 
                 let mut generator = AIDataGenerator::new();
-                match generator.generate_from_request(&format!("{} rows {}", count, request)) {
-                    Ok(data_records) => {
-                        // Convert DataRecord to string for display
+                match generator.generate_table(&format!("{} rows {}", count, request)) {
+                    Ok((fields, rows)) => {
+                        // Columns follow the order the request names the fields, from A
                         let mut result = String::new();
-                        for (i, record) in data_records.iter().enumerate().take(count as usize) {
-                            result.push_str(&format!("Row {}: {:?}\n", i + 1, record));
+                        for (i, values) in rows.iter().enumerate().take(count as usize) {
+                            result.push_str(&format!("Row {}: {}\n", i + 1, values.join(", ")));
                             
-                            // Store in grid (simplified - just store as string in first column)
-                            if i < 100 { // Limit to first 100 rows
-                                let cell = format!("A{}", i + 1);
-                                let _ = grid.set_cell(&cell, 0.0); // Placeholder value
+                            let row = i as u32 + 1;
+                            for (col, (field, value)) in fields.iter().zip(values).enumerate() {
+                                let cell = CellRef::new(row, col as u32 + 1).to_string();
+                                match field {
+                                    Field::Id => grid.set_cell(&cell, (i + 1) as f64)?,
+                                    _ => grid.set_text(&cell, value)?,
+                                }
                             }
                         }
-                        Ok(format!("Generated {} rows:\n{}", count, result))
+                        let stored = rows.len().min(count as usize);
+                        let last = CellRef::new(stored as u32, fields.len() as u32);
+                        Ok(format!("Generated {} rows (stored in A1:{}):\n{}", stored, last, result))
                     }
                     Err(e) => Err(format!("Failed to generate data: {}", e)),
                 }
             }),
+        }.atomic());
+        
+        // SUMIF / COUNTIF - conditional aggregation, evaluated against the grid
+        self.register(Operation {
+            name: "SUMIF".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Sum cells matching a criterion, e.g. SUMIF(A1:A10, \">100\")".to_string(),
+            execute: Box::new(|grid, args| evaluate_function(grid, "SUMIF", args)),
+        });
+
+        self.register(Operation {
+            name: "COUNTIF".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Count cells matching a criterion, e.g. COUNTIF(B1:B10, \"Male\")".to_string(),
+            execute: Box::new(|grid, args| evaluate_function(grid, "COUNTIF", args)),
+        });
+        
+        // CAGR - compound annual growth rate
+        self.register(Operation {
+            name: "CAGR".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Compound growth rate, e.g. CAGR(100, 200, 2) or CAGR(A1:A10)".to_string(),
+            execute: Box::new(|grid, args| evaluate_function(grid, "CAGR", args)),
+        });
+        
+        // COUNT / COUNTA - numbers only vs anything non-empty
+        self.register(Operation {
+            name: "COUNT".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Count numbers, ignoring text and blanks, e.g. COUNT(A1:A10)".to_string(),
+            execute: Box::new(|grid, args| evaluate_function(grid, "COUNT", args)),
+        });
+        self.register(Operation {
+            name: "COUNTA".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Count non-empty cells of any type, e.g. COUNTA(A1:A10)".to_string(),
+            execute: Box::new(|grid, args| evaluate_function(grid, "COUNTA", args)),
+        });
+        
+        // SORT operation - sort a column in place, e.g. SORT A1:A10 desc
+        self.register(Operation {
+            name: "SORT".to_string(),
+            op_type: OperationType::Custom,
+            description: "Sort a column in place: SORT A1:A10 [asc|desc]".to_string(),
+            execute: Box::new(|grid, args| {
+                use crate::excel::CellRange;
+
+                let range = CellRange::parse(args.first().ok_or("SORT requires a range")?)?;
+                let ascending = match args.get(1).map(|order| order.to_lowercase()) {
+                    None => true,
+                    Some(order) if order == "asc" || order == "ascending" => true,
+                    Some(order) if order == "desc" || order == "descending" => false,
+                    Some(order) => return Err(format!("Sort order must be asc or desc, not '{}'", order)),
+                };
+
+                grid.sort_range(&range, ascending)?;
+                Ok(format!("Sorted {} {}", range.to_excel(), if ascending { "ascending" } else { "descending" }))
+            }),
+        });
+        
+        // UNIQUE operation - distinct values of a range in first-seen order
+        self.register(Operation {
+            name: "UNIQUE".to_string(),
+            op_type: OperationType::Custom,
+            description: "Distinct values of a range, one per line, or spilled down from a cell: UNIQUE(A1:A20 [, C1])".to_string(),
+            execute: Box::new(|grid, args| {
+                use crate::excel::CellRange;
+
+                let range = CellRange::parse(args.first().ok_or("UNIQUE requires a range")?)?;
+                let values: Vec<CellValue> = range.cells().map(|cell| grid.get_cell_value(&cell)).collect();
+                let distinct = distinct_values(values);
+
+                match args.get(1) {
+                    Some(dest) => {
+                        if distinct.is_empty() {
+                            return Ok(format!("No values in {}", range.to_excel()));
+                        }
+                        let rows: Vec<Vec<CellValue>> = distinct.into_iter().map(|value| vec![value]).collect();
+                        let block = grid.spill(&CellRef::parse(dest)?, &rows)?;
+                        Ok(format!("{} unique values written to {}", rows.len(), block.to_excel()))
+                    }
+                    None => Ok(distinct.iter().map(|value| value.to_string()).collect::<Vec<_>>().join("\n")),
+                }
+            }),
         });
         
+        // FILTER operation - rows whose first column passes a numeric comparison
+        self.register(Operation {
+            name: "FILTER".to_string(),
+            op_type: OperationType::Custom,
+            description: "Rows where the first column compares to a number: FILTER A1:B10 > 100 [D1]".to_string(),
+            execute: Box::new(|grid, args| {
+                use crate::excel::CellRange;
+                use crate::formula::evaluator::Criterion;
+
+                // "A1:B10 > 100 D1", "B >= 5", or "data where value > 100" (column A)
+                let pattern = regex::Regex::new(concat!(
+                    r"(?i)^\s*(?:(?P<range>\$?[a-z]{1,3}(?:\$?\d+\s*:\s*\$?[a-z]{1,3}\$?\d+)?)|data\s+where\s+value)?",
+                    r"\s*(?P<op>>=|<=|>|<|=)\s*(?P<threshold>-?\d+(?:\.\d+)?)",
+                    r"(?:\s+(?:(?:in|into|to)\s+)?(?P<dest>\$?[a-z]{1,3}\$?\d+))?\s*$",
+                )).expect("valid filter pattern");
+                let text = args.join(" ");
+                let caps = pattern.captures(&text)
+                    .ok_or_else(|| format!("FILTER expects a range, >, <, >=, <= or = and a number, not '{}'", text))?;
+
+                let range = match caps.name("range").map(|m| m.as_str().replace(' ', "")) {
+                    Some(range) if range.contains(':') => CellRange::parse(&range)?,
+                    column => {
+                        let col = CellRef::parse(&format!("{}1", column.as_deref().unwrap_or("A")))?.col;
+                        let last_row = grid.used_range().map_or(1, |used| used.end.row);
+                        CellRange::new(CellRef::new(1, col), CellRef::new(last_row, col))
+                    }
+                };
+                let condition = format!("{}{}", &caps["op"], &caps["threshold"]);
+                let criterion = Criterion::parse(&CellValue::Text(condition.clone()));
+
+                let rows: SpillRows = (0..range.height())
+                    .filter(|&row| criterion.matches(&grid.get_cell_value(&range.cell_at(row, 0))))
+                    .map(|row| (0..range.width()).map(|col| grid.get_cell_value(&range.cell_at(row, col))).collect())
+                    .collect();
+                if rows.is_empty() {
+                    return Ok(format!("No rows in {} are {}", range.to_excel(), condition));
+                }
+
+                match caps.name("dest") {
+                    Some(dest) => {
+                        let block = grid.spill(&CellRef::parse(dest.as_str())?, &rows)?;
+                        Ok(format!("{} matching rows written to {}", rows.len(), block.to_excel()))
+                    }
+                    None => Ok(rows.iter()
+                        .map(|row| row.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(", "))
+                        .collect::<Vec<_>>()
+                        .join("\n")),
+                }
+            }),
+        });
+        
+        // CONCAT - join values into text
+        self.register(Operation {
+            name: "CONCAT".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Join values into text, e.g. CONCAT(A1, B1)".to_string(),
+            execute: Box::new(|grid, args| evaluate_function(grid, "CONCAT", args)),
+        });
+        
+        // VLOOKUP - exact-match lookup in a range's first column
+        self.register(Operation {
+            name: "VLOOKUP".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Exact-match lookup, e.g. VLOOKUP(102, A1:C10, 2)".to_string(),
+            execute: Box::new(|grid, args| evaluate_function(grid, "VLOOKUP", args)),
+        });
+        
+        // INDEX - the cell at a (row, column) offset inside a range
+        self.register(Operation {
+            name: "INDEX".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Cell at a 1-based offset in a range, e.g. INDEX(A1:C10, 2, 3)".to_string(),
+            execute: Box::new(|grid, args| evaluate_function(grid, "INDEX", args)),
+        });
+        
+        // MATCH - position of an exact match in a row or column
+        self.register(Operation {
+            name: "MATCH".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Position of an exact match in a row or column, e.g. MATCH(102, A1:A10, 0)".to_string(),
+            execute: Box::new(|grid, args| evaluate_function(grid, "MATCH", args)),
+        });
+        
+        // PRODUCT - multiply numbers and ranges
+        self.register(Operation {
+            name: "PRODUCT".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Multiply numbers, e.g. PRODUCT(A1:A5); an empty range gives 1".to_string(),
+            execute: Box::new(|grid, args| evaluate_function(grid, "PRODUCT", args)),
+        });
+        
+        // AVERAGE / MIN / MAX / PERCENTILE / CORREL / SLOPE / INTERCEPT - evaluated like SUMIF
+        for (name, description) in [
+            ("AVERAGE", "Average of numbers, e.g. AVERAGE(A1:A10)"),
+            ("MIN", "Smallest number, e.g. MIN(A1:A10)"),
+            ("MAX", "Largest number, e.g. MAX(A1:A10)"),
+            ("PERCENTILE", "k-th percentile (k from 0 to 1), e.g. PERCENTILE(A1:A100, 0.9)"),
+            ("CORREL", "Pearson correlation of two ranges, e.g. CORREL(A1:A100, B1:B100)"),
+            ("SLOPE", "Slope of the least-squares line, e.g. SLOPE(B1:B100, A1:A100) for y in B, x in A"),
+            ("INTERCEPT", "Intercept of the least-squares line, e.g. INTERCEPT(B1:B100, A1:A100)"),
+        ] {
+            self.register(Operation {
+                name: name.to_string(),
+                op_type: OperationType::Calculation,
+                description: description.to_string(),
+                execute: Box::new(move |grid, args| evaluate_function(grid, name, args)),
+            });
+        }
+        
+        // TRIM / CLEAN - tidy imported text in place, or into a destination
+        for (name, description) in [
+            ("TRIM", "Strip surrounding whitespace from text cells, in place or at a given cell, e.g. TRIM(A1:A100)"),
+            ("CLEAN", "Remove non-printable characters from text cells, in place or at a given cell, e.g. CLEAN(A1:A100)"),
+        ] {
+            self.register(Operation {
+                name: name.to_string(),
+                op_type: OperationType::Custom,
+                description: description.to_string(),
+                execute: Box::new(move |grid, args| clean_text_cells(grid, name, args)),
+            });
+        }
+
+        // STATS operation - every summary statistic of a range in one block
+        self.register(Operation {
+            name: "STATS".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Count, sum, mean, median, mode, min, max and stddev of a range, e.g. STATS(A1:A100)".to_string(),
+            execute: Box::new(|grid, args| {
+                use crate::excel::CellRange;
+
+                let range = CellRange::parse(args.first().ok_or("STATS requires a range")?)?;
+                let values: Vec<f64> = range.cells()
+                    .filter_map(|cell| match grid.get_cell_value(&cell) {
+                        CellValue::Number(n) => Some(n),
+                        _ => None,
+                    })
+                    .collect();
+                let stats = crate::compute::calculate_full_stats(&values)
+                    .ok_or_else(|| format!("No numbers in {}", range.to_excel()))?;
+
+                let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_else(|| "none".to_string());
+                Ok(format!(
+                    "Statistics for {}:\ncount: {}\nsum: {}\nmean: {}\nmedian: {}\nmode: {}\nmin: {}\nmax: {}\nstddev: {}",
+                    range.to_excel(), stats.count, stats.sum, stats.mean, stats.median,
+                    optional(stats.mode), stats.min, stats.max, optional(stats.std_dev)
+                ))
+            }),
+        });
+
+        // HISTOGRAM operation - counts of a range's numbers in equal-width bins
+        self.register(Operation {
+            name: "HISTOGRAM".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Count numbers into equal-width bins, e.g. HISTOGRAM(A1:A100, 10)".to_string(),
+            execute: Box::new(|grid, args| {
+                use crate::excel::CellRange;
+
+                let range = CellRange::parse(args.first().ok_or("HISTOGRAM requires a range")?)?;
+                let bins = match args.get(1) {
+                    Some(text) => text.parse::<usize>()
+                        .ok()
+                        .filter(|&bins| bins > 0)
+                        .ok_or_else(|| format!("'{}' is not a valid number of bins", text))?,
+                    None => 10,
+                };
+                let values: Vec<f64> = range.cells()
+                    .filter_map(|cell| match grid.get_cell_value(&cell) {
+                        CellValue::Number(n) => Some(n),
+                        _ => None,
+                    })
+                    .collect();
+                if values.is_empty() {
+                    return Err(format!("No numbers in {}", range.to_excel()));
+                }
+
+                let mut result = format!("Histogram of {} ({} values):", range.to_excel(), values.len());
+                for (low, high, count) in crate::compute::histogram(&values, bins) {
+                    result.push_str(&format!("\n{} to {}: {}", low, high, count));
+                }
+                Ok(result)
+            }),
+        });
+
+        // CUMSUM operation - running total next to a column
+        self.register(Operation {
+            name: "CUMSUM".to_string(),
+            op_type: OperationType::Custom,
+            description: "Running total of a column, written beside it (or at a given cell), e.g. CUMSUM(A1:A10)".to_string(),
+            execute: Box::new(|grid, args| {
+                use crate::excel::CellRange;
+
+                let range = CellRange::parse(args.first().ok_or("CUMSUM requires a range")?)?;
+                if range.width() != 1 {
+                    return Err("CUMSUM works on a single column".to_string());
+                }
+                let dest = match args.get(1) {
+                    Some(cell) => CellRef::parse(cell)?,
+                    None => CellRef::new(range.start.row, range.end.col + 1),
+                };
+
+                // Text and blanks add nothing, so the total carries over
+                let mut total = 0.0;
+                let mut rows = Vec::new();
+                for row in 0..range.height() {
+                    match grid.get_cell_value(&range.cell_at(row, 0)) {
+                        CellValue::Number(n) => total += n,
+                        CellValue::Error(e) => return Err(format!("{} in {}", e, range.cell_at(row, 0))),
+                        CellValue::Text(_) | CellValue::Empty => {}
+                    }
+                    rows.push(vec![CellValue::Number(total)]);
+                }
+
+                let block = grid.spill(&dest, &rows)?;
+                Ok(format!("Running total written to {}", block.to_excel()))
+            }),
+        });
+        
+        // MOVAVG operation - rolling mean of a column, written beside it
+        self.register(Operation {
+            name: "MOVAVG".to_string(),
+            op_type: OperationType::Custom,
+            description: "Rolling mean of a column, written beside it (or at a given cell), e.g. MOVAVG(A1:A100, 5)".to_string(),
+            execute: Box::new(|grid, args| {
+                use crate::excel::CellRange;
+
+                if args.len() < 2 {
+                    return Err("MOVAVG requires a range and a window size".to_string());
+                }
+                let range = CellRange::parse(&args[0])?;
+                if range.width() != 1 {
+                    return Err("MOVAVG works on a single column".to_string());
+                }
+                let window = args[1].parse::<usize>()
+                    .map_err(|_| format!("'{}' is not a valid window size", args[1]))?;
+                let dest = match args.get(2) {
+                    Some(cell) => CellRef::parse(cell)?,
+                    None => CellRef::new(range.start.row, range.end.col + 1),
+                };
+
+                // Text and blanks are gaps that keep their rows
+                let mut values = Vec::new();
+                for cell in range.cells() {
+                    match grid.get_cell_value(&cell) {
+                        CellValue::Number(n) => values.push(Some(n)),
+                        CellValue::Error(e) => return Err(format!("{} in {}", e, cell)),
+                        CellValue::Text(_) | CellValue::Empty => values.push(None),
+                    }
+                }
+                if window == 0 || window > values.len() {
+                    return Err(format!("Window must be between 1 and {}, the number of rows", values.len()));
+                }
+
+                // Each average sits in the row of its window's last value. Nothing is
+                // written where the window would reach back past the start or over a gap.
+                let mut rows = vec![vec![CellValue::Empty]; values.len()];
+                let mut run_start = 0;
+                for run in values.split(Option::is_none) {
+                    let numbers: Vec<f64> = run.iter().flatten().copied().collect();
+                    for (i, mean) in crate::compute::moving_average(&numbers, window).into_iter().enumerate() {
+                        rows[run_start + window - 1 + i] = vec![CellValue::Number(mean)];
+                    }
+                    run_start += run.len() + 1;
+                }
+                if rows.iter().all(|row| row[0] == CellValue::Empty) {
+                    return Err(format!("No {} numbers in a row in {} to average", window, range.to_excel()));
+                }
+
+                let block = grid.spill(&dest, &rows)?;
+                Ok(format!("Moving average (window {}) written to {}", window, block.to_excel()))
+            }),
+        });
+
+        // JOIN operation - relational join of two ranges on key columns
+        self.register(Operation {
+            name: "JOIN".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Join two ranges on key columns (inner or left)".to_string(),
+            execute: Box::new(|grid, args| {
+                use crate::excel::CellRange;
+                use crate::grid::JoinKind;
+
+                if args.len() < 4 {
+                    return Err("JOIN requires left_range, right_range, left_key_col, right_key_col[, join_type]".to_string());
+                }
+
+                let left = CellRange::parse(&args[0])?;
+                let right = CellRange::parse(&args[1])?;
+                let left_key = args[2].parse::<u32>()
+                    .map_err(|_| format!("'{}' is not a valid key column", args[2]))?;
+                let right_key = args[3].parse::<u32>()
+                    .map_err(|_| format!("'{}' is not a valid key column", args[3]))?;
+                let kind = match args.get(4) {
+                    Some(text) => JoinKind::parse(text)?,
+                    None => JoinKind::Inner,
+                };
+
+                let rows = grid.join_ranges(&left, &right, left_key, right_key, kind)?;
+
+                let mut result = format!("Joined {} rows:", rows.len());
+                for row in &rows {
+                    let cells: Vec<String> = row.iter()
+                        .map(|cell| cell.map(|v| v.to_string()).unwrap_or_default())
+                        .collect();
+                    result.push('\n');
+                    result.push_str(&cells.join(","));
+                }
+                Ok(result)
+            }),
+        });
+
+        // CORRMATRIX operation - pairwise correlations written to a block
+        self.register(Operation {
+            name: "CORRMATRIX".to_string(),
+            op_type: OperationType::Custom,
+            description: "Correlation matrix of a range's columns, e.g. CORRMATRIX A1:C100 E1".to_string(),
+            execute: Box::new(|grid, args| {
+                use crate::excel::CellRange;
+
+                if args.len() < 2 {
+                    return Err("CORRMATRIX requires a range and a destination cell".to_string());
+                }
+
+                let range = CellRange::parse(&args[0])?;
+                let dest = CellRef::parse(&args[1])?;
+
+                let columns: Vec<Vec<Option<f64>>> = (0..range.width())
+                    .map(|col| {
+                        (0..range.height())
+                            .map(|row| match grid.get_cell_value(&range.cell_at(row, col)) {
+                                CellValue::Number(n) => Some(n),
+                                _ => None,
+                            })
+                            .collect()
+                    })
+                    .collect();
+
+                // Pairs that can't be correlated (constant columns) are #DIV/0!
+                let rows: SpillRows = crate::compute::correlation_matrix(&columns)
+                    .into_iter()
+                    .map(|row| {
+                        row.into_iter()
+                            .map(|r| r.map_or(CellValue::Error(CellError::DivZero), CellValue::Number))
+                            .collect()
+                    })
+                    .collect();
+
+                let block = grid.spill(&dest, &rows)?;
+                Ok(format!("Correlation matrix ({}x{}) written to {}", rows.len(), rows.len(), block.to_excel()))
+            }),
+        });
+
+        // SEQUENCE operation - spills a run of numbers, e.g. SEQUENCE 3 A1 fills A1:A3
+        self.register_array(ArrayOperation {
+            name: "SEQUENCE".to_string(),
+            op_type: OperationType::Calculation,
+            description: "Spill numbers: SEQUENCE rows [cols] [start] [step] dest".to_string(),
+            execute: Box::new(|grid, args| {
+                let number = |i: usize, default: f64| -> Result<f64, String> {
+                    match args.get(i) {
+                        Some(arg) => arg.parse::<f64>()
+                            .map_err(|_| format!("SEQUENCE argument '{}' is not a number", arg)),
+                        None => Ok(default),
+                    }
+                };
+                if args.is_empty() {
+                    return Err("SEQUENCE requires a row count".to_string());
+                }
+
+                let (rows, cols) = (number(0, 1.0)?, number(1, 1.0)?);
+                let (start, step) = (number(2, 1.0)?, number(3, 1.0)?);
+                if rows < 1.0 || cols < 1.0 || rows.fract() != 0.0 || cols.fract() != 0.0 {
+                    return Err("SEQUENCE rows and columns must be whole numbers of at least 1".to_string());
+                }
+                // Refuse before allocating a block that could never be spilled
+                let limits = grid.limits();
+                if rows > limits.max_rows as f64 || cols > limits.max_cols as f64 {
+                    return Err(format!(
+                        "SEQUENCE {}x{} is larger than the grid limit of {} rows x {} columns",
+                        rows, cols, limits.max_rows, limits.max_cols
+                    ));
+                }
+
+                let (rows, cols) = (rows as usize, cols as usize);
+                Ok((0..rows)
+                    .map(|i| {
+                        (0..cols)
+                            .map(|j| CellValue::Number(start + step * (i * cols + j) as f64))
+                            .collect()
+                    })
+                    .collect())
+            }),
+        });
+
         // NATURAL operation - SIMPLIFIED WORKING VERSION
         self.register(Operation {
             name: "NATURAL".to_string(),
@@ -153,6 +736,26 @@ impl OperationRegistry {
                 let command = args.join(" ");
                 let translator = NaturalLanguageTranslator::new();
                 
+                // "... in C1" / "... into C1" stores the result in that cell
+                let target = regex::Regex::new(r"(?i)\s+(?:in|into)\s+(\$?[a-z]+\$?\d+)\s*$")
+                    .expect("valid target pattern");
+                if let Some(caps) = target.captures(&command) {
+                    let cell = crate::excel::CellRef::parse(&caps[1])?.to_excel();
+                    let request = &command[..caps.get(0).map_or(0, |m| m.start())];
+                    let formula = translator.translate(request)
+                        .ok_or_else(|| format!("Could not translate '{}' to a formula", request))?;
+                    
+                    let value = crate::formula::parser::evaluate_formula(&formula, grid)
+                        .map_err(|e| format!("Could not execute: {}", e))?;
+                    match &value {
+                        CellValue::Number(n) => grid.set_cell(&cell, *n)?,
+                        CellValue::Text(text) => grid.set_text(&cell, text)?,
+                        CellValue::Empty => grid.set_cell(&cell, 0.0)?,
+                        CellValue::Error(e) => return Err(format!("Could not execute: {} gives {}", formula, e)),
+                    }
+                    return Ok(format!("Translated to: {} = {} (stored in {})", formula, value, cell));
+                }
+                
                 // The translator returns Option<String>
                 match translator.translate(&command) {
                     Some(formula) => {
@@ -170,4 +773,459 @@ impl OperationRegistry {
             }),
         });
     }
-}
\ No newline at end of file
+}
+
+/// Values with duplicates and blanks removed, keeping the first of each.
+/// Text matches case-insensitively, like Excel's UNIQUE.
+fn distinct_values(values: Vec<CellValue>) -> Vec<CellValue> {
+    let mut distinct: Vec<CellValue> = Vec::new();
+    for value in values {
+        let seen = distinct.iter().any(|kept| match (kept, &value) {
+            (CellValue::Text(a), CellValue::Text(b)) => a.to_lowercase() == b.to_lowercase(),
+            (a, b) => a == b,
+        });
+        if value != CellValue::Empty && !seen {
+            distinct.push(value);
+        }
+    }
+    distinct
+}
+
+/// Run a formula function through the evaluator with string arguments
+fn evaluate_function(grid: &QuantumGrid, name: &str, args: &[String]) -> Result<String, String> {
+    use crate::formula::ast::Expr;
+    use crate::formula::evaluator::Evaluator;
+    use crate::formula::parser::parse_argument;
+
+    let args = args.iter().map(|arg| parse_argument(arg)).collect();
+    let value = Evaluator::new(grid).eval(&Expr::Function(name.to_string(), args));
+    Ok(value.to_string())
+}
+
+/// Apply the TRIM or CLEAN function to every text cell of a range (or single cell).
+/// Without a destination, changed text cells are rewritten in place as one edit;
+/// with one, the whole range is spilled there, numbers and blanks included.
+/// Formula cells are never overwritten.
+fn clean_text_cells(grid: &mut QuantumGrid, name: &str, args: &[String]) -> Result<String, String> {
+    use crate::excel::CellRange;
+    use crate::formula::ast::Expr;
+    use crate::formula::evaluator::Evaluator;
+
+    let target = args.first().ok_or_else(|| format!("{} requires a range", name))?;
+    let range = CellRange::parse(target)
+        .or_else(|_| CellRef::parse(target).map(|cell| CellRange::new(cell, cell)))?;
+    let clean = |grid: &QuantumGrid, cell: CellRef| {
+        Evaluator::new(grid).eval(&Expr::Function(name.to_string(), vec![Expr::CellRef(cell)]))
+    };
+
+    if let Some(dest) = args.get(1) {
+        let rows: Vec<Vec<CellValue>> = (0..range.height())
+            .map(|row| (0..range.width()).map(|col| clean(grid, range.cell_at(row, col))).collect())
+            .collect();
+        let block = grid.spill(&CellRef::parse(dest)?, &rows)?;
+        return Ok(format!("{} of {} written to {}", name, range.to_excel(), block.to_excel()));
+    }
+
+    let mut changed = Vec::new();
+    for cell in range.cells() {
+        let Some(text) = grid.get_text(&cell) else {
+            continue;
+        };
+        if let CellValue::Text(cleaned) = clean(grid, cell) {
+            if cleaned != text {
+                changed.push((cell.to_excel(), cleaned));
+            }
+        }
+    }
+    let entries: Vec<(&str, &str)> = changed.iter().map(|(cell, text)| (cell.as_str(), text.as_str())).collect();
+    grid.set_texts(&entries);
+    Ok(format!("{} changed {} cells in {}", name, changed.len(), range.to_excel()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_rewrites_text_cells_in_place() {
+        let mut grid = QuantumGrid::new();
+        grid.set_text("A1", "  Kathmandu  ").unwrap();
+        grid.set_text("A2", "Pokhara").unwrap();
+        grid.set_cell("A3", 7.0).unwrap();
+
+        let registry = OperationRegistry::new();
+        let result = registry.execute("TRIM", &mut grid, &["A1:A3".to_string()]).unwrap();
+        assert_eq!(result, "TRIM changed 1 cells in A1:A3");
+        assert_eq!(grid.get_text(&CellRef::parse("A1").unwrap()), Some("Kathmandu"));
+        assert_eq!(grid.get_cell("A3"), Ok(7.0));
+
+        grid.set_text("B1", " \u{7}tab ").unwrap();
+        registry.execute("CLEAN", &mut grid, &["B1".to_string(), "C1".to_string()]).unwrap();
+        assert_eq!(grid.get_text(&CellRef::parse("C1").unwrap()), Some(" tab "));
+    }
+
+    #[test]
+    fn test_corrmatrix_is_symmetric_with_unit_diagonal() {
+        let mut grid = QuantumGrid::new();
+        let rows = [(1.0, 2.0, 9.0), (2.0, 4.1, 7.0), (3.0, 5.9, 8.0), (4.0, 8.2, 3.0), (5.0, 9.9, 1.0)];
+        for (i, (a, b, c)) in rows.iter().enumerate() {
+            grid.set_cell(&format!("A{}", i + 1), *a).unwrap();
+            grid.set_cell(&format!("B{}", i + 1), *b).unwrap();
+            grid.set_cell(&format!("C{}", i + 1), *c).unwrap();
+        }
+
+        let registry = OperationRegistry::new();
+        let args = ["A1:C5".to_string(), "E1".to_string()];
+        let result = registry.execute("CORRMATRIX", &mut grid, &args).unwrap();
+        assert_eq!(result, "Correlation matrix (3x3) written to E1:G3");
+
+        let r = |row: u32, col: u32| grid.get_cell(&crate::excel::CellRef::new(row, col).to_excel()).unwrap();
+        for i in 1..=3 {
+            assert_eq!(r(i, 4 + i), 1.0);
+            for j in 1..=3 {
+                assert_eq!(r(i, 4 + j), r(j, 4 + i));
+            }
+        }
+        assert!(r(1, 7) < -0.8); // A and C move in opposite directions
+
+        // A single column is a 1x1 matrix; a constant column can't be correlated
+        registry.execute("CORRMATRIX", &mut grid, &["B1:B5".to_string(), "J1".to_string()]).unwrap();
+        assert_eq!(grid.get_cell("J1").unwrap(), 1.0);
+        for row in 1..=5 {
+            grid.set_cell(&format!("K{}", row), 3.0).unwrap();
+        }
+        registry.execute("CORRMATRIX", &mut grid, &["J1:K5".to_string(), "M1".to_string()]).unwrap();
+        assert_eq!(grid.get_cell_value(&CellRef::parse("N1").unwrap()), CellValue::Text("#DIV/0!".to_string()));
+
+        // The block spills like CUMSUM: nothing in the way is overwritten, and one undo removes it
+        let err = registry.execute("CORRMATRIX", &mut grid, &["A1:C5".to_string(), "F2".to_string()]).unwrap_err();
+        assert!(err.contains("#SPILL!"), "{}", err);
+        assert_eq!(grid.get_cell_value(&CellRef::parse("H4").unwrap()), CellValue::Empty);
+        assert!(grid.undo());
+        assert_eq!(grid.get_cell_value(&CellRef::parse("M1").unwrap()), CellValue::Empty);
+    }
+
+    #[test]
+    fn test_describe_operations_is_sorted_with_types() {
+        let registry = OperationRegistry::new();
+        let described = registry.describe_operations();
+
+        assert!(described.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        let sum = described.iter().find(|(name, _, _)| *name == "SUM").unwrap();
+        assert_eq!(sum.2.to_str(), "calculation");
+        let generate = described.iter().find(|(name, _, _)| *name == "GENERATE_DATA").unwrap();
+        assert_eq!(generate.2.to_str(), "data_generation");
+        assert!(described.iter().any(|(name, _, _)| *name == "AVERAGE"));
+        assert!(described.iter().any(|(name, _, _)| *name == "SEQUENCE"));
+    }
+
+    #[test]
+    fn test_operations_by_type_and_typed_execute() {
+        let registry = OperationRegistry::new();
+        let generators: Vec<&str> = registry.operations_by_type(OperationType::DataGeneration)
+            .iter()
+            .map(|op| op.name.as_str())
+            .collect();
+        assert_eq!(generators, ["GENERATE_DATA", "NEPAL_PHONE"]);
+
+        let mut grid = QuantumGrid::new();
+        let args = ["5".to_string(), "Nepal phone numbers".to_string()];
+        let err = registry.execute_typed("GENERATE_DATA", OperationType::Calculation, &mut grid, &args);
+        assert_eq!(err, Err("'GENERATE_DATA' is a data_generation operation, not a calculation one".to_string()));
+        assert!(grid.used_range().is_none());
+
+        let sum = registry.execute_typed("SUM", OperationType::Calculation, &mut grid, &["1".to_string(), "2".to_string()]);
+        assert_eq!(sum, Ok("3".to_string()));
+    }
+
+    #[test]
+    fn test_filter_by_numeric_threshold() {
+        let mut grid = QuantumGrid::new();
+        for (row, (value, name)) in [(50.0, "a"), (150.0, "b"), (100.0, "c"), (250.0, "d")].iter().enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *value).unwrap();
+            grid.set_text(&format!("B{}", row + 1), name).unwrap();
+        }
+        let registry = OperationRegistry::new();
+        let filter = |grid: &mut QuantumGrid, command: &str| {
+            let args: Vec<String> = command.split_whitespace().map(String::from).collect();
+            registry.execute("FILTER", grid, &args)
+        };
+
+        assert_eq!(filter(&mut grid, "data where value > 100").unwrap(), "150\n250");
+        assert_eq!(filter(&mut grid, "A >= 100").unwrap(), "150\n100\n250");
+        assert_eq!(filter(&mut grid, "A1:A4 <= 100").unwrap(), "50\n100");
+        assert_eq!(filter(&mut grid, "A1:A4 < 50").unwrap(), "No rows in A1:A4 are <50");
+        assert_eq!(filter(&mut grid, "A1:B4 = 100").unwrap(), "100, c");
+
+        let result = filter(&mut grid, "A1:B4 > 100 D1").unwrap();
+        assert_eq!(result, "2 matching rows written to D1:E2");
+        assert_eq!(grid.get_cell("D2").unwrap(), 250.0);
+        assert_eq!(grid.get_text(&CellRef::parse("E1").unwrap()), Some("b"));
+
+        assert!(filter(&mut grid, "A1:A4 > lots").is_err());
+    }
+
+    #[test]
+    fn test_sum_ignores_text_in_ranges() {
+        let mut grid = QuantumGrid::new();
+        let registry = OperationRegistry::new();
+        grid.set_cell("A1", 4.0).unwrap();
+        grid.set_text("A2", "pending").unwrap();
+        grid.set_cell("A3", 6.0).unwrap();
+
+        let sum = |grid: &mut QuantumGrid, args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            registry.execute("SUM", grid, &args).unwrap()
+        };
+        assert_eq!(sum(&mut grid, &["A1:A3"]), "10");
+        assert_eq!(sum(&mut grid, &["A1:A3", "5"]), "15");
+        assert_eq!(sum(&mut grid, &["\"abc\""]), "#VALUE!");
+    }
+
+    #[test]
+    fn test_atomic_operation_rolls_back_on_error() {
+        let mut grid = QuantumGrid::new();
+        let mut registry = OperationRegistry::new();
+        grid.set_cell("A1", 1.0).unwrap();
+        let write_then_fail = || Operation {
+            name: "HALF_WRITE".to_string(),
+            op_type: OperationType::Custom,
+            description: "Write two cells, then fail".to_string(),
+            execute: Box::new(|grid, _args| {
+                grid.set_cell("A1", 100.0)?;
+                grid.set_text("B1", "written")?;
+                Err("failed after writing".to_string())
+            }),
+        };
+
+        registry.register(write_then_fail().atomic());
+        let result = registry.execute("HALF_WRITE", &mut grid, &[]);
+        assert_eq!(result, Err("failed after writing".to_string()));
+        assert_eq!(grid.get_cell("A1").unwrap(), 1.0);
+        assert_eq!(grid.get_text(&CellRef::parse("B1").unwrap()), None);
+
+        // The rolled-back writes left nothing in the undo history
+        assert!(grid.undo());
+        assert!(grid.get_value(&CellRef::parse("A1").unwrap()).is_none());
+        assert!(!grid.undo());
+
+        // Without `atomic`, the writes before the error stay
+        let mut grid = QuantumGrid::new();
+        registry.register(write_then_fail());
+        assert!(registry.execute("HALF_WRITE", &mut grid, &[]).is_err());
+        assert_eq!(grid.get_cell("A1").unwrap(), 100.0);
+    }
+
+    #[test]
+    fn test_generate_data_stores_every_row() {
+        let mut grid = QuantumGrid::new();
+        let registry = OperationRegistry::new();
+        let args = ["150".to_string(), "ids with Nepal phone numbers".to_string()];
+        let summary = registry.execute("GENERATE_DATA", &mut grid, &args).unwrap();
+
+        assert!(summary.starts_with("Generated 150 rows (stored in A1:B150)"), "{}", summary);
+        for row in 1..=150 {
+            assert_eq!(grid.get_cell(&format!("A{}", row)).unwrap(), row as f64);
+        }
+        let last_phone = CellRef::parse("B150").unwrap();
+        assert_eq!(grid.get_text(&last_phone).map(str::len), Some(10));
+    }
+
+    #[test]
+    fn test_generate_data_columns_follow_request_order() {
+        let mut grid = QuantumGrid::new();
+        let registry = OperationRegistry::new();
+        let args = ["3".to_string(), "phone, then name, then city".to_string()];
+        let summary = registry.execute("GENERATE_DATA", &mut grid, &args).unwrap();
+        assert!(summary.starts_with("Generated 3 rows (stored in A1:C3)"), "{}", summary);
+
+        let text = |reference: &str| grid.get_text(&CellRef::parse(reference).unwrap()).map(str::to_string);
+        for row in 1..=3 {
+            let phone = text(&format!("A{}", row)).unwrap();
+            assert!(crate::ai::data_generator::validate_nepal_phone(&phone), "{}", phone);
+            assert!(text(&format!("B{}", row)).unwrap().contains(' '));
+            assert!(text(&format!("C{}", row)).is_some());
+        }
+        assert_eq!(text("D1"), None);
+        assert!(grid.get_value(&CellRef::parse("D1").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_sequence_spills_down_a_column() {
+        let mut grid = QuantumGrid::new();
+        let registry = OperationRegistry::new();
+        let a1 = CellRef::parse("A1").unwrap();
+
+        let result = registry.spill("SEQUENCE", &mut grid, &["3".to_string()], &a1).unwrap();
+        assert_eq!(result, "SEQUENCE spilled 3x1 into A1:A3");
+        assert_eq!(grid.get_cell("A3").unwrap(), 3.0);
+
+        // E1 is in the way of C1:E1, so nothing is written
+        grid.set_cell("E1", 9.0).unwrap();
+        let c1 = CellRef::parse("C1").unwrap();
+        let args = ["1".to_string(), "3".to_string()];
+        let err = registry.spill("SEQUENCE", &mut grid, &args, &c1).unwrap_err();
+        assert!(err.starts_with("#SPILL!"), "{}", err);
+        assert!(grid.get_value(&c1).is_none());
+        assert_eq!(grid.get_cell("E1").unwrap(), 9.0);
+
+        // Sizes past the grid limits are refused before anything is allocated
+        let args = ["100000".to_string(), "100000".to_string()];
+        let err = registry.spill("SEQUENCE", &mut grid, &args, &a1).unwrap_err();
+        assert!(err.contains("larger than the grid limit"), "{}", err);
+        grid.set_limits(crate::grid::GridLimits { max_rows: 10, max_cols: 10 });
+        assert!(registry.spill("SEQUENCE", &mut grid, &["11".to_string()], &a1).is_err());
+    }
+
+    #[test]
+    fn test_natural_stores_into_target_cell() {
+        let mut grid = QuantumGrid::new();
+        for (cell, value) in [("A1", 1.0), ("A2", 2.0), ("A3", 3.0), ("B1", 10.0)] {
+            grid.set_cell(cell, value).unwrap();
+        }
+        let registry = OperationRegistry::new();
+        let natural = |grid: &mut QuantumGrid, text: &str| {
+            registry.execute("NATURAL", grid, &[text.to_string()])
+        };
+
+        let result = natural(&mut grid, "put the sum of A1 to A3 in C1").unwrap();
+        assert_eq!(result, "Translated to: =SUM(A1:A3) = 6 (stored in C1)");
+        assert_eq!(grid.get_cell("C1").unwrap(), 6.0);
+
+        natural(&mut grid, "add a1 and b1 into d2").unwrap();
+        assert_eq!(grid.get_cell("D2").unwrap(), 11.0);
+
+        // Without a target nothing is written
+        assert_eq!(natural(&mut grid, "add A1 and B1").unwrap(), "Translated to: =A1+B1 = 11");
+        assert!(grid.get_value(&CellRef::parse("E1").unwrap()).is_none());
+        assert!(natural(&mut grid, "hello world in C1").is_err());
+    }
+
+    #[test]
+    fn test_cumsum_writes_running_total_beside_column() {
+        let mut grid = QuantumGrid::new();
+        for (row, value) in [5.0, 10.0, 15.0, 20.0].iter().enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *value).unwrap();
+        }
+        let registry = OperationRegistry::new();
+
+        let result = registry.execute("CUMSUM", &mut grid, &["A1:A4".to_string()]).unwrap();
+        assert_eq!(result, "Running total written to B1:B4");
+        let totals: Vec<f64> = (1..=4).map(|row| grid.get_cell(&format!("B{}", row)).unwrap()).collect();
+        assert_eq!(totals, vec![5.0, 15.0, 30.0, 50.0]);
+
+        assert_eq!(registry.execute("PRODUCT", &mut grid, &["A1:A2".to_string()]).unwrap(), "50");
+        assert!(registry.execute("CUMSUM", &mut grid, &["A1:A4".to_string()]).unwrap_err().contains("#SPILL!"));
+    }
+
+    #[test]
+    fn test_movavg_writes_three_value_window() {
+        let values = [1.0, 2.0, 6.0, 4.0, 8.0];
+        assert_eq!(crate::compute::moving_average(&values, 3), vec![3.0, 4.0, 6.0]);
+        assert!(crate::compute::moving_average(&values, 0).is_empty());
+        assert!(crate::compute::moving_average(&values, 6).is_empty());
+
+        let mut grid = QuantumGrid::new();
+        for (row, value) in values.iter().enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *value).unwrap();
+        }
+        let registry = OperationRegistry::new();
+
+        let args = ["A1:A5".to_string(), "3".to_string(), "C1".to_string()];
+        assert_eq!(registry.execute("MOVAVG", &mut grid, &args).unwrap(), "Moving average (window 3) written to C1:C5");
+        let averages: Vec<f64> = (3..=5).map(|row| grid.get_cell(&format!("C{}", row)).unwrap()).collect();
+        assert_eq!(averages, vec![3.0, 4.0, 6.0]);
+
+        for window in ["0", "6"] {
+            let args = ["A1:A5".to_string(), window.to_string()];
+            assert!(registry.execute("MOVAVG", &mut grid, &args).is_err());
+        }
+    }
+
+    #[test]
+    fn test_movavg_leaves_windows_over_a_blank_empty() {
+        let mut grid = QuantumGrid::new();
+        for (row, value) in [1.0, 2.0, 4.0, 6.0, 8.0].iter().enumerate() {
+            // Row 3 stays blank; the values below it keep their rows
+            let row = if row < 2 { row + 1 } else { row + 2 };
+            grid.set_cell(&format!("A{}", row), *value).unwrap();
+        }
+        let registry = OperationRegistry::new();
+
+        let args = ["A1:A6".to_string(), "2".to_string(), "C1".to_string()];
+        assert_eq!(registry.execute("MOVAVG", &mut grid, &args).unwrap(), "Moving average (window 2) written to C1:C6");
+        let averages: Vec<Option<f64>> = (1..=6).map(|row| grid.get_value(&CellRef::new(row, 3))).collect();
+        assert_eq!(averages, vec![None, Some(1.5), None, None, Some(5.0), Some(7.0)]);
+
+        // No window of three fits between the gaps
+        let args = ["A1:A4".to_string(), "3".to_string(), "E1".to_string()];
+        assert!(registry.execute("MOVAVG", &mut grid, &args).unwrap_err().contains("No 3 numbers in a row"));
+    }
+
+    #[test]
+    fn test_histogram_counts_every_value() {
+        let values = [1.0, 2.0, 2.5, 3.0, 4.0, 5.0, 9.0, 10.0];
+        let bins = crate::compute::histogram(&values, 3);
+        assert_eq!(bins, vec![(1.0, 4.0, 4), (4.0, 7.0, 2), (7.0, 10.0, 2)]);
+        assert_eq!(bins.iter().map(|(_, _, count)| count).sum::<usize>(), values.len());
+
+        let flat = crate::compute::histogram(&[3.0; 5], 4);
+        assert_eq!(flat, vec![(3.0, 3.0, 5)]);
+
+        let mut grid = QuantumGrid::new();
+        for (row, value) in values.iter().enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *value).unwrap();
+        }
+        let registry = OperationRegistry::new();
+        let result = registry.execute("HISTOGRAM", &mut grid, &["A1:A8".to_string(), "3".to_string()]).unwrap();
+        assert_eq!(result, "Histogram of A1:A8 (8 values):\n1 to 4: 4\n4 to 7: 2\n7 to 10: 2");
+        assert!(registry.execute("HISTOGRAM", &mut grid, &["A1:A8".to_string(), "0".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_unique_keeps_first_of_each_value() {
+        let mut grid = QuantumGrid::new();
+        grid.set_cell("A1", 3.0).unwrap();
+        grid.set_text("A2", "Pune").unwrap();
+        grid.set_cell("A3", 3.0).unwrap();
+        grid.set_text("A4", "Delhi").unwrap();
+        grid.set_text("A5", "pune").unwrap();
+        grid.set_cell("A6", 1.5).unwrap();
+        let registry = OperationRegistry::new();
+
+        let result = registry.execute("UNIQUE", &mut grid, &["A1:A8".to_string()]).unwrap();
+        assert_eq!(result, "3\nPune\nDelhi\n1.5");
+
+        let args = ["A1:A6".to_string(), "C1".to_string()];
+        assert_eq!(registry.execute("UNIQUE", &mut grid, &args).unwrap(), "4 unique values written to C1:C4");
+        assert_eq!(grid.get_text(&CellRef::parse("C3").unwrap()), Some("Delhi"));
+        assert_eq!(grid.get_cell("C4").unwrap(), 1.5);
+    }
+
+    #[test]
+    fn test_stats_reports_median_and_stddev() {
+        let mut grid = QuantumGrid::new();
+        for (row, value) in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0].iter().enumerate() {
+            grid.set_cell(&format!("A{}", row + 1), *value).unwrap();
+        }
+        grid.set_text("A9", "n/a").unwrap();
+        let registry = OperationRegistry::new();
+
+        let result = registry.execute("STATS", &mut grid, &["A1:A10".to_string()]).unwrap();
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(lines[..8], [
+            "Statistics for A1:A10:", "count: 8", "sum: 40", "mean: 5",
+            "median: 4.5", "mode: 4", "min: 2", "max: 9",
+        ]);
+        let std_dev: f64 = lines[8].strip_prefix("stddev: ").unwrap().parse().unwrap();
+        assert!((std_dev - (32.0f64 / 7.0).sqrt()).abs() < 1e-12);
+
+        assert_eq!(registry.execute("STATS", &mut grid, &["B1:B3".to_string()]), Err("No numbers in B1:B3".to_string()));
+
+        // Ties go to the value seen first; nothing repeating means no mode
+        let mode = |data: &[f64]| crate::compute::calculate_full_stats(data).unwrap().mode;
+        assert_eq!(mode(&[3.0, 1.0, 1.0, 3.0]), Some(3.0));
+        assert_eq!(mode(&[0.0, -0.0, 2.0]), Some(0.0));
+        assert_eq!(mode(&[1.0, 2.0, 3.0]), None);
+    }
+}
@@ -0,0 +1,264 @@
+//! Typed column payloads: a column holds the narrowest type its values fit
+
+use std::borrow::Cow;
+
+/// Value type of a dense column, narrowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColumnType {
+    Bool,
+    I64,
+    F64,
+    Text,
+}
+
+impl ColumnType {
+    /// Narrowest numeric type that holds `value` exactly. -0.0 stays F64 to keep its sign.
+    pub fn for_value(value: f64) -> Self {
+        let negative_zero = value == 0.0 && value.is_sign_negative();
+        if (value == 0.0 && !negative_zero) || value == 1.0 {
+            ColumnType::Bool
+        } else if value.fract() == 0.0 && value.abs() < i64::MAX as f64 && !negative_zero {
+            ColumnType::I64
+        } else {
+            ColumnType::F64
+        }
+    }
+}
+
+/// Growable bit array, one bit per row
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BitVec {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitVec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<bool> {
+        (index < self.len).then(|| self.words[index / 64] >> (index % 64) & 1 == 1)
+    }
+
+    /// Overwrite a bit; `index` must be below `len()`
+    pub fn set(&mut self, index: usize, bit: bool) {
+        assert!(index < self.len, "bit {} out of range for length {}", index, self.len);
+        let mask = 1 << (index % 64);
+        if bit {
+            self.words[index / 64] |= mask;
+        } else {
+            self.words[index / 64] &= !mask;
+        }
+    }
+
+    pub fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(64) {
+            self.words.push(0);
+        }
+        self.len += 1;
+        self.set(self.len - 1, bit);
+    }
+
+    /// Insert a bit, shifting later bits up by one
+    pub fn insert(&mut self, index: usize, bit: bool) {
+        self.push(false);
+        for i in (index + 1..self.len).rev() {
+            let previous = self.get(i - 1).unwrap_or(false);
+            self.set(i, previous);
+        }
+        self.set(index, bit);
+    }
+
+    /// Remove a bit, shifting later bits down by one
+    pub fn remove(&mut self, index: usize) -> bool {
+        let removed = self.get(index).unwrap_or(false);
+        for i in index..self.len - 1 {
+            let next = self.get(i + 1).unwrap_or(false);
+            self.set(i, next);
+        }
+        // Clear the vacated bit so count_ones() doesn't see it
+        self.set(self.len - 1, false);
+        self.len -= 1;
+        if self.len.is_multiple_of(64) {
+            self.words.pop();
+        }
+        removed
+    }
+
+    /// Number of set bits
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|index| self.get(index).unwrap_or(false))
+    }
+
+    /// Heap bytes reserved for the bits
+    pub fn memory_used(&self) -> usize {
+        self.words.capacity() * std::mem::size_of::<u64>()
+    }
+}
+
+/// Dense values of one column in their narrowest type
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnData {
+    F64(Vec<f64>),
+    I64(Vec<i64>),
+    Bool(BitVec),
+    Text(Vec<String>),
+}
+
+impl ColumnData {
+    pub fn new(column_type: ColumnType) -> Self {
+        match column_type {
+            ColumnType::F64 => ColumnData::F64(Vec::new()),
+            ColumnType::I64 => ColumnData::I64(Vec::new()),
+            ColumnType::Bool => ColumnData::Bool(BitVec::new()),
+            ColumnType::Text => ColumnData::Text(Vec::new()),
+        }
+    }
+
+    pub fn column_type(&self) -> ColumnType {
+        match self {
+            ColumnData::F64(_) => ColumnType::F64,
+            ColumnData::I64(_) => ColumnType::I64,
+            ColumnData::Bool(_) => ColumnType::Bool,
+            ColumnData::Text(_) => ColumnType::Text,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnData::F64(values) => values.len(),
+            ColumnData::I64(values) => values.len(),
+            ColumnData::Bool(bits) => bits.len(),
+            ColumnData::Text(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Numeric value of a row: true is 1, and text only if it parses as a number
+    pub fn get(&self, row: usize) -> Option<f64> {
+        match self {
+            ColumnData::F64(values) => values.get(row).copied(),
+            ColumnData::I64(values) => values.get(row).map(|&n| n as f64),
+            ColumnData::Bool(bits) => bits.get(row).map(|bit| bit as u8 as f64),
+            ColumnData::Text(values) => values.get(row).and_then(|text| text.trim().parse().ok()),
+        }
+    }
+
+    /// Text of a row; numbers are formatted like cell values (1, not 1.0)
+    pub fn text(&self, row: usize) -> Option<Cow<'_, str>> {
+        match self {
+            ColumnData::Text(values) => values.get(row).map(|text| Cow::Borrowed(text.as_str())),
+            _ => self.get(row).map(|value| Cow::Owned(value.to_string())),
+        }
+    }
+
+    /// Heap bytes reserved for the values
+    pub fn memory_used(&self) -> usize {
+        match self {
+            ColumnData::F64(values) => values.capacity() * std::mem::size_of::<f64>(),
+            ColumnData::I64(values) => values.capacity() * std::mem::size_of::<i64>(),
+            ColumnData::Bool(bits) => bits.memory_used(),
+            ColumnData::Text(values) => {
+                values.capacity() * std::mem::size_of::<String>()
+                    + values.iter().map(String::capacity).sum::<usize>()
+            }
+        }
+    }
+
+    /// Convert to a wider type so it can also hold `column_type` values.
+    /// Never narrows: a column only ever widens, so no value is lost.
+    pub fn widen(&mut self, column_type: ColumnType) {
+        if column_type <= self.column_type() {
+            return;
+        }
+        let len = self.len();
+        *self = match column_type {
+            ColumnType::Bool => unreachable!("Bool is the narrowest type"),
+            ColumnType::I64 => ColumnData::I64((0..len).map(|row| self.get(row).unwrap_or(0.0) as i64).collect()),
+            ColumnType::F64 => ColumnData::F64((0..len).map(|row| self.get(row).unwrap_or(0.0)).collect()),
+            ColumnType::Text => {
+                ColumnData::Text((0..len).map(|row| self.text(row).unwrap_or_default().into_owned()).collect())
+            }
+        };
+    }
+
+    /// Append a number, widening first if it doesn't fit
+    pub fn push(&mut self, value: f64) {
+        self.widen(ColumnType::for_value(value));
+        match self {
+            ColumnData::F64(values) => values.push(value),
+            ColumnData::I64(values) => values.push(value as i64),
+            ColumnData::Bool(bits) => bits.push(value == 1.0),
+            ColumnData::Text(values) => values.push(value.to_string()),
+        }
+    }
+
+    /// Overwrite row `row` (below `len()`), widening first if needed
+    pub fn set(&mut self, row: usize, value: f64) {
+        self.widen(ColumnType::for_value(value));
+        match self {
+            ColumnData::F64(values) => values[row] = value,
+            ColumnData::I64(values) => values[row] = value as i64,
+            ColumnData::Bool(bits) => bits.set(row, value == 1.0),
+            ColumnData::Text(values) => values[row] = value.to_string(),
+        }
+    }
+
+    /// Insert a number at `row`, shifting later rows down
+    pub fn insert(&mut self, row: usize, value: f64) {
+        self.widen(ColumnType::for_value(value));
+        match self {
+            ColumnData::F64(values) => values.insert(row, value),
+            ColumnData::I64(values) => values.insert(row, value as i64),
+            ColumnData::Bool(bits) => bits.insert(row, value == 1.0),
+            ColumnData::Text(values) => values.insert(row, value.to_string()),
+        }
+    }
+
+    /// Remove row `row`, returning its numeric value (None for non-numeric text)
+    pub fn remove(&mut self, row: usize) -> Option<f64> {
+        let value = self.get(row);
+        match self {
+            ColumnData::F64(values) => {
+                values.remove(row);
+            }
+            ColumnData::I64(values) => {
+                values.remove(row);
+            }
+            ColumnData::Bool(bits) => {
+                bits.remove(row);
+            }
+            ColumnData::Text(values) => {
+                values.remove(row);
+            }
+        }
+        value
+    }
+
+    /// Store text at `row`, padding any gap with empty strings; the column becomes Text
+    pub fn set_text(&mut self, row: usize, text: &str) {
+        self.widen(ColumnType::Text);
+        if let ColumnData::Text(values) = self {
+            if values.len() <= row {
+                values.resize(row + 1, String::new());
+            }
+            values[row] = text.to_string();
+        }
+    }
+}
@@ -1,35 +1,388 @@
 //! QuantumColumn - Beats Excel's memory usage
 
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use super::column_data::{BitVec, ColumnData, ColumnType};
 use super::ColumnStats;
 
+/// A dense column switches to sparse storage when one write would gap-fill more rows than this
+pub const SPARSE_GAP_ROWS: usize = 4096;
+
+/// How a column keeps its values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageMode {
+    /// Every row up to the last one in the narrowest `ColumnType`; gaps are stored as zeros
+    /// and flagged as empty
+    Dense,
+    /// Only written rows, keyed by row; gaps read as 0 but take no memory
+    Sparse,
+}
+
+enum Storage {
+    Dense(ColumnData),
+    Sparse { values: BTreeMap<u32, f64>, len: usize },
+}
+
 /// QuantumColumn - Our efficient column storage
 pub struct QuantumColumn {
     _name: String,  // Underscore indicates intentionally unused
-    pub(crate) data: Vec<f64>,  // Changed from Option<f64> to f64 for simplicity
+    data: Storage,
     stats: ColumnStats,
+    gaps: BitVec, // Dense rows never written; rows past its end are written ones
 }
 
 impl QuantumColumn {
-    /// Create a new column
+    /// Create a new column. Its type is inferred: it starts as Bool and
+    /// widens to I64, then F64, as values stop fitting.
     pub fn new(name: &str) -> Self {
+        Self::with_type(name, ColumnType::Bool)
+    }
+    
+    /// Create a column declared as `column_type`. It still widens when a value
+    /// doesn't fit, so no write is ever lost.
+    pub fn with_type(name: &str, column_type: ColumnType) -> Self {
         Self {
             _name: name.to_string(),
-            data: Vec::new(),
+            data: Storage::Dense(ColumnData::new(column_type)),
             stats: ColumnStats::new(),
+            gaps: BitVec::new(),
+        }
+    }
+    
+    /// Create a column that only stores written rows
+    pub fn new_sparse(name: &str) -> Self {
+        let mut column = Self::new(name);
+        column.set_storage_mode(StorageMode::Sparse);
+        column
+    }
+    
+    /// Current storage mode
+    pub fn storage_mode(&self) -> StorageMode {
+        match self.data {
+            Storage::Dense(_) => StorageMode::Dense,
+            Storage::Sparse { .. } => StorageMode::Sparse,
+        }
+    }
+    
+    /// Type of the values: the dense column's type, or F64 for sparse storage
+    pub fn column_type(&self) -> ColumnType {
+        match &self.data {
+            Storage::Dense(data) => data.column_type(),
+            Storage::Sparse { .. } => ColumnType::F64,
+        }
+    }
+    
+    /// Convert between dense and sparse storage, keeping every row's value and
+    /// which rows are empty. Text columns stay dense, since sparse storage only
+    /// holds numbers.
+    pub fn set_storage_mode(&mut self, mode: StorageMode) {
+        if mode == self.storage_mode() || self.column_type() == ColumnType::Text {
+            return;
+        }
+        self.data = match &self.data {
+            Storage::Dense(_) => Storage::Sparse {
+                values: self.entries().map(|(row, value)| (row as u32, value)).collect(),
+                len: self.len(),
+            },
+            Storage::Sparse { values, len } => {
+                let mut data = ColumnData::new(ColumnType::Bool);
+                self.gaps = BitVec::new();
+                for row in 0..*len as u32 {
+                    data.push(values.get(&row).copied().unwrap_or(0.0));
+                    self.gaps.push(!values.contains_key(&row));
+                }
+                Storage::Dense(data)
+            }
+        };
+        if self.storage_mode() == StorageMode::Sparse {
+            self.gaps = BitVec::new();
+        }
+        self.recompute_stats();
+    }
+
+    /// True for a 0-based row that was never written (or was cleared), and past the end
+    pub fn is_null(&self, row: usize) -> bool {
+        match &self.data {
+            Storage::Dense(data) => row >= data.len() || self.gaps.get(row).unwrap_or(false),
+            Storage::Sparse { values, .. } => u32::try_from(row).map_or(true, |row| !values.contains_key(&row)),
+        }
+    }
+
+    /// Flag a dense row as a gap or as written, keeping `null_count` in step
+    fn mark_gap(&mut self, row: usize, gap: bool) {
+        if !gap && row >= self.gaps.len() {
+            return;
+        }
+        while self.gaps.len() <= row {
+            self.gaps.push(false);
+        }
+        if self.gaps.get(row) != Some(gap) {
+            self.gaps.set(row, gap);
+            if gap {
+                self.stats.null_count += 1;
+            } else {
+                self.stats.null_count -= 1;
+            }
         }
     }
     
     /// Add a value to the column
     pub fn push(&mut self, value: f64) {
-        self.data.push(value);
+        match &mut self.data {
+            Storage::Dense(data) => data.push(value),
+            Storage::Sparse { values, len } => {
+                values.insert(*len as u32, value);
+                *len += 1;
+            }
+        }
         
         // Update statistics
-        self.stats.count += 1;
+        include(&mut self.stats, value);
+    }
+
+    /// Write a value at a 0-based row. A dense column fills any gap with zeros,
+    /// flagged as empty, switching to sparse storage first if the gap is over
+    /// `SPARSE_GAP_ROWS`.
+    pub fn set(&mut self, row: usize, value: f64) {
+        if row > self.len() + SPARSE_GAP_ROWS {
+            self.set_storage_mode(StorageMode::Sparse);
+        }
+
+        if let Storage::Dense(data) = &mut self.data {
+            let start = data.len();
+            while data.len() < row {
+                data.push(0.0);
+            }
+            for gap in start..row {
+                self.mark_gap(gap, true);
+            }
+        }
+        if row == self.len() {
+            self.push(value);
+            return;
+        }
+
+        let filling_gap = self.is_null(row);
+        let old = match &mut self.data {
+            Storage::Dense(data) => {
+                let old = data.get(row);
+                data.set(row, value);
+                old
+            }
+            Storage::Sparse { values, len } => {
+                *len = (*len).max(row + 1);
+                let old = values.insert(row as u32, value);
+                self.stats.null_count = *len - values.len();
+                old
+            }
+        };
+        if filling_gap {
+            self.mark_gap(row, false);
+        }
+        let old = match old {
+            Some(old) if !filling_gap => old,
+            // A gap, a new sparse row, or non-numeric text the stats never saw
+            _ => {
+                include(&mut self.stats, value);
+                return;
+            }
+        };
+
+        // Overwriting the current min/max can move it anywhere, so rescan;
+        // otherwise the incremental update stays exact
+        if Some(old) == self.stats.min || Some(old) == self.stats.max {
+            self.recompute_stats();
+            return;
+        }
+
         self.stats.min = Some(self.stats.min.map(|m| m.min(value)).unwrap_or(value));
         self.stats.max = Some(self.stats.max.map(|m| m.max(value)).unwrap_or(value));
-        self.stats.sum = Some(self.stats.sum.unwrap_or(0.0) + value);
+        self.stats.sum = Some(self.stats.sum.unwrap_or(0.0) - old + value);
     }
-    
+
+    /// Insert a value at a 0-based row, shifting later values down
+    pub fn insert(&mut self, row: usize, value: f64) {
+        if row >= self.len() {
+            self.set(row, value);
+            return;
+        }
+
+        self.insert_gap(row);
+        self.set(row, value);
+    }
+
+    /// Insert an empty row at a 0-based row, shifting later values down
+    pub fn insert_gap(&mut self, row: usize) {
+        if row >= self.len() {
+            return;
+        }
+
+        match &mut self.data {
+            Storage::Dense(data) => {
+                data.insert(row, 0.0);
+                if data.column_type() == ColumnType::Text {
+                    data.set_text(row, "");
+                }
+                if row < self.gaps.len() {
+                    self.gaps.insert(row, false);
+                }
+            }
+            Storage::Sparse { values, len } => {
+                let shifted = values.split_off(&(row as u32));
+                values.extend(shifted.into_iter().map(|(r, v)| (r + 1, v)));
+                *len += 1;
+            }
+        }
+        self.mark_gap(row, true);
+        if let Storage::Sparse { values, len } = &self.data {
+            self.stats.null_count = *len - values.len();
+        }
+    }
+
+    /// Remove the value at a 0-based row, shifting later values up.
+    /// None past the end of the column, for an empty row, or for non-numeric text.
+    pub fn remove(&mut self, row: usize) -> Option<f64> {
+        if row >= self.len() {
+            return None;
+        }
+
+        let was_null = self.is_null(row);
+        if row < self.gaps.len() {
+            self.gaps.remove(row);
+        }
+        let removed = match &mut self.data {
+            Storage::Dense(data) => data.remove(row),
+            Storage::Sparse { values, len } => {
+                let mut shifted = values.split_off(&(row as u32));
+                let removed = shifted.remove(&(row as u32)).unwrap_or(0.0);
+                values.extend(shifted.into_iter().map(|(r, v)| (r - 1, v)));
+                *len -= 1;
+                Some(removed)
+            }
+        };
+        // min/max can't be decremented, so rebuild them from the remaining data
+        self.recompute_stats();
+        removed.filter(|_| !was_null)
+    }
+
+    /// Store text at a 0-based row, turning the column into a Text column.
+    /// Its numbers are kept as their text; text that parses as a number still
+    /// counts toward the stats.
+    pub fn set_text(&mut self, row: usize, text: &str) {
+        self.set_storage_mode(StorageMode::Dense);
+        let start = self.len();
+        if let Storage::Dense(data) = &mut self.data {
+            data.set_text(row, text);
+        }
+        for gap in start..row {
+            self.mark_gap(gap, true);
+        }
+        self.mark_gap(row, false);
+        self.recompute_stats();
+    }
+
+    /// Text at a 0-based row; numbers are formatted like cell values (1, not 1.0)
+    pub fn text(&self, row: usize) -> Option<Cow<'_, str>> {
+        match &self.data {
+            Storage::Dense(data) => data.text(row),
+            Storage::Sparse { .. } => self.get(row).map(|value| Cow::Owned(value.to_string())),
+        }
+    }
+
+    /// Clear a 0-based row without shifting others, leaving it empty. Empty rows at
+    /// the end are dropped, so the column ends at its last written row.
+    pub fn unset(&mut self, row: usize) {
+        if self.is_null(row) {
+            return;
+        }
+        match &mut self.data {
+            Storage::Dense(data) => {
+                if data.column_type() == ColumnType::Text {
+                    data.set_text(row, "");
+                } else {
+                    data.set(row, 0.0);
+                }
+                self.mark_gap(row, true);
+                while !self.is_empty() && self.is_null(self.len() - 1) {
+                    let last = self.len() - 1;
+                    if let Storage::Dense(data) = &mut self.data {
+                        data.remove(last);
+                    }
+                    if last < self.gaps.len() {
+                        self.gaps.remove(last);
+                    }
+                }
+            }
+            Storage::Sparse { values, len } => {
+                values.remove(&(row as u32));
+                *len = values.last_key_value().map_or(0, |(&last, _)| last as usize + 1);
+            }
+        }
+        self.recompute_stats();
+    }
+
+    /// Written numeric rows as (0-based row, value), in row order; gaps are skipped
+    pub fn entries(&self) -> Box<dyn Iterator<Item = (usize, f64)> + '_> {
+        match &self.data {
+            Storage::Dense(data) => Box::new((0..data.len())
+                .filter(|&row| !self.gaps.get(row).unwrap_or(false))
+                .filter_map(|row| Some((row, data.get(row)?)))),
+            Storage::Sparse { values, .. } => Box::new(values.iter().map(|(&row, &value)| (row as usize, value))),
+        }
+    }
+
+    /// Values of the written numeric rows, in row order (`entries` without the rows)
+    pub fn iter(&self) -> impl Iterator<Item = f64> + '_ {
+        self.entries().map(|(_, value)| value)
+    }
+
+    /// Keep only the numbers for which `keep` returns true, like `Vec::retain`.
+    /// Remaining rows shift up to close the holes, so values change rows; empty
+    /// rows are dropped too, while text that isn't a number is kept. Stats are
+    /// rebuilt afterwards.
+    pub fn retain(&mut self, mut keep: impl FnMut(f64) -> bool) {
+        match &mut self.data {
+            Storage::Dense(data) => {
+                let mut kept = ColumnData::new(data.column_type());
+                for row in 0..data.len() {
+                    if self.gaps.get(row).unwrap_or(false) {
+                        continue;
+                    }
+                    match data.get(row) {
+                        Some(value) if !keep(value) => {}
+                        Some(value) if data.column_type() != ColumnType::Text => kept.push(value),
+                        _ => {
+                            let text = data.text(row).unwrap_or_default();
+                            kept.set_text(kept.len(), &text);
+                        }
+                    }
+                }
+                *data = kept;
+                self.gaps = BitVec::new();
+            }
+            Storage::Sparse { values, len } => {
+                let kept: Vec<f64> = values.values().copied().filter(|&value| keep(value)).collect();
+                *len = kept.len();
+                *values = kept.into_iter().enumerate().map(|(row, value)| (row as u32, value)).collect();
+            }
+        }
+        self.recompute_stats();
+    }
+
+    /// Rebuild statistics from scratch
+    fn recompute_stats(&mut self) {
+        self.stats = ColumnStats::new();
+        let values: Vec<f64> = self.entries().map(|(_, value)| value).collect();
+        for value in values {
+            include(&mut self.stats, value);
+        }
+        self.stats.null_count = match &self.data {
+            Storage::Dense(_) => self.gaps.count_ones(),
+            Storage::Sparse { values, len } => len - values.len(),
+        };
+    }
+
     /// Sum all values in the column
     pub fn sum(&self) -> f64 {
         self.stats.sum.unwrap_or(0.0)
@@ -44,15 +397,47 @@ impl QuantumColumn {
         }
     }
     
-    /// Count of values
+    /// Value at a 0-based row, or None past the end of the column.
+    /// Gaps read as 0 in both storage modes; non-numeric text reads as None.
+    pub fn get(&self, row: usize) -> Option<f64> {
+        match &self.data {
+            Storage::Dense(data) => data.get(row),
+            Storage::Sparse { .. } if row >= self.len() => None,
+            Storage::Sparse { values, .. } => Some(values.get(&(row as u32)).copied().unwrap_or(0.0)),
+        }
+    }
+    
+    /// Number of rows up to the last written one (including gaps)
+    pub fn len(&self) -> usize {
+        match &self.data {
+            Storage::Dense(data) => data.len(),
+            Storage::Sparse { len, .. } => *len,
+        }
+    }
+    
+    /// True if nothing has been stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Count of written numbers; empty rows and non-numeric text don't count
     pub fn count(&self) -> usize {
         self.stats.count
     }
     
-    /// Get memory usage in bytes
+    /// Get memory usage in bytes. Sparse columns are estimated from B-tree
+    /// nodes, which hold up to 11 entries and are at least half full.
     pub fn memory_used(&self) -> usize {
-        std::mem::size_of::<Self>() + 
-        (self.data.capacity() * std::mem::size_of::<f64>())
+        let data = match &self.data {
+            Storage::Dense(data) => data.memory_used(),
+            Storage::Sparse { values, .. } => {
+                const NODE_CAPACITY: usize = 11;
+                let entry = std::mem::size_of::<u32>() + std::mem::size_of::<f64>();
+                let node = NODE_CAPACITY * entry + 2 * std::mem::size_of::<usize>();
+                values.len().div_ceil(NODE_CAPACITY / 2) * node
+            }
+        };
+        std::mem::size_of::<Self>() + data + self.gaps.memory_used()
     }
     
     /// Get memory usage per value
@@ -64,6 +449,32 @@ impl QuantumColumn {
         }
     }
     
+    /// Column statistics (kept correct across overwrites and removals)
+    pub fn stats(&self) -> &ColumnStats {
+        &self.stats
+    }
+    
+    /// (sum, average, min, max, count) like `compute::calculate_stats`, in O(1): the
+    /// stats behind it are updated on every write, so nothing is rescanned. Covers
+    /// written rows only: empty rows and rows of non-numeric text are skipped.
+    pub fn stats_snapshot(&self) -> (f64, f64, f64, f64, f64) {
+        if self.stats.count == 0 {
+            return (0.0, 0.0, 0.0, 0.0, 0.0);
+        }
+        (
+            self.sum(),
+            self.average(),
+            self.stats.min.unwrap_or(0.0),
+            self.stats.max.unwrap_or(0.0),
+            self.stats.count as f64,
+        )
+    }
+
+    /// Number of empty rows below `len()`
+    pub fn null_count(&self) -> usize {
+        self.stats.null_count
+    }
+
     /// Minimum value
     pub fn min(&self) -> Option<f64> {
         self.stats.min
@@ -102,8 +513,233 @@ impl QuantumColumn {
         self.max().unwrap_or(0.0)
     }
     
-    /// Get data slice
-    pub fn data(&self) -> &[f64] {
-        &self.data
+    /// Every row's value, gaps (and non-numeric text) as zeros. Borrowed for F64
+    /// columns; other columns have to build it, so prefer `entries` there.
+    pub fn data(&self) -> Cow<'_, [f64]> {
+        match &self.data {
+            Storage::Dense(ColumnData::F64(data)) => Cow::Borrowed(data),
+            Storage::Dense(data) => Cow::Owned((0..data.len()).map(|row| data.get(row).unwrap_or(0.0)).collect()),
+            Storage::Sparse { values, len } => {
+                let mut data = vec![0.0; *len];
+                for (&row, &value) in values {
+                    data[row as usize] = value;
+                }
+                Cow::Owned(data)
+            }
+        }
+    }
+}
+
+/// Fold one more value into running statistics
+fn include(stats: &mut ColumnStats, value: f64) {
+    stats.count += 1;
+    stats.min = Some(stats.min.map(|m| m.min(value)).unwrap_or(value));
+    stats.max = Some(stats.max.map(|m| m.max(value)).unwrap_or(value));
+    stats.sum = Some(stats.sum.unwrap_or(0.0) + value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overwriting_min_and_max_updates_stats() {
+        let mut column = QuantumColumn::new("Test");
+        for value in [5.0, 1.0, 9.0, 3.0] {
+            column.push(value);
+        }
+
+        // Overwrite the minimum: the next smallest value takes over
+        column.set(1, 7.0);
+        assert_eq!(column.min(), Some(3.0));
+        assert_eq!(column.sum(), 24.0);
+
+        // Overwrite the maximum with a smaller value
+        column.set(2, 4.0);
+        assert_eq!(column.max(), Some(7.0));
+
+        // Remove the new minimum
+        column.remove(3);
+        assert_eq!(column.min(), Some(4.0));
+        assert_eq!(column.stats().count, 3);
+        assert_eq!(column.stats().sum, Some(16.0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_get_out_of_range_is_none() {
+        let mut column = QuantumColumn::new("Test");
+        assert!(column.is_empty());
+        assert_eq!(column.get(0), None);
+
+        column.set(2, 42.0);
+        assert_eq!(column.len(), 3);
+        assert_eq!(column.get(2), Some(42.0));
+        assert_eq!(column.get(1), Some(0.0)); // Gap-filled
+        assert_eq!(column.get(3), None);
+        assert_eq!(column.get(usize::MAX), None);
+    }
+
+    #[test]
+    fn test_far_apart_writes_stay_small() {
+        let mut column = QuantumColumn::new("Test");
+        column.set(0, 1.0);
+        column.set(999_999, 2.0);
+        column.set(500_000, 3.0);
+
+        // The million-row gap switched the column to sparse storage
+        assert_eq!(column.storage_mode(), StorageMode::Sparse);
+        assert_eq!(column.len(), 1_000_000);
+        assert_eq!(column.count(), 3);
+        assert_eq!(column.sum(), 6.0);
+        assert_eq!(column.get(500_000), Some(3.0));
+        assert_eq!(column.get(7), Some(0.0));
+        assert_eq!(column.get(1_000_000), None);
+        assert!(column.memory_used() < 1024, "{} bytes", column.memory_used());
+
+        column.insert(1, 4.0);
+        assert_eq!(column.get(1_000_000), Some(2.0));
+        column.remove(0);
+        assert_eq!(column.entries().collect::<Vec<_>>(), [(0, 4.0), (500_000, 3.0), (999_999, 2.0)]);
+        assert_eq!(column.min(), Some(2.0));
+
+        column.unset(999_999);
+        assert_eq!(column.len(), 500_001);
+        assert_eq!(column.count(), 2);
+    }
+
+    #[test]
+    fn test_bool_column_is_compact() {
+        let mut flags = QuantumColumn::new("Flags");
+        let mut floats = QuantumColumn::with_type("Floats", ColumnType::F64);
+        for i in 0..10_000 {
+            let value = (i % 3 == 0) as u8 as f64;
+            flags.push(value);
+            floats.push(value);
+        }
+
+        assert_eq!(flags.column_type(), ColumnType::Bool);
+        assert_eq!(flags.sum(), 3334.0); // true counts as 1
+        assert_eq!(flags.count(), 10_000);
+        assert_eq!(flags.get(3), Some(1.0));
+        assert_eq!(flags.get(4), Some(0.0));
+        assert!(flags.memory_per_value() < 0.5, "{} bytes per value", flags.memory_per_value());
+        assert!(floats.memory_per_value() >= 8.0);
+    }
+
+    #[test]
+    fn test_columns_widen_without_losing_values() {
+        let mut column = QuantumColumn::new("Test");
+        column.push(1.0);
+        column.push(0.0);
+        assert_eq!(column.column_type(), ColumnType::Bool);
+
+        column.push(42.0);
+        assert_eq!(column.column_type(), ColumnType::I64);
+        column.set(1, 2.5);
+        assert_eq!(column.column_type(), ColumnType::F64);
+        assert_eq!(column.data(), [1.0, 2.5, 42.0].as_slice());
+
+        column.set_text(3, "n/a");
+        assert_eq!(column.column_type(), ColumnType::Text);
+        assert_eq!(column.text(2).as_deref(), Some("42"));
+        assert_eq!(column.text(3).as_deref(), Some("n/a"));
+        assert_eq!(column.get(3), None);
+        assert_eq!(column.count(), 3); // Text isn't counted
+        assert_eq!(column.sum(), 45.5);
+    }
+
+    #[test]
+    fn test_gaps_are_null_until_written() {
+        let mut column = QuantumColumn::new("test");
+        column.set(0, 10.0);
+        column.set(3, 20.0);
+        assert_eq!((column.null_count(), column.count()), (2, 2));
+        assert!(column.is_null(1) && !column.is_null(3));
+        assert_eq!(column.get(1), Some(0.0)); // Still reads as 0 in arithmetic
+        assert_eq!(column.average(), 15.0);
+
+        column.set(1, 0.0);
+        column.unset(3);
+        assert_eq!((column.len(), column.null_count(), column.count()), (2, 0, 2));
+        assert_eq!(column.average(), 5.0);
+
+        column.insert_gap(0);
+        assert!(column.is_null(0));
+        assert_eq!(column.entries().collect::<Vec<_>>(), [(1, 10.0), (2, 0.0)]);
+    }
+
+    #[test]
+    fn test_sparse_and_dense_read_the_same() {
+        let mut dense = QuantumColumn::new("Dense");
+        let mut sparse = QuantumColumn::new_sparse("Sparse");
+        for (row, value) in [(3, 5.0), (0, 2.0), (3, 7.0), (6, -1.0)] {
+            dense.set(row, value);
+            sparse.set(row, value);
+        }
+
+        assert_eq!(dense.storage_mode(), StorageMode::Dense);
+        assert_eq!(sparse.len(), dense.len());
+        assert_eq!(sparse.data(), dense.data());
+        assert_eq!(sparse.sum(), dense.sum());
+        assert_eq!(dense.count(), 3); // Only written rows
+        assert_eq!(sparse.count(), 3);
+        assert_eq!((dense.null_count(), sparse.null_count()), (4, 4));
+
+        sparse.set_storage_mode(StorageMode::Dense);
+        assert_eq!(sparse.data(), dense.data());
+        assert_eq!(sparse.count(), 3);
+        assert!(sparse.is_null(1) && !sparse.is_null(3));
+    }
+
+    #[test]
+    fn test_retain_positive_values() {
+        let values = [3.0, -1.0, 0.0, 4.5, -2.0, 2.0];
+        let mut dense = QuantumColumn::new("Dense");
+        let mut sparse = QuantumColumn::new_sparse("Sparse");
+        for column in [&mut dense, &mut sparse] {
+            for (row, value) in values.iter().enumerate() {
+                column.set(row, *value);
+            }
+            column.set(8, -5.0); // Rows 6 and 7 stay empty
+
+            column.retain(|value| value > 0.0);
+            assert_eq!(column.iter().collect::<Vec<_>>(), [3.0, 4.5, 2.0]);
+            assert_eq!(column.len(), 3); // Shifted up, gaps gone
+            assert_eq!(column.count(), 3);
+            assert_eq!(column.null_count(), 0);
+            assert_eq!(column.sum(), 9.5);
+            assert_eq!((column.min(), column.max()), (Some(2.0), Some(4.5)));
+        }
+
+        // Text that isn't a number is kept
+        let mut text = QuantumColumn::new("Text");
+        text.set(0, -1.0);
+        text.set_text(1, "north");
+        text.set(2, 7.0);
+        text.retain(|value| value > 0.0);
+        assert_eq!(text.text(0).as_deref(), Some("north"));
+        assert_eq!(text.get(1), Some(7.0));
+        assert_eq!(text.sum(), 7.0);
+    }
+
+    #[test]
+    fn test_stats_snapshot_tracks_edits() {
+        let mut column = QuantumColumn::new("Test");
+        for (row, value) in [4.0, 9.0, 1.5, 7.0].iter().enumerate() {
+            column.set(row, *value);
+        }
+        let rescan = |column: &QuantumColumn| {
+            crate::compute::calculate_stats(&column.entries().map(|(_, value)| value).collect::<Vec<_>>())
+        };
+        assert_eq!(column.stats_snapshot(), rescan(&column));
+
+        column.set(1, 2.0); // Overwrite the max
+        column.set(6, -3.0); // Rows 4 and 5 stay empty
+        assert_eq!(column.stats_snapshot(), rescan(&column));
+        assert_eq!(column.stats_snapshot().3, 7.0);
+
+        column.remove(0);
+        assert_eq!(column.stats_snapshot(), rescan(&column));
+        assert_eq!(QuantumColumn::new("Empty").stats_snapshot(), (0.0, 0.0, 0.0, 0.0, 0.0));
+    }
+}
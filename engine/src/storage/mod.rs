@@ -1,7 +1,9 @@
 //! Columnar storage system
 
+mod column_data;
 mod columnar;
-pub use columnar::QuantumColumn;
+pub use column_data::{BitVec, ColumnData, ColumnType};
+pub use columnar::{QuantumColumn, StorageMode, SPARSE_GAP_ROWS};
 
 /// Simple column statistics
 #[derive(Debug, Clone)]
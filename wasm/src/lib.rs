@@ -1,6 +1,67 @@
 use wasm_bindgen::prelude::*;
 use js_sys::Function;
 use quantum_engine::api::QuantumAPI;
+use quantum_engine::excel::{CellRange, CellRef};
+use quantum_engine::CellValue;
+use serde::Serialize;
+
+/// Most cells `get_range` returns; taller ranges are cut to whole rows that fit
+const MAX_RANGE_CELLS: u32 = 100_000;
+
+/// Error thrown to JavaScript as `{ code, message }`, so callers can branch on `code`
+#[derive(Serialize)]
+struct WasmError {
+    code: &'static str,
+    message: String,
+}
+
+/// One entry of `list_operations`
+#[derive(Serialize)]
+struct OperationEntry<'a> {
+    name: &'a str,
+    description: &'a str,
+    #[serde(rename = "type")]
+    op_type: &'static str,
+}
+
+/// One entry of `list_formulas`
+#[derive(Serialize)]
+struct FormulaEntry {
+    cell: String,
+    formula: String,
+}
+
+/// A cell for JavaScript: a number, a string, or null when empty
+#[derive(Serialize)]
+#[serde(untagged)]
+enum JsCell {
+    Number(f64),
+    Text(String),
+}
+
+impl JsCell {
+    fn from_value(value: CellValue) -> Option<Self> {
+        match value {
+            CellValue::Number(n) => Some(JsCell::Number(n)),
+            CellValue::Empty => None,
+            // Errors show as their text, e.g. "#DIV/0!"
+            other => Some(JsCell::Text(other.to_string())),
+        }
+    }
+}
+
+impl WasmError {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+
+    fn into_js(self) -> JsValue {
+        match serde_wasm_bindgen::to_value(&self) {
+            Ok(value) => value,
+            Err(_) => JsValue::from_str(&self.message),
+        }
+    }
+}
 
 // WASM Interface for Quantum Sheets
 #[wasm_bindgen]
@@ -14,42 +75,63 @@ impl QuantumSheetsWasm {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
         // Set up panic hook for better error messages in browser
-        // We'll add this dependency later if needed
-        // console_error_panic_hook::set_once();
+        console_error_panic_hook::set_once();
         
         Self {
             api: QuantumAPI::new(),
         }
     }
     
-    /// Execute a command (formula, natural language, or operation)
+    /// Execute a command (formula, natural language, or operation).
+    /// Throws `{ code, message }` with code FORMULA_ERROR for formulas, else COMMAND_ERROR.
     #[wasm_bindgen]
-    pub fn execute(&mut self, command: &str) -> Result<String, JsError> {
-        match self.api.execute(command) {
-            Ok(result) => Ok(result),
-            Err(e) => Err(JsError::new(&format!("Error: {}", e))),
-        }
+    pub fn execute(&mut self, command: &str) -> Result<String, JsValue> {
+        self.api.execute(command).map_err(|e| {
+            let code = if command.trim_start().starts_with('=') { "FORMULA_ERROR" } else { "COMMAND_ERROR" };
+            WasmError::new(code, e).into_js()
+        })
     }
     
     /// Generate data using natural language
     #[wasm_bindgen]
     pub fn generate_data(&mut self, request: &str, count: u32) -> Result<String, JsError> {
-        let full_request = format!("{} rows {}", count, request);
+        let full_request = format!("generate {} {}", count, request);
         match self.api.execute(&full_request) {
             Ok(result) => Ok(result),
             Err(e) => Err(JsError::new(&format!("Generation error: {}", e))),
         }
     }
     
-    /// Get cell value
+    /// Get cell value. Throws `{ code: "CELL_ERROR", message }` for a bad reference.
     #[wasm_bindgen]
-    pub fn get_cell(&self, cell_ref: &str) -> Result<String, JsError> {
+    pub fn get_cell(&self, cell_ref: &str) -> Result<String, JsValue> {
         match self.api.get_cell(cell_ref) {
             Ok(value) => Ok(value.to_string()),  // Convert f64 to String
-            Err(e) => Err(JsError::new(&format!("Cell error: {}", e))),
+            Err(e) => Err(WasmError::new("CELL_ERROR", e).into_js()),
         }
     }
     
+    /// Evaluated values of a range as rows of numbers, strings and nulls (empty cells),
+    /// e.g. for rendering a table. Ranges over 100,000 cells are cut to the rows that fit.
+    /// Throws `{ code: "CELL_ERROR", message }` for a bad range.
+    #[wasm_bindgen]
+    pub fn get_range(&self, range: &str) -> Result<JsValue, JsValue> {
+        let mut range = CellRange::parse(range).map_err(|e| WasmError::new("CELL_ERROR", e).into_js())?;
+        let max_rows = (MAX_RANGE_CELLS / range.width()).max(1);
+        if range.height() > max_rows {
+            range = CellRange::new(range.start, CellRef::new(range.start.row + max_rows - 1, range.end.col));
+        }
+
+        let rows: Vec<Vec<Option<JsCell>>> = self.api.get_range(&range.to_excel())
+            .map_err(|e| WasmError::new("CELL_ERROR", e).into_js())?
+            .into_iter()
+            .map(|row| row.into_iter().map(JsCell::from_value).collect())
+            .collect();
+        let serializer = serde_wasm_bindgen::Serializer::new().serialize_missing_as_null(true);
+        rows.serialize(&serializer)
+            .map_err(|e| WasmError::new("SERIALIZE_ERROR", e.to_string()).into_js())
+    }
+    
     /// Set cell value
     #[wasm_bindgen]
     pub fn set_cell(&mut self, cell_ref: &str, value: &str) -> Result<(), JsError> {
@@ -74,6 +156,61 @@ impl QuantumSheetsWasm {
         }
     }
     
+    /// Load CSV text (e.g. from a paste) and return [rows, cols] so the view can be sized.
+    /// `replace` clears the grid first; otherwise the CSV is merged over existing cells.
+    #[wasm_bindgen]
+    pub fn load_csv(&mut self, text: &str, replace: bool) -> Result<js_sys::Array, JsError> {
+        match self.api.load_csv(text, replace) {
+            Ok((rows, cols)) => {
+                let dimensions = js_sys::Array::new();
+                dimensions.push(&JsValue::from(rows));
+                dimensions.push(&JsValue::from(cols));
+                Ok(dimensions)
+            }
+            Err(e) => Err(JsError::new(&format!("CSV import error: {}", e))),
+        }
+    }
+    
+    /// Import CSV text (e.g. a dropped file) and return how many cells were filled.
+    /// `replace` clears the grid first; otherwise the CSV is merged over existing cells.
+    #[wasm_bindgen]
+    pub fn import_csv(&mut self, csv: &str, replace: bool) -> Result<u32, JsError> {
+        self.api.import_csv(csv, replace)
+            .map_err(|e| JsError::new(&format!("CSV import error: {}", e)))
+    }
+    
+    /// Run a newline-delimited script of commands in one call.
+    /// Returns one entry per non-blank line: the result string, or an `Error` if it failed.
+    #[wasm_bindgen]
+    pub fn execute_batch(&mut self, script: &str) -> js_sys::Array {
+        let commands: Vec<&str> = script.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        let results = js_sys::Array::new();
+        for result in self.api.execute_batch(&commands) {
+            match result {
+                Ok(output) => results.push(&JsValue::from(output)),
+                Err(e) => results.push(&js_sys::Error::new(&format!("Error: {}", e))),
+            };
+        }
+        results
+    }
+    
+    /// Call `callback` with an array of changed cell addresses (e.g. ["A1", "B1"])
+    /// after each edit, so the view can repaint only those cells
+    #[wasm_bindgen]
+    pub fn set_on_change(&mut self, callback: Function) {
+        self.api.set_on_change(move |cells| {
+            let addresses = js_sys::Array::new();
+            for cell in cells {
+                addresses.push(&JsValue::from(cell.to_excel()));
+            }
+            let _ = callback.call1(&JsValue::NULL, &addresses);
+        });
+    }
+    
     /// Register a custom operation
     #[wasm_bindgen]
     pub fn register_operation(
@@ -114,6 +251,26 @@ impl QuantumSheetsWasm {
             .map_err(|e| JsError::new(&format!("Operation registration error: {}", e)))
     }
     
+    /// Every operation as `{ name, description, type }`, sorted by name, e.g. for a function palette
+    #[wasm_bindgen]
+    pub fn list_operations(&self) -> Result<JsValue, JsValue> {
+        let entries: Vec<OperationEntry> = self.api.describe_operations().into_iter()
+            .map(|(name, description, op_type)| OperationEntry { name, description, op_type: op_type.to_str() })
+            .collect();
+        serde_wasm_bindgen::to_value(&entries)
+            .map_err(|e| WasmError::new("SERIALIZE_ERROR", e.to_string()).into_js())
+    }
+    
+    /// Every formula cell as `{ cell, formula }`, row by row, e.g. for an audit view
+    #[wasm_bindgen]
+    pub fn list_formulas(&self) -> Result<JsValue, JsValue> {
+        let entries: Vec<FormulaEntry> = self.api.list_formulas().into_iter()
+            .map(|(cell, formula)| FormulaEntry { cell, formula })
+            .collect();
+        serde_wasm_bindgen::to_value(&entries)
+            .map_err(|e| WasmError::new("SERIALIZE_ERROR", e.to_string()).into_js())
+    }
+    
     /// Evaluate a formula
     #[wasm_bindgen]
     pub fn evaluate_formula(&mut self, formula: &str) -> Result<String, JsError> {
@@ -141,13 +298,10 @@ impl QuantumSheetsWasm {
         }
     }
     
-    /// Export grid to JSON
+    /// Export every cell (address, typed value or text, formula) as JSON
     #[wasm_bindgen]
     pub fn export_json(&mut self) -> Result<String, JsError> {
-        match self.api.execute("export json") {
-            Ok(result) => Ok(result),
-            Err(e) => Err(JsError::new(&format!("Export error: {}", e))),
-        }
+        Ok(self.api.export_json())
     }
     
     /// Clear the grid
@@ -162,18 +316,15 @@ impl QuantumSheetsWasm {
     /// Get performance stats
     #[wasm_bindgen]
     pub fn get_stats(&mut self) -> Result<String, JsError> {
-        match self.api.execute("stats") {
-            Ok(result) => Ok(result),
-            Err(e) => Err(JsError::new(&format!("Stats error: {}", e))),
-        }
+        Ok(self.api.get_stats())
     }
 }
 
 // Helper function to initialize logging in browser
 #[wasm_bindgen(start)]
 pub fn start() {
-    // We'll add this later when we add the dependency
-    // console_error_panic_hook::set_once();
+    // Report Rust panics in the browser console instead of a bare `unreachable`
+    console_error_panic_hook::set_once();
 }
 
 // Re-export console.log functions
@@ -184,4 +335,117 @@ extern "C" {
     
     #[wasm_bindgen(js_namespace = console, js_name = log)]
     pub fn log_many(args: &js_sys::Array);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+    
+    #[wasm_bindgen_test]
+    fn test_load_csv_returns_dimensions() {
+        let mut sheets = QuantumSheetsWasm::new();
+        let dimensions = sheets.load_csv("id,score\n1,90\n2,85\n", true).unwrap();
+        
+        assert_eq!(dimensions.length(), 2);
+        assert_eq!(dimensions.get(0).as_f64(), Some(3.0));
+        assert_eq!(dimensions.get(1).as_f64(), Some(2.0));
+        assert_eq!(sheets.get_cell("B2").unwrap(), "90");
+    }
+    
+    #[wasm_bindgen_test]
+    fn test_import_csv_returns_cell_count() {
+        let mut sheets = QuantumSheetsWasm::new();
+        assert_eq!(sheets.import_csv("city,temp\nPune,31\n", false).unwrap(), 4);
+        assert_eq!(sheets.get_cell("B2").unwrap(), "31");
+    }
+    
+    #[wasm_bindgen_test]
+    fn test_get_range_returns_typed_values() {
+        let mut sheets = QuantumSheetsWasm::new();
+        sheets.load_csv("name,score\nSita,90\n", true).unwrap();
+        sheets.set_cell("C1", "1").unwrap();
+        
+        let rows = js_sys::Array::from(&sheets.get_range("A1:B2").unwrap());
+        assert_eq!(rows.length(), 2);
+        let row = js_sys::Array::from(&rows.get(1));
+        assert_eq!(row.get(0).as_string(), Some("Sita".to_string()));
+        assert_eq!(row.get(1).as_f64(), Some(90.0));
+        
+        let rows = js_sys::Array::from(&sheets.get_range("B1:C2").unwrap());
+        assert!(js_sys::Array::from(&rows.get(1)).get(1).is_null());
+        
+        // Clamped to the rows that fit
+        let rows = js_sys::Array::from(&sheets.get_range("A1:J1000000").unwrap());
+        assert_eq!(rows.length(), 10_000);
+    }
+    
+    #[wasm_bindgen_test]
+    fn test_execute_batch_reports_each_line() {
+        let mut sheets = QuantumSheetsWasm::new();
+        let results = sheets.execute_batch("SET A1 2\n\nbogus\n=A1*3\n");
+        
+        assert_eq!(results.length(), 3);
+        assert_eq!(results.get(0).as_string(), Some("A1 = 2".to_string()));
+        assert!(results.get(1).is_instance_of::<js_sys::Error>());
+        assert_eq!(results.get(2).as_string(), Some("6".to_string()));
+    }
+    
+    #[wasm_bindgen_test]
+    fn test_bad_formula_throws_structured_error() {
+        let mut sheets = QuantumSheetsWasm::new();
+        let error = sheets.execute("=A1+").unwrap_err();
+        
+        let code = js_sys::Reflect::get(&error, &JsValue::from_str("code")).unwrap();
+        let message = js_sys::Reflect::get(&error, &JsValue::from_str("message")).unwrap();
+        assert_eq!(code.as_string(), Some("FORMULA_ERROR".to_string()));
+        assert!(message.as_string().is_some_and(|m| !m.is_empty()));
+        
+        let error = sheets.get_cell("nope").unwrap_err();
+        let code = js_sys::Reflect::get(&error, &JsValue::from_str("code")).unwrap();
+        assert_eq!(code.as_string(), Some("CELL_ERROR".to_string()));
+    }
+    
+    #[wasm_bindgen_test]
+    fn test_list_formulas_returns_cells_and_sources() {
+        let mut sheets = QuantumSheetsWasm::new();
+        sheets.execute("SET A1 2").unwrap();
+        sheets.api.set_formula("B1", "=A1*3").unwrap();
+        sheets.api.set_formula("A2", "=A1+B1").unwrap();
+        
+        let entries = js_sys::Array::from(&sheets.list_formulas().unwrap());
+        let field = |index: u32, key: &str| {
+            js_sys::Reflect::get(&entries.get(index), &JsValue::from_str(key)).unwrap().as_string()
+        };
+        assert_eq!(entries.length(), 2);
+        assert_eq!((field(0, "cell"), field(0, "formula")), (Some("B1".to_string()), Some("=A1 * 3".to_string())));
+        assert_eq!((field(1, "cell"), field(1, "formula")), (Some("A2".to_string()), Some("=A1 + B1".to_string())));
+    }
+    
+    #[wasm_bindgen_test]
+    fn test_list_operations_includes_builtins() {
+        let sheets = QuantumSheetsWasm::new();
+        let operations = js_sys::Array::from(&sheets.list_operations().unwrap());
+        let field = |entry: &JsValue, key: &str| {
+            js_sys::Reflect::get(entry, &JsValue::from_str(key)).unwrap().as_string()
+        };
+        
+        for expected in ["SUM", "AVERAGE"] {
+            let entry = operations.iter()
+                .find(|entry| field(entry, "name").as_deref() == Some(expected))
+                .unwrap_or_else(|| panic!("{} not listed", expected));
+            assert_eq!(field(&entry, "type").as_deref(), Some("calculation"));
+            assert!(field(&entry, "description").is_some());
+        }
+    }
+    
+    #[wasm_bindgen_test]
+    fn test_export_json_lists_cells() {
+        let mut sheets = QuantumSheetsWasm::new();
+        sheets.set_cell("A1", "7").unwrap();
+        
+        let json = sheets.export_json().unwrap();
+        assert!(json.contains(r#""cell":"A1""#), "{}", json);
+        assert!(json.contains(r#""value":7.0"#), "{}", json);
+    }
+}